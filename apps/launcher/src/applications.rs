@@ -0,0 +1,130 @@
+use applications::{ApplicationIndex, DesktopEntry, LiveIndex};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+/// The installed applications, kept up to date by [`watch_applications`].
+/// Starts from the cache written on the last change, so the list is there
+/// before the directories have been read.
+pub struct ApplicationState(pub Mutex<LiveIndex>);
+
+impl Default for ApplicationState {
+    fn default() -> Self {
+        let index = ApplicationIndex::new();
+        let live = match cache_path() {
+            Some(path) if cfg!(target_os = "linux") => LiveIndex::with_cache(index, &path),
+            _ => LiveIndex::new(index),
+        };
+        Self(Mutex::new(live))
+    }
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("mrunner").join("applications.json"))
+}
+
+/// Re-reads the application directories on startup and then whenever
+/// inotify reports a change in them, saving the cache and emitting
+/// `applications-changed` with the new list when it differs. Runs for the
+/// lifetime of the app; does nothing outside Linux.
+pub fn watch_applications(app: tauri::AppHandle) {
+    if !cfg!(target_os = "linux") {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let state = app.state::<ApplicationState>();
+        let update = |refresh: &dyn Fn(&mut LiveIndex) -> bool| {
+            // Rescan a copy, so commands can read the list meanwhile. This
+            // thread is the only writer, so the copy can't miss a change.
+            let Ok(mut live) = state.0.lock().map(|live| live.clone()) else {
+                return;
+            };
+            if !refresh(&mut live) {
+                return;
+            }
+            if let Some(path) = cache_path() {
+                if let Err(e) = live.save_cache(&path) {
+                    log::warn!("Failed to save application cache: {}", e);
+                }
+            }
+            let applications = live.applications();
+            if let Ok(mut current) = state.0.lock() {
+                *current = live;
+            }
+            // New applications may have brought icons with them
+            app.state::<crate::icons::IconState>().refresh();
+            let _ = app.emit("applications-changed", applications);
+        };
+
+        update(&|live| live.refresh());
+
+        #[cfg(target_os = "linux")]
+        {
+            let dirs = state
+                .0
+                .lock()
+                .map(|live| live.index().dirs().to_vec())
+                .unwrap_or_default();
+            let mut watcher = match applications::DirectoryWatcher::new(&dirs) {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    log::warn!("Failed to watch application directories: {}", e);
+                    return;
+                }
+            };
+            loop {
+                match watcher.wait(std::time::Duration::from_secs(60)) {
+                    Ok(changed) if changed.is_empty() => {}
+                    Ok(changed) => update(&|live| live.refresh_paths(&changed)),
+                    Err(e) => {
+                        log::warn!("Stopped watching application directories: {}", e);
+                        return;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Installed applications from their `.desktop` files, sorted by name.
+/// Desktop entries only exist on Linux; elsewhere the list is empty.
+#[tauri::command]
+pub async fn list_applications(app: tauri::AppHandle) -> Result<Vec<DesktopEntry>, String> {
+    if !cfg!(target_os = "linux") {
+        return Ok(Vec::new());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<ApplicationState>();
+        let live = state.0.lock().map_err(|e| e.to_string())?;
+        Ok(live.applications())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Starts the application with desktop file ID `id`, or one of its
+/// `action`s, opening `targets` (files or URLs).
+#[tauri::command]
+pub async fn launch_application(
+    id: String,
+    action: Option<String>,
+    targets: Option<Vec<String>>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let entry = {
+            let state = app.state::<ApplicationState>();
+            let live = state.0.lock().map_err(|e| e.to_string())?;
+            live.find(&id)
+                .cloned()
+                .ok_or_else(|| applications::ApplicationError::NotFound(id.clone()).to_string())?
+        };
+        entry
+            .launch(action.as_deref(), &targets.unwrap_or_default())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
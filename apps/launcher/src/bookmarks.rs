@@ -1,5 +1,14 @@
-use bookmarks::{Bookmark, BookmarkError, BookmarkStore, Tag};
+use bookmarks::{
+    Bookmark, BookmarkError, BookmarkOrder, BookmarkPage, BookmarkSort, BookmarkStore,
+    DatabaseWatcher, Folder, GitSync, ImportReport, KeywordMatch, LinkChecker, LinkStatus,
+    MetadataFetcher, PageMetadata, PageRequest, SyncReport, Tag, TrashedBookmark, UndoEntry,
+};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager, State};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BookmarkResponse {
@@ -8,6 +17,8 @@ pub struct BookmarkResponse {
     pub title: String,
     pub tags: String,
     pub description: String,
+    pub keyword: Option<String>,
+    pub folder_id: Option<i32>,
 }
 
 impl From<Bookmark> for BookmarkResponse {
@@ -18,6 +29,60 @@ impl From<Bookmark> for BookmarkResponse {
             title: b.title,
             tags: b.tags.join(", "),
             description: b.description,
+            keyword: b.keyword,
+            folder_id: b.folder_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookmarkPageResponse {
+    pub bookmarks: Vec<BookmarkResponse>,
+    pub total: usize,
+    pub next_cursor: Option<String>,
+}
+
+impl From<BookmarkPage> for BookmarkPageResponse {
+    fn from(p: BookmarkPage) -> Self {
+        Self {
+            bookmarks: p
+                .bookmarks
+                .into_iter()
+                .map(BookmarkResponse::from)
+                .collect(),
+            total: p.total,
+            next_cursor: p.next_cursor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedBookmarkResponse {
+    #[serde(flatten)]
+    pub bookmark: BookmarkResponse,
+    pub deleted_at: i64,
+}
+
+impl From<TrashedBookmark> for TrashedBookmarkResponse {
+    fn from(t: TrashedBookmark) -> Self {
+        Self {
+            bookmark: BookmarkResponse::from(t.bookmark),
+            deleted_at: t.deleted_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeywordMatchResponse {
+    pub bookmark: BookmarkResponse,
+    pub url: String,
+}
+
+impl From<KeywordMatch> for KeywordMatchResponse {
+    fn from(m: KeywordMatch) -> Self {
+        Self {
+            bookmark: BookmarkResponse::from(m.bookmark),
+            url: m.url,
         }
     }
 }
@@ -26,11 +91,227 @@ fn map_error(e: BookmarkError) -> String {
     e.to_string()
 }
 
+/// Long-lived bookmark database shared by every command. The connection is
+/// not `Sync`, so calls are serialized through the mutex. It is opened on
+/// first use, so a broken database does not prevent the app from starting.
+#[derive(Default)]
+pub struct BookmarkState(pub Mutex<Option<BookmarkStore>>);
+
+impl BookmarkState {
+    pub fn with_store<T>(
+        &self,
+        f: impl FnOnce(&BookmarkStore) -> Result<T, BookmarkError>,
+    ) -> Result<T, String> {
+        let mut guard = self.0.lock().map_err(|e| e.to_string())?;
+        if guard.is_none() {
+            *guard = Some(open_store().map_err(map_error)?);
+        }
+
+        let store = guard.as_ref().ok_or("Bookmark database is not open")?;
+        f(store).map_err(map_error)
+    }
+}
+
+/// Opens the bookmark database selected in preferences: MRunner's own
+/// database by default, or buku's when `bookmarks.database` is `"buku"`.
+/// On Linux, `bookmarks.encrypted` keeps MRunner's database encrypted with
+/// a passphrase held in the keyring, encrypting an existing one on first use.
+fn open_store() -> Result<BookmarkStore, BookmarkError> {
+    let prefs = crate::preferences::load();
+    let bookmarks = prefs.as_ref().and_then(|prefs| prefs.get("bookmarks"));
+    let database = bookmarks
+        .and_then(|b| b.get("database"))
+        .and_then(|d| d.as_str());
+    let encrypted = bookmarks
+        .and_then(|b| b.get("encrypted"))
+        .and_then(|e| e.as_bool())
+        .unwrap_or(false);
+
+    match database {
+        Some("buku") => BookmarkStore::buku(),
+        #[cfg(target_os = "linux")]
+        _ if encrypted => {
+            let path = BookmarkStore::get_db_path()?;
+            let passphrase = bookmarks::Keyring::bookmarks().get_or_create(&path)?;
+            BookmarkStore::with_path_encrypted(path, &passphrase)
+        }
+        _ => {
+            if encrypted {
+                log::warn!("Encrypted bookmarks are only supported on Linux");
+            }
+            BookmarkStore::new()
+        }
+    }
+}
+
+/// How often the database files are checked for outside changes where
+/// they cannot be watched, and how soon a busy store is checked again.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long to wait for the watched files to change before checking
+/// whether the store was opened or reopened on another database.
+const IDLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Wakes the watch loop when the database files may have changed: through
+/// inotify on the database's directory on Linux, or else by polling every
+/// `WATCH_INTERVAL` and leaving the check to
+/// [`DatabaseWatcher::files_changed`].
+struct FileEvents {
+    #[cfg(target_os = "linux")]
+    watcher: Option<applications::DirectoryWatcher>,
+}
+
+impl FileEvents {
+    fn new(database: &Path) -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            let dirs = database
+                .parent()
+                .map(|dir| vec![dir.to_path_buf()])
+                .unwrap_or_default();
+            let watcher = match applications::DirectoryWatcher::new(&dirs) {
+                Ok(watcher) => Some(watcher),
+                Err(e) => {
+                    log::warn!(
+                        "Failed to watch the bookmark database, polling instead: {}",
+                        e
+                    );
+                    None
+                }
+            };
+            Self { watcher }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = database;
+            Self {}
+        }
+    }
+
+    /// Waits up to `timeout` and returns whether the files may have changed.
+    fn wait(&mut self, timeout: Duration) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Some(watcher) = self.watcher.as_mut() {
+            match watcher.wait(timeout) {
+                Ok(changed) => return !changed.is_empty(),
+                Err(e) => {
+                    log::warn!(
+                        "Stopped watching the bookmark database, polling instead: {}",
+                        e
+                    );
+                    self.watcher = None;
+                }
+            }
+        }
+
+        std::thread::sleep(timeout.min(WATCH_INTERVAL));
+        true
+    }
+}
+
+/// Emits `bookmarks-changed` with a `BookmarkChanges` whenever another
+/// program (buku, a sync job, a second MRunner window) commits to the open
+/// bookmark database. Runs for the lifetime of the app.
+pub fn watch_database(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let state = app.state::<BookmarkState>();
+        let mut watcher: Option<(DatabaseWatcher, FileEvents)> = None;
+        let mut pending = false;
+
+        loop {
+            match watcher.as_mut() {
+                Some((w, events)) => {
+                    let timeout = if pending {
+                        WATCH_INTERVAL
+                    } else {
+                        IDLE_INTERVAL
+                    };
+                    if events.wait(timeout) {
+                        pending |= w.files_changed();
+                    }
+                }
+                None => std::thread::sleep(WATCH_INTERVAL),
+            }
+
+            // Never hold up a command; a busy store is checked next time
+            let Ok(guard) = state.0.try_lock() else {
+                continue;
+            };
+            let Some(store) = guard.as_ref() else {
+                watcher = None;
+                continue;
+            };
+
+            match watcher.as_mut() {
+                Some((w, _)) if Some(w.path()) == store.path() => {
+                    if !pending {
+                        continue;
+                    }
+                    pending = false;
+                    match w.changes(store) {
+                        Ok(Some(changes)) => {
+                            let _ = app.emit("bookmarks-changed", &changes);
+                        }
+                        Ok(None) => {}
+                        Err(e) => log::warn!("Failed to read bookmark changes: {}", e),
+                    }
+                }
+                // First use, or the store was reopened on another database
+                _ => {
+                    watcher = DatabaseWatcher::new(store).ok().map(|w| {
+                        let events = FileEvents::new(w.path());
+                        (w, events)
+                    });
+                    pending = false;
+                }
+            }
+        }
+    });
+}
+
+fn parse_tags(tags: String) -> Vec<String> {
+    tags.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Drops the open database so the next command reopens it, e.g. after the
+/// `bookmarks.database` preference changed.
 #[tauri::command]
-pub fn bookmark_list(limit: Option<i32>) -> Result<Vec<BookmarkResponse>, String> {
-    let store = BookmarkStore::new().map_err(map_error)?;
-    let bookmarks = store.list(limit.map(|n| n as u32)).map_err(map_error)?;
-    Ok(bookmarks.into_iter().map(BookmarkResponse::from).collect())
+pub fn bookmark_reload_store(state: State<'_, BookmarkState>) -> Result<(), String> {
+    state.0.lock().map_err(|e| e.to_string())?.take();
+    Ok(())
+}
+
+/// `page` when given, otherwise the first page for the older `order` and
+/// `limit` arguments.
+fn page_request(
+    page: Option<PageRequest>,
+    order: Option<BookmarkOrder>,
+    limit: Option<i32>,
+) -> PageRequest {
+    page.unwrap_or_else(|| PageRequest {
+        sort: match order.unwrap_or_default() {
+            BookmarkOrder::Default => BookmarkSort::Relevance,
+            BookmarkOrder::Frecency => BookmarkSort::Frecency,
+        },
+        limit: limit.map(|n| n.max(0) as u32),
+        ..Default::default()
+    })
+}
+
+#[tauri::command]
+pub fn bookmark_list(
+    limit: Option<i32>,
+    order: Option<BookmarkOrder>,
+    page: Option<PageRequest>,
+    state: State<'_, BookmarkState>,
+) -> Result<BookmarkPageResponse, String> {
+    let page = page_request(page, order, limit);
+    let result = state.with_store(|store| store.list_page(&page))?;
+    Ok(BookmarkPageResponse::from(result))
 }
 
 #[tauri::command]
@@ -38,63 +319,159 @@ pub fn bookmark_search(
     query: String,
     #[allow(non_snake_case)] tagFilter: Option<String>,
     #[allow(non_snake_case)] tagOr: Option<bool>,
+    order: Option<BookmarkOrder>,
+    folder_id: Option<i32>,
+    page: Option<PageRequest>,
+    state: State<'_, BookmarkState>,
+) -> Result<BookmarkPageResponse, String> {
+    let tags: Option<Vec<String>> = tagFilter.map(parse_tags);
+    let tag_or = tagOr.unwrap_or(false);
+    let page = page_request(page, order, None);
+
+    let result = state
+        .with_store(|store| store.search_page(&query, tags.as_deref(), tag_or, folder_id, &page))?;
+    Ok(BookmarkPageResponse::from(result))
+}
+
+/// Bookmarks directly in a folder (the top level when `folder_id` is
+/// omitted) in manual order, or everything below it with `recursive`.
+#[tauri::command]
+pub fn bookmark_list_folder(
+    folder_id: Option<i32>,
+    recursive: Option<bool>,
+    state: State<'_, BookmarkState>,
 ) -> Result<Vec<BookmarkResponse>, String> {
-    let store = BookmarkStore::new().map_err(map_error)?;
+    let bookmarks =
+        state.with_store(|store| store.list_folder(folder_id, recursive.unwrap_or(false)))?;
+    Ok(bookmarks.into_iter().map(BookmarkResponse::from).collect())
+}
 
-    let tags: Option<Vec<String>> = tagFilter.map(|t| {
-        t.split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect()
-    });
+#[tauri::command]
+pub fn bookmark_list_folders(state: State<'_, BookmarkState>) -> Result<Vec<Folder>, String> {
+    state.with_store(|store| store.list_folders())
+}
 
-    let bookmarks = store
-        .search(&query, tags.as_deref(), tagOr.unwrap_or(false))
-        .map_err(map_error)?;
+#[tauri::command]
+pub fn bookmark_create_folder(
+    name: String,
+    parent_id: Option<i32>,
+    state: State<'_, BookmarkState>,
+) -> Result<i32, String> {
+    state.with_store(|store| store.create_folder(&name, parent_id))
+}
 
-    Ok(bookmarks.into_iter().map(BookmarkResponse::from).collect())
+#[tauri::command]
+pub fn bookmark_rename_folder(
+    id: i32,
+    name: String,
+    state: State<'_, BookmarkState>,
+) -> Result<(), String> {
+    state.with_store(|store| store.rename_folder(id, &name))
+}
+
+/// Moves a folder into `parent_id` (the top level when omitted) at
+/// `position`, or at the end.
+#[tauri::command]
+pub fn bookmark_move_folder(
+    id: i32,
+    parent_id: Option<i32>,
+    position: Option<usize>,
+    state: State<'_, BookmarkState>,
+) -> Result<(), String> {
+    state.with_store(|store| store.move_folder(id, parent_id, position))
+}
+
+#[tauri::command]
+pub fn bookmark_delete_folder(id: i32, state: State<'_, BookmarkState>) -> Result<(), String> {
+    state.with_store(|store| store.delete_folder(id))
+}
+
+/// Moves a bookmark into `folder_id` (the top level when omitted) at
+/// `position`, or at the end.
+#[tauri::command]
+pub fn bookmark_move(
+    id: i32,
+    folder_id: Option<i32>,
+    position: Option<usize>,
+    state: State<'_, BookmarkState>,
+) -> Result<(), String> {
+    state.with_store(|store| store.move_bookmark(id, folder_id, position))
 }
 
 #[tauri::command]
-pub fn bookmark_get_by_id(id: i32) -> Result<Option<BookmarkResponse>, String> {
-    let store = BookmarkStore::new().map_err(map_error)?;
-    let bookmark = store.get(id).map_err(map_error)?;
+pub fn bookmark_get_by_id(
+    id: i32,
+    state: State<'_, BookmarkState>,
+) -> Result<Option<BookmarkResponse>, String> {
+    let bookmark = state.with_store(|store| store.get(id))?;
     Ok(bookmark.map(BookmarkResponse::from))
 }
 
 #[tauri::command]
-pub fn bookmark_open(id: i32) -> Result<(), String> {
-    let store = BookmarkStore::new().map_err(map_error)?;
-    store.open(id).map_err(map_error)
+pub fn bookmark_open(id: i32, state: State<'_, BookmarkState>) -> Result<(), String> {
+    state.with_store(|store| store.open(id))
 }
 
+/// Adds a bookmark. Unless `fetch_metadata` is `false`, a blank title or
+/// description is filled from the page and its favicon cached; a page that
+/// cannot be fetched is still bookmarked.
 #[tauri::command]
-pub fn bookmark_add(
+pub async fn bookmark_add(
     url: String,
     title: Option<String>,
     tags: Option<String>,
     description: Option<String>,
+    keyword: Option<String>,
+    fetch_metadata: Option<bool>,
+    state: State<'_, BookmarkState>,
 ) -> Result<(), String> {
-    let store = BookmarkStore::new().map_err(map_error)?;
-
-    let tags_vec: Option<Vec<String>> = tags.map(|t| {
-        t.split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect()
-    });
-
-    let input = bookmarks::BookmarkInput {
+    let mut input = bookmarks::BookmarkInput {
         url,
         title,
-        tags: tags_vec,
+        tags: tags.map(parse_tags),
         description,
+        keyword: keyword.filter(|k| !k.trim().is_empty()),
+        ..Default::default()
     };
 
-    store.add(input).map_err(map_error)?;
+    // A URL that is already bookmarked fails below, so the page is only
+    // fetched for new ones
+    let exists = state
+        .with_store(|store| store.find_by_url(&input.url, None))?
+        .is_some();
+
+    if fetch_metadata.unwrap_or(true) && !exists {
+        input = tauri::async_runtime::spawn_blocking(move || {
+            if let Ok(fetcher) = MetadataFetcher::new() {
+                let _ = fetcher.fill(&mut input);
+            }
+            input
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    state.with_store(|store| store.add(input))?;
     Ok(())
 }
 
+#[tauri::command]
+pub async fn bookmark_fetch_metadata(url: String) -> Result<PageMetadata, String> {
+    tauri::async_runtime::spawn_blocking(move || MetadataFetcher::new()?.fetch(&url))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(map_error)
+}
+
+/// Path of the cached favicon for `url`'s site, if one was fetched.
+#[tauri::command]
+pub fn bookmark_favicon(url: String) -> Result<Option<String>, String> {
+    let fetcher = MetadataFetcher::new().map_err(map_error)?;
+    Ok(fetcher
+        .cached_favicon(&url)
+        .map(|path| path.to_string_lossy().into_owned()))
+}
+
 #[tauri::command]
 pub fn bookmark_update(
     id: i32,
@@ -102,47 +479,310 @@ pub fn bookmark_update(
     title: Option<String>,
     tags: Option<String>,
     description: Option<String>,
+    keyword: Option<String>,
+    state: State<'_, BookmarkState>,
 ) -> Result<(), String> {
-    let store = BookmarkStore::new().map_err(map_error)?;
+    let tags_vec: Option<Vec<String>> = tags.map(parse_tags);
 
-    let tags_vec: Option<Vec<String>> = tags.map(|t| {
-        t.split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect()
-    });
+    // An empty keyword clears it
+    let keyword = keyword.as_deref().map(str::trim);
+    let keyword = keyword.map(|k| Some(k).filter(|k| !k.is_empty()));
 
-    store
-        .update(
+    state.with_store(|store| {
+        store.update_with_keyword(
             id,
             url.as_deref(),
             title.as_deref(),
             tags_vec.as_deref(),
             description.as_deref(),
+            keyword,
         )
-        .map_err(map_error)
+    })
+}
+
+/// Resolves launcher input whose first word is a bookmark keyword, e.g.
+/// `drs serde`, to the URL it would open.
+#[tauri::command]
+pub fn bookmark_resolve_keyword(
+    query: String,
+    state: State<'_, BookmarkState>,
+) -> Result<Option<KeywordMatchResponse>, String> {
+    let resolved = state.with_store(|store| store.resolve_keyword(&query))?;
+    Ok(resolved.map(KeywordMatchResponse::from))
+}
+
+/// Opens the URL a keyword query resolves to. Returns `false` if the first
+/// word is not a keyword.
+#[tauri::command]
+pub fn bookmark_open_keyword(
+    query: String,
+    state: State<'_, BookmarkState>,
+) -> Result<bool, String> {
+    let opened = state.with_store(|store| store.open_keyword(&query))?;
+    Ok(opened.is_some())
+}
+
+#[tauri::command]
+pub fn bookmark_delete(id: i32, state: State<'_, BookmarkState>) -> Result<(), String> {
+    state.with_store(|store| store.delete(id))
+}
+
+#[tauri::command]
+pub fn bookmark_list_trash(
+    state: State<'_, BookmarkState>,
+) -> Result<Vec<TrashedBookmarkResponse>, String> {
+    let trash = state.with_store(|store| store.list_trash())?;
+    Ok(trash
+        .into_iter()
+        .map(TrashedBookmarkResponse::from)
+        .collect())
+}
+
+#[tauri::command]
+pub fn bookmark_restore(
+    id: i32,
+    state: State<'_, BookmarkState>,
+) -> Result<BookmarkResponse, String> {
+    let bookmark = state.with_store(|store| store.restore(id))?;
+    Ok(BookmarkResponse::from(bookmark))
+}
+
+/// Permanently removes bookmarks trashed at least `older_than_days` ago, or
+/// everything in the trash when omitted.
+#[tauri::command]
+pub fn bookmark_purge_trash(
+    older_than_days: Option<u32>,
+    state: State<'_, BookmarkState>,
+) -> Result<usize, String> {
+    let age = Duration::from_secs(u64::from(older_than_days.unwrap_or(0)) * 24 * 60 * 60);
+    state.with_store(|store| store.purge_older_than(age))
+}
+
+#[tauri::command]
+pub fn bookmark_undo_history(state: State<'_, BookmarkState>) -> Result<Vec<UndoEntry>, String> {
+    state.with_store(|store| store.undo_history())
+}
+
+#[tauri::command]
+pub fn bookmark_undo(state: State<'_, BookmarkState>) -> Result<Option<UndoEntry>, String> {
+    state.with_store(|store| store.undo())
+}
+
+#[tauri::command]
+pub fn bookmark_find_duplicates(
+    state: State<'_, BookmarkState>,
+) -> Result<Vec<Vec<BookmarkResponse>>, String> {
+    let groups = state.with_store(|store| store.find_duplicates())?;
+    Ok(groups
+        .into_iter()
+        .map(|group| group.into_iter().map(BookmarkResponse::from).collect())
+        .collect())
+}
+
+#[tauri::command]
+pub fn bookmark_merge(
+    keep: i32,
+    others: Vec<i32>,
+    state: State<'_, BookmarkState>,
+) -> Result<BookmarkResponse, String> {
+    let merged = state.with_store(|store| store.merge(keep, &others))?;
+    Ok(BookmarkResponse::from(merged))
+}
+
+#[tauri::command]
+pub fn bookmark_list_tags(state: State<'_, BookmarkState>) -> Result<Vec<Tag>, String> {
+    state.with_store(|store| store.list_tags())
+}
+
+#[tauri::command]
+pub fn bookmark_rename_tag(
+    old_tag: String,
+    new_tag: String,
+    state: State<'_, BookmarkState>,
+) -> Result<(), String> {
+    state.with_store(|store| store.rename_tag(&old_tag, &new_tag))
+}
+
+#[tauri::command]
+pub fn bookmark_merge_tags(
+    sources: String,
+    target: String,
+    state: State<'_, BookmarkState>,
+) -> Result<(), String> {
+    let sources = parse_tags(sources);
+    state.with_store(|store| store.merge_tags(&sources, target.trim()))
+}
+
+#[tauri::command]
+pub fn bookmark_delete_tag(tag: String, state: State<'_, BookmarkState>) -> Result<(), String> {
+    state.with_store(|store| store.delete_tag(&tag))
+}
+
+#[tauri::command]
+pub fn bookmark_import_html(
+    path: String,
+    state: State<'_, BookmarkState>,
+) -> Result<ImportReport, String> {
+    state.with_store(|store| store.import_netscape(Path::new(&path)))
+}
+
+#[tauri::command]
+pub fn bookmark_export_html(
+    path: String,
+    state: State<'_, BookmarkState>,
+) -> Result<usize, String> {
+    state.with_store(|store| store.export_netscape(Path::new(&path)))
 }
 
+/// Set while a link check runs, so a second one is refused instead of
+/// probing every link twice.
+static LINK_CHECK_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Checks bookmark links in the background, skipping those checked within
+/// `max_age_days`. Emits `bookmark-link-check-progress` after each link and
+/// `bookmark-link-check-done` with the number checked. Returns the number of
+/// links queued. Fails while another check is running.
 #[tauri::command]
-pub fn bookmark_delete(id: i32) -> Result<(), String> {
-    let store = BookmarkStore::new().map_err(map_error)?;
-    store.delete(id).map_err(map_error)
+pub fn bookmark_check_links(
+    max_age_days: Option<u32>,
+    app: tauri::AppHandle,
+    state: State<'_, BookmarkState>,
+) -> Result<usize, String> {
+    if LINK_CHECK_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("A link check is already running".to_string());
+    }
+
+    let max_age = max_age_days.map(|days| Duration::from_secs(u64::from(days) * 24 * 60 * 60));
+    let total = match state.with_store(|store| store.links_to_check(max_age)) {
+        Ok(targets) => targets.len(),
+        Err(e) => {
+            LINK_CHECK_RUNNING.store(false, Ordering::SeqCst);
+            return Err(e);
+        }
+    };
+
+    // The check runs on its own connection, so the shared store stays free
+    // for other commands while requests are in flight
+    std::thread::spawn(move || {
+        let mut checked = 0;
+        let result = open_store().and_then(|store| {
+            store.check_links(&LinkChecker::new(), max_age, |progress| {
+                checked = progress.checked;
+                let _ = app.emit("bookmark-link-check-progress", progress);
+            })
+        });
+        LINK_CHECK_RUNNING.store(false, Ordering::SeqCst);
+        if let Err(e) = result {
+            log::warn!("Link check failed: {}", e);
+        }
+        let _ = app.emit("bookmark-link-check-done", checked);
+    });
+
+    Ok(total)
+}
+
+/// Validates a sync remote, which comes from the renderer or the
+/// renderer-writable preferences, before it reaches git. Only network
+/// remotes are allowed: `https://`, `ssh://` or scp-like `user@host:path`.
+/// Local paths and `file://`, which read other repositories on this machine,
+/// and `<transport>::` helpers such as `ext::`, which run commands, are
+/// rejected, as is a leading `-` (argument injection).
+fn validate_sync_remote(remote: &str) -> Result<(), String> {
+    let remote = remote.trim();
+    if remote.starts_with('-') {
+        return Err("Invalid sync remote: must not start with '-'".to_string());
+    }
+
+    let lower = remote.to_ascii_lowercase();
+    let url = ["https://", "ssh://"].iter().any(|s| lower.starts_with(s));
+    let scp = match remote.split_once(':') {
+        Some((host, _)) => host.contains('@') && !host.contains('/') && !remote.contains("::"),
+        None => false,
+    };
+    if !url && !scp {
+        return Err(
+            "Invalid sync remote: only https, ssh and user@host:path remotes are allowed"
+                .to_string(),
+        );
+    }
+    Ok(())
 }
 
+/// Set while a sync runs, so two syncs never share the working copy.
+static SYNC_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Syncs bookmarks through the git repository at `remote`, or the one set
+/// as `bookmarks.sync_remote` in preferences. Fails while another sync is
+/// running.
 #[tauri::command]
-pub fn bookmark_list_tags() -> Result<Vec<Tag>, String> {
-    let store = BookmarkStore::new().map_err(map_error)?;
-    store.list_tags().map_err(map_error)
+pub async fn bookmark_sync(remote: Option<String>) -> Result<SyncReport, String> {
+    let remote = remote.or_else(|| {
+        crate::preferences::load().and_then(|prefs| {
+            prefs
+                .get("bookmarks")?
+                .get("sync_remote")?
+                .as_str()
+                .map(str::to_string)
+        })
+    });
+    if let Some(remote) = &remote {
+        validate_sync_remote(remote)?;
+    }
+    let sync = GitSync::in_data_dir(remote).map_err(map_error)?;
+
+    if SYNC_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("A sync is already running".to_string());
+    }
+
+    // The sync runs on its own connection, so the shared store stays free
+    // for other commands while git talks to the remote
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        open_store()
+            .and_then(|store| sync.sync(&store))
+            .map_err(map_error)
+    })
+    .await;
+    SYNC_RUNNING.store(false, Ordering::SeqCst);
+    result.map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
-pub fn bookmark_rename_tag(old_tag: String, new_tag: String) -> Result<(), String> {
-    let store = BookmarkStore::new().map_err(map_error)?;
-    store.rename_tag(&old_tag, &new_tag).map_err(map_error)
+pub fn bookmark_link_problems(state: State<'_, BookmarkState>) -> Result<Vec<LinkStatus>, String> {
+    state.with_store(|store| store.link_problems())
 }
 
 #[tauri::command]
-pub fn bookmark_delete_tag(tag: String) -> Result<(), String> {
-    let store = BookmarkStore::new().map_err(map_error)?;
-    store.delete_tag(&tag).map_err(map_error)
+pub fn bookmark_fix_redirect(
+    id: i32,
+    state: State<'_, BookmarkState>,
+) -> Result<BookmarkResponse, String> {
+    let bookmark = state.with_store(|store| store.fix_redirect(id))?;
+    Ok(BookmarkResponse::from(bookmark))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_remote_accepts_network_remotes() {
+        assert!(validate_sync_remote("https://github.com/user/bookmarks.git").is_ok());
+        assert!(validate_sync_remote("ssh://git@github.com/user/bookmarks.git").is_ok());
+        assert!(validate_sync_remote("git@github.com:user/bookmarks.git").is_ok());
+        assert!(validate_sync_remote("  HTTPS://example.com/bookmarks  ").is_ok());
+    }
+
+    #[test]
+    fn sync_remote_rejects_options_and_local_or_command_transports() {
+        assert!(validate_sync_remote("--upload-pack=touch /tmp/x").is_err());
+        assert!(validate_sync_remote("  -oProxyCommand=x").is_err());
+        assert!(validate_sync_remote("ext::sh -c whoami").is_err());
+        assert!(validate_sync_remote("user@ext::sh -c whoami").is_err());
+        assert!(validate_sync_remote("fd::3").is_err());
+        assert!(validate_sync_remote("file:///home/user/bookmarks.git").is_err());
+        assert!(validate_sync_remote("FILE:///etc").is_err());
+        assert!(validate_sync_remote("/home/user/bookmarks.git").is_err());
+        assert!(validate_sync_remote("../bookmarks.git").is_err());
+        assert!(validate_sync_remote("http://example.com/bookmarks.git").is_err());
+    }
 }
@@ -1,6 +1,9 @@
+use crate::bookmarks::BookmarkState;
 use crate::platform;
+use bookmarks::ImportReport;
 use serde::Serialize;
 use std::fs;
+use std::path::Component;
 
 #[derive(Debug, Serialize)]
 pub struct ChromeProfile {
@@ -44,3 +47,32 @@ pub fn list_chrome_profiles() -> Result<Vec<ChromeProfile>, String> {
 
     Ok(profiles)
 }
+
+/// Imports the bookmarks of a profile returned by `list_chrome_profiles`.
+#[tauri::command]
+pub fn import_chrome_bookmarks(
+    directory: String,
+    state: tauri::State<'_, BookmarkState>,
+) -> Result<ImportReport, String> {
+    let chrome_dir =
+        platform::get_chrome_config_dir().ok_or("Could not determine Chrome config directory")?;
+
+    // Only accept a plain profile directory name such as "Default" or "Profile 1"
+    let mut components = std::path::Path::new(&directory).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        return Err(format!("Invalid Chrome profile directory: {}", directory));
+    }
+
+    let bookmarks_file = chrome_dir.join(&directory).join("Bookmarks");
+    if !bookmarks_file.exists() {
+        return Err(format!(
+            "No bookmarks found for Chrome profile '{}'",
+            directory
+        ));
+    }
+
+    state.with_store(|store| store.import_chrome(&bookmarks_file))
+}
@@ -0,0 +1,169 @@
+use crate::bookmarks::BookmarkState;
+use crate::platform;
+use bookmarks::ImportReport;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+#[derive(Debug, Serialize)]
+pub struct FirefoxProfile {
+    pub name: String,
+    /// Absolute path of the profile directory.
+    pub path: String,
+    pub is_default: bool,
+}
+
+/// Parses `profiles.ini`. Paths marked `IsRelative=1` are resolved against
+/// `firefox_dir`. A profile is the default when an `[Install...]` section
+/// points at it, or, on older installs, when it has `Default=1`.
+fn parse_profiles_ini(content: &str, firefox_dir: &Path) -> Vec<FirefoxProfile> {
+    let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.to_string(), Vec::new()));
+        } else if let (Some((key, value)), Some((_, entries))) =
+            (line.split_once('='), sections.last_mut())
+        {
+            entries.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let get = |entries: &[(String, String)], key: &str| {
+        entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    };
+
+    let install_defaults: Vec<String> = sections
+        .iter()
+        .filter(|(name, _)| name.starts_with("Install"))
+        .filter_map(|(_, entries)| get(entries, "Default"))
+        .collect();
+
+    let mut profiles: Vec<FirefoxProfile> = sections
+        .iter()
+        .filter(|(name, _)| name.starts_with("Profile"))
+        .filter_map(|(_, entries)| {
+            let raw_path = get(entries, "Path")?;
+            let path: PathBuf = if get(entries, "IsRelative").as_deref() == Some("1") {
+                firefox_dir.join(&raw_path)
+            } else {
+                PathBuf::from(&raw_path)
+            };
+
+            let is_default = if install_defaults.is_empty() {
+                get(entries, "Default").as_deref() == Some("1")
+            } else {
+                install_defaults.contains(&raw_path)
+            };
+
+            Some(FirefoxProfile {
+                name: get(entries, "Name").unwrap_or_else(|| raw_path.clone()),
+                path: path.to_string_lossy().to_string(),
+                is_default,
+            })
+        })
+        .collect();
+
+    profiles.sort_by(|a, b| b.is_default.cmp(&a.is_default).then(a.name.cmp(&b.name)));
+    profiles
+}
+
+#[tauri::command]
+pub fn list_firefox_profiles() -> Result<Vec<FirefoxProfile>, String> {
+    let firefox_dir =
+        platform::get_firefox_config_dir().ok_or("Could not determine Firefox config directory")?;
+
+    let profiles_ini = firefox_dir.join("profiles.ini");
+    if !profiles_ini.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&profiles_ini).map_err(|e| e.to_string())?;
+    Ok(parse_profiles_ini(&content, &firefox_dir))
+}
+
+/// Imports bookmarks and their history from a profile returned by
+/// `list_firefox_profiles`. Works while Firefox is running.
+#[tauri::command]
+pub async fn import_firefox_bookmarks(
+    path: String,
+    app: tauri::AppHandle,
+) -> Result<ImportReport, String> {
+    // Only accept a profile listed in profiles.ini, not any directory. Its
+    // path may have several components, e.g. `Profiles/abcd.default` on
+    // Windows, so it is compared whole rather than checked as a name
+    if !list_firefox_profiles()?
+        .iter()
+        .any(|profile| profile.path == path)
+    {
+        return Err(format!("Unknown Firefox profile: {}", path));
+    }
+
+    let places = Path::new(&path).join("places.sqlite");
+    if !places.exists() {
+        return Err(format!(
+            "No places.sqlite found in Firefox profile '{}'",
+            path
+        ));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<BookmarkState>()
+            .with_store(|store| store.import_firefox(&places))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profiles_ini() {
+        let ini = "\
+[Install4F96D1932A9F858E]
+Default=abcd.default-release
+Locked=1
+
+[Profile1]
+Name=default
+IsRelative=1
+Path=efgh.default
+Default=1
+
+[Profile0]
+Name=default-release
+IsRelative=1
+Path=abcd.default-release
+
+[Profile2]
+Name=work
+IsRelative=0
+Path=/srv/firefox/work
+
+[General]
+StartWithLastProfile=1
+Version=2
+";
+        let dir = Path::new("/home/user/.mozilla/firefox");
+        let profiles = parse_profiles_ini(ini, dir);
+
+        let names: Vec<&str> = profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["default-release", "default", "work"]);
+        assert!(profiles[0].is_default);
+        assert!(!profiles[1].is_default);
+        assert_eq!(
+            profiles[0].path,
+            dir.join("abcd.default-release").to_string_lossy()
+        );
+        assert_eq!(profiles[2].path, "/srv/firefox/work");
+    }
+}
@@ -0,0 +1,53 @@
+use history::{Boost, HistoryError, LaunchHistory, LaunchKind};
+use std::sync::Mutex;
+use tauri::State;
+
+/// Launch history shared by every command. Opened on first use, like the
+/// bookmark database, so a broken file does not prevent the app from starting.
+#[derive(Default)]
+pub struct HistoryState(pub Mutex<Option<LaunchHistory>>);
+
+impl HistoryState {
+    pub fn with_history<T>(
+        &self,
+        f: impl FnOnce(&mut LaunchHistory) -> Result<T, HistoryError>,
+    ) -> Result<T, String> {
+        let mut guard = self.0.lock().map_err(|e| e.to_string())?;
+        if guard.is_none() {
+            *guard = Some(LaunchHistory::new().map_err(|e| e.to_string())?);
+        }
+
+        let history = guard.as_mut().ok_or("Launch history is not open")?;
+        f(history).map_err(|e| e.to_string())
+    }
+}
+
+/// Records that the result `command_id` of `kind` was launched after typing
+/// `query`.
+#[tauri::command]
+pub fn record_launch(
+    command_id: String,
+    kind: LaunchKind,
+    query: Option<String>,
+    state: State<'_, HistoryState>,
+) -> Result<(), String> {
+    state.with_history(|history| {
+        history.record(&command_id, kind, query.as_deref().unwrap_or_default())
+    })
+}
+
+/// Frecency boosts, best first and in `0.0..=1.0`, for results launched
+/// after typing `query` or a prefix or extension of it.
+#[tauri::command]
+pub fn launch_boosts(
+    query: Option<String>,
+    state: State<'_, HistoryState>,
+) -> Result<Vec<Boost>, String> {
+    state.with_history(|history| history.boosts(query.as_deref().unwrap_or_default()))
+}
+
+/// Forgets every recorded launch.
+#[tauri::command]
+pub fn clear_launch_history(state: State<'_, HistoryState>) -> Result<(), String> {
+    state.with_history(|history| history.clear())
+}
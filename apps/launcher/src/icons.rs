@@ -0,0 +1,93 @@
+use applications::{IconCache, IconResolver};
+use percent_encoding::percent_decode_str;
+use std::path::PathBuf;
+use tauri::http::{header, HeaderValue, Request, Response, StatusCode};
+use tauri::Manager;
+
+/// Size in logical pixels when a request doesn't give one.
+const DEFAULT_SIZE: u32 = 32;
+
+/// Resolves icon names for the `icon://` protocol.
+pub struct IconState {
+    resolver: IconResolver,
+    cache: Option<IconCache>,
+}
+
+impl Default for IconState {
+    fn default() -> Self {
+        Self {
+            resolver: IconResolver::new(),
+            cache: IconCache::in_cache_dir(),
+        }
+    }
+}
+
+impl IconState {
+    /// Forgets earlier lookups, so icons of newly installed applications
+    /// are found.
+    pub fn refresh(&self) {
+        self.resolver.clear_cache();
+    }
+
+    /// The file to serve for icon `name`, scaled down when the theme only
+    /// has larger PNGs.
+    fn find(&self, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let icon = self.resolver.lookup(name, size, scale)?;
+        let Some(cache) = &self.cache else {
+            return Some(icon);
+        };
+        cache
+            .get(&icon, size * scale)
+            .map_err(|e| log::warn!("Failed to scale icon {}: {}", icon.display(), e))
+            .ok()
+    }
+}
+
+/// Serves `icon://localhost/<name>?size=<pixels>&scale=<factor>`: icon
+/// `name` of the current icon theme, or the PNG or SVG file at an absolute
+/// path, as desktop entries and plugin commands give them. Responds with
+/// 404 when there is no such icon, so the renderer can fall back to its own.
+pub fn icon_response(app: &tauri::AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let uri = request.uri();
+    let name = percent_decode_str(uri.path().trim_start_matches('/')).decode_utf8_lossy();
+
+    let mut size = DEFAULT_SIZE;
+    let mut scale = 1;
+    for (key, value) in uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+    {
+        match key {
+            "size" => size = value.parse().unwrap_or(size).clamp(1, 512),
+            "scale" => scale = value.parse().unwrap_or(scale).clamp(1, 4),
+            _ => {}
+        }
+    }
+
+    let icon = app.state::<IconState>().find(&name, size, scale);
+    let content_type = match icon.as_ref().and_then(|i| i.extension()?.to_str()) {
+        Some("png") => "image/png",
+        Some("svg") => "image/svg+xml",
+        _ => return not_found(),
+    };
+    let Some(body) = icon.and_then(|icon| std::fs::read(icon).ok()) else {
+        return not_found();
+    };
+
+    let mut response = Response::new(body);
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("max-age=3600"),
+    );
+    response
+}
+
+fn not_found() -> Response<Vec<u8>> {
+    let mut response = Response::new(Vec::new());
+    *response.status_mut() = StatusCode::NOT_FOUND;
+    response
+}
@@ -1,7 +1,13 @@
+mod applications;
 mod bookmarks;
 mod chrome;
+mod firefox;
 pub mod github;
+mod history;
+mod icons;
 mod platform;
+mod policy;
+mod preferences;
 pub mod plugins;
 mod shortcuts;
 mod tools;
@@ -20,42 +26,34 @@ use shortcuts::{load_saved_shortcuts, RegisteredShortcuts};
 
 type PluginRegistry = Mutex<Vec<plugins::RegisteredPlugin>>;
 
-fn is_command_allowed(cmd: &str) -> bool {
-    let executable = cmd.split_whitespace().next().unwrap_or("");
-    let base_name = executable.rsplit('/').next().unwrap_or(executable);
-
-    // Windows: also strip .exe extension
-    #[cfg(target_os = "windows")]
-    let base_name = base_name.strip_suffix(".exe").unwrap_or(base_name);
-
-    platform::get_allowed_commands().contains(&base_name)
-}
-
+/// Starts `command`, split like a shell would but without one, if the
+/// command policy allows it. `plugin` is the ID of the plugin whose action
+/// runs it, so its grants apply.
 #[tauri::command]
-fn run_shell_command(command: &str) -> Result<String, String> {
+async fn run_shell_command(
+    command: String,
+    plugin: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<String, String> {
     log::debug!("run_shell_command called with: {}", command);
 
-    if !is_command_allowed(command) {
-        log::warn!("Command not allowed: {}", command);
-        return Err(format!(
-            "Command not allowed. Only these executables are permitted: {:?}",
-            platform::get_allowed_commands()
-        ));
-    }
-
-    let parts = shell_words::split(command).map_err(|e| e.to_string())?;
+    let parts = shell_words::split(&command).map_err(|e| e.to_string())?;
     if parts.is_empty() {
         return Err("Empty command".to_string());
     }
+    let mut argv = vec![parts[0].clone()];
+    argv.extend(parts[1..].iter().map(|arg| platform::expand_path(arg)));
 
-    let executable = &parts[0];
-    let args: Vec<String> = parts[1..]
-        .iter()
-        .map(|arg| platform::expand_path(arg))
-        .collect();
+    // Asking the user blocks until they answer
+    let check = argv.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        policy::authorize(&app, &command, &check, plugin.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
 
-    Command::new(executable)
-        .args(&args)
+    Command::new(&argv[0])
+        .args(&argv[1..])
         .spawn()
         .map_err(|e| e.to_string())?;
 
@@ -262,6 +260,10 @@ pub fn run() {
         .manage(Mutex::new(Vec::<plugins::RegisteredPlugin>::new()))
         .manage(std::sync::Arc::new(plugins::InstallSessions::default()))
         .manage(tools::AiProcessState(Mutex::new(None)))
+        .manage(bookmarks::BookmarkState::default())
+        .manage(history::HistoryState::default())
+        .manage(applications::ApplicationState::default())
+        .manage(icons::IconState::default())
         .plugin(
             tauri_plugin_log::Builder::new()
                 .targets([
@@ -290,6 +292,13 @@ pub fn run() {
             MacosLauncher::LaunchAgent,
             None,
         ))
+        .register_asynchronous_uri_scheme_protocol("icon", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            // Lookups and scaling read the disk; keep them off the webview
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(icons::icon_response(&app, &request))
+            });
+        })
         .setup(|app| {
             let window = app
                 .get_webview_window("main")
@@ -368,6 +377,9 @@ pub fn run() {
                 log::warn!("Failed to load saved shortcuts: {}", e);
             }
 
+            bookmarks::watch_database(app.handle().clone());
+            applications::watch_applications(app.handle().clone());
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -378,17 +390,51 @@ pub fn run() {
             toggle_autostart,
             hide_main_window,
             shortcuts::sync_global_shortcuts,
+            applications::list_applications,
+            applications::launch_application,
             chrome::list_chrome_profiles,
+            chrome::import_chrome_bookmarks,
+            firefox::list_firefox_profiles,
+            firefox::import_firefox_bookmarks,
             bookmarks::bookmark_list,
             bookmarks::bookmark_search,
+            bookmarks::bookmark_list_folder,
+            bookmarks::bookmark_list_folders,
+            bookmarks::bookmark_create_folder,
+            bookmarks::bookmark_rename_folder,
+            bookmarks::bookmark_move_folder,
+            bookmarks::bookmark_delete_folder,
+            bookmarks::bookmark_move,
             bookmarks::bookmark_get_by_id,
             bookmarks::bookmark_open,
             bookmarks::bookmark_add,
             bookmarks::bookmark_update,
+            bookmarks::bookmark_resolve_keyword,
+            bookmarks::bookmark_open_keyword,
             bookmarks::bookmark_delete,
+            bookmarks::bookmark_list_trash,
+            bookmarks::bookmark_restore,
+            bookmarks::bookmark_purge_trash,
+            bookmarks::bookmark_undo_history,
+            bookmarks::bookmark_undo,
+            bookmarks::bookmark_reload_store,
+            bookmarks::bookmark_find_duplicates,
+            bookmarks::bookmark_merge,
             bookmarks::bookmark_list_tags,
             bookmarks::bookmark_rename_tag,
+            bookmarks::bookmark_merge_tags,
             bookmarks::bookmark_delete_tag,
+            bookmarks::bookmark_import_html,
+            bookmarks::bookmark_export_html,
+            bookmarks::bookmark_check_links,
+            bookmarks::bookmark_sync,
+            bookmarks::bookmark_link_problems,
+            bookmarks::bookmark_fix_redirect,
+            bookmarks::bookmark_fetch_metadata,
+            bookmarks::bookmark_favicon,
+            history::record_launch,
+            history::launch_boosts,
+            history::clear_launch_history,
             discover_plugins,
             run_plugin_command,
             prepare_plugin_install,
@@ -53,6 +53,19 @@ pub fn get_chrome_config_dir() -> Option<PathBuf> {
     }
 }
 
+/// Returns the Firefox directory containing `profiles.ini` for the current OS.
+pub fn get_firefox_config_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    {
+        dirs::home_dir().map(|p| p.join(".mozilla").join("firefox"))
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        dirs::data_dir().map(|p| p.join("Mozilla").join("Firefox"))
+    }
+}
+
 /// Expands `~` and `~/` prefixes to the user's home directory.
 pub fn expand_path(path: &str) -> String {
     if path == "~" {
@@ -68,19 +81,6 @@ pub fn expand_path(path: &str) -> String {
     }
 }
 
-/// Returns the list of allowed shell commands for the current OS.
-pub fn get_allowed_commands() -> &'static [&'static str] {
-    #[cfg(target_os = "linux")]
-    {
-        &["google-chrome-stable", "xdg-open", "code", "cursor"]
-    }
-
-    #[cfg(target_os = "windows")]
-    {
-        &["chrome", "explorer", "code", "cursor", "cmd"]
-    }
-}
-
 /// Returns the list of standard user directories for the current OS.
 pub fn get_user_directories() -> Vec<UserDirectory> {
     let mut directories = Vec::new();
@@ -0,0 +1,470 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Executables allowed, with any arguments, when there is no policy file.
+#[cfg(target_os = "linux")]
+const DEFAULT_EXECUTABLES: &[&str] = &["google-chrome-stable", "xdg-open", "code", "cursor"];
+
+#[cfg(target_os = "windows")]
+const DEFAULT_EXECUTABLES: &[&str] = &["chrome", "explorer", "code", "cursor", "cmd"];
+
+/// What happens to commands no rule allows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyMode {
+    /// They fail.
+    Deny,
+    /// The user is asked, and may allow the command once or add a rule for
+    /// its executable to the policy file.
+    #[default]
+    Prompt,
+}
+
+/// An executable and, optionally, the arguments it may be given.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandRule {
+    /// A file name such as `xdg-open`, matching it when run by that name
+    /// and so found through `PATH`, or a path matching only that file.
+    pub executable: String,
+    /// Glob patterns the arguments must match, one per argument: `*`
+    /// matches any run of characters and `?` any one character. A last
+    /// pattern of `**` matches all remaining arguments. Without `args`, any
+    /// arguments are allowed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<Vec<String>>,
+}
+
+impl CommandRule {
+    fn executable(executable: &str) -> Self {
+        Self {
+            executable: executable.to_string(),
+            args: None,
+        }
+    }
+
+    fn matches(&self, argv: &[String]) -> bool {
+        let Some((executable, args)) = argv.split_first() else {
+            return false;
+        };
+
+        // A bare name must not match a file of that name elsewhere, such as
+        // `~/Downloads/code`
+        let executable_matches = if self.executable.contains(['/', '\\']) {
+            *executable == self.executable
+        } else {
+            !executable.contains(['/', '\\'])
+                && base_name(executable) == base_name(&self.executable)
+        };
+        if !executable_matches {
+            return false;
+        }
+
+        let Some(patterns) = &self.args else {
+            return true;
+        };
+        match patterns.split_last() {
+            Some((last, init)) if last == "**" => {
+                args.len() >= init.len() && init.iter().zip(args).all(|(p, a)| glob_matches(p, a))
+            }
+            _ => {
+                args.len() == patterns.len()
+                    && patterns.iter().zip(args).all(|(p, a)| glob_matches(p, a))
+            }
+        }
+    }
+}
+
+/// The file name of `executable`, without `.exe` on Windows.
+fn base_name(executable: &str) -> &str {
+    let name = executable.rsplit(['/', '\\']).next().unwrap_or(executable);
+
+    #[cfg(target_os = "windows")]
+    let name = name.strip_suffix(".exe").unwrap_or(name);
+
+    name
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of
+/// characters and `?` any one character.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was and the text position it is matched up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` take one more character and retry
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The outcome of checking a command against the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Prompt,
+    Deny,
+}
+
+/// The user's command execution policy. Rules in `allow` apply to every
+/// command; those under `plugins` only to commands the renderer says come
+/// from that plugin. That attribution is not verified, so plugin rules keep
+/// a rule from applying to unrelated commands but do not isolate plugins
+/// from each other.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandPolicy {
+    #[serde(default)]
+    pub mode: PolicyMode,
+    #[serde(default)]
+    pub allow: Vec<CommandRule>,
+    #[serde(default)]
+    pub plugins: BTreeMap<String, Vec<CommandRule>>,
+}
+
+impl Default for CommandPolicy {
+    fn default() -> Self {
+        Self {
+            mode: PolicyMode::default(),
+            allow: DEFAULT_EXECUTABLES
+                .iter()
+                .map(|executable| CommandRule::executable(executable))
+                .collect(),
+            plugins: BTreeMap::new(),
+        }
+    }
+}
+
+impl CommandPolicy {
+    /// `command-policy.json` in MRunner's config directory. The renderer's
+    /// file access to that directory excludes it.
+    pub fn path() -> Option<PathBuf> {
+        crate::preferences::config_dir().map(|dir| dir.join("command-policy.json"))
+    }
+
+    /// Reads the policy file, or the default policy if there is none. An
+    /// invalid file only allows the default executables and never prompts,
+    /// so a typo can't widen what runs.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Invalid command policy {}: {}", path.display(), e);
+            Self {
+                mode: PolicyMode::Deny,
+                ..Self::default()
+            }
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("Config directory not found")?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, content).map_err(|e| e.to_string())
+    }
+
+    /// Checks `argv`, the command line after `~` expansion, run on behalf
+    /// of plugin `plugin` if any.
+    pub fn evaluate(&self, argv: &[String], plugin: Option<&str>) -> Decision {
+        let plugin_rules = plugin
+            .and_then(|plugin| self.plugins.get(plugin))
+            .into_iter()
+            .flatten();
+        if self
+            .allow
+            .iter()
+            .chain(plugin_rules)
+            .any(|r| r.matches(argv))
+        {
+            return Decision::Allow;
+        }
+
+        match self.mode {
+            PolicyMode::Deny => Decision::Deny,
+            PolicyMode::Prompt => Decision::Prompt,
+        }
+    }
+
+    /// Allows `executable`, the path or bare name it was run by, with any
+    /// arguments.
+    pub fn grant(&mut self, executable: &str) {
+        let rule = CommandRule::executable(executable);
+        if !self.allow.contains(&rule) {
+            self.allow.push(rule);
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    time: String,
+    command: &'a str,
+    plugin: Option<&'a str>,
+    decision: &'a str,
+}
+
+/// Appends a line to `commands.log` in MRunner's log directory recording
+/// `command`, the plugin the renderer says ran it and `decision`: `allowed`,
+/// `approved` once or `granted` from now on by the user, `declined`, or
+/// `denied`.
+pub fn audit(command: &str, plugin: Option<&str>, decision: &str) {
+    let Some(dir) = dirs::data_local_dir().map(|dir| dir.join("mrunner").join("logs")) else {
+        return;
+    };
+    let entry = AuditEntry {
+        time: chrono::Local::now().to_rfc3339(),
+        command,
+        plugin,
+        decision,
+    };
+
+    let result = std::fs::create_dir_all(&dir).and_then(|_| {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join("commands.log"))?;
+        let line = serde_json::to_string(&entry).map_err(std::io::Error::other)?;
+        writeln!(file, "{}", line)
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to write command audit log: {}", e);
+    }
+}
+
+/// Held from reading the policy until any rule the user grants is saved, so
+/// prompts are asked one at a time and never save over each other's grants.
+static POLICY_LOCK: Mutex<()> = Mutex::new(());
+
+/// What the user answered when asked about a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Answer {
+    Always,
+    Once,
+    Deny,
+}
+
+/// Asks the user whether `argv` may run, this time or from now on.
+fn prompt(app: &tauri::AppHandle, argv: &[String], plugin: Option<&str>) -> Answer {
+    use tauri_plugin_dialog::{
+        DialogExt, MessageDialogButtons, MessageDialogKind, MessageDialogResult,
+    };
+
+    const ALWAYS: &str = "Always allow";
+    const ONCE: &str = "Allow once";
+
+    let requester = plugin
+        .map(|plugin| format!("An action of the plugin \"{}\"", plugin))
+        .unwrap_or_else(|| "MRunner".to_string());
+    let message = format!(
+        "{} wants to run:\n\n{}\n\n\"{}\" lets {} run with any arguments from now on.",
+        requester,
+        shell_words::join(argv),
+        ALWAYS,
+        argv[0]
+    );
+
+    let result = app
+        .dialog()
+        .message(message)
+        .title("Allow command?")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::YesNoCancelCustom(
+            ALWAYS.to_string(),
+            ONCE.to_string(),
+            "Deny".to_string(),
+        ))
+        .blocking_show_with_result();
+
+    match result {
+        MessageDialogResult::Yes => Answer::Always,
+        MessageDialogResult::No => Answer::Once,
+        MessageDialogResult::Custom(label) if label == ALWAYS => Answer::Always,
+        MessageDialogResult::Custom(label) if label == ONCE => Answer::Once,
+        _ => Answer::Deny,
+    }
+}
+
+/// Checks `argv` (the parsed `command`) against the policy, asking the user
+/// if the policy says so, and records the decision in the audit log. Blocks
+/// while the user is asked.
+pub fn authorize(
+    app: &tauri::AppHandle,
+    command: &str,
+    argv: &[String],
+    plugin: Option<&str>,
+) -> Result<(), String> {
+    let _lock = POLICY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let decision = match CommandPolicy::load().evaluate(argv, plugin) {
+        Decision::Allow => "allowed",
+        Decision::Prompt => match prompt(app, argv, plugin) {
+            Answer::Always => {
+                // Reread the file, which may have been edited while asking
+                let mut policy = CommandPolicy::load();
+                policy.grant(&argv[0]);
+                if let Err(e) = policy.save() {
+                    log::warn!("Failed to save command policy: {}", e);
+                }
+                "granted"
+            }
+            Answer::Once => "approved",
+            Answer::Deny => "declined",
+        },
+        Decision::Deny => "denied",
+    };
+    audit(command, plugin, decision);
+
+    if matches!(decision, "allowed" | "approved" | "granted") {
+        return Ok(());
+    }
+    log::warn!("Command not allowed: {}", command);
+    Err(format!(
+        "Command not allowed: {}. Allow it in {}",
+        base_name(&argv[0]),
+        CommandPolicy::path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "command-policy.json".to_string())
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(command: &str) -> Vec<String> {
+        shell_words::split(command).unwrap()
+    }
+
+    fn rule(executable: &str, args: Option<&[&str]>) -> CommandRule {
+        CommandRule {
+            executable: executable.to_string(),
+            args: args.map(|args| args.iter().map(|a| a.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn glob_matches_stars_and_question_marks() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("--profile=*", "--profile=Default"));
+        assert!(glob_matches("*.txt", "/tmp/a.b.txt"));
+        assert!(glob_matches("file?.png", "file1.png"));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(!glob_matches("file?.png", "file.png"));
+        assert!(!glob_matches("*.txt", "/tmp/a.txt.sh"));
+        assert!(!glob_matches("play", "play-pause"));
+    }
+
+    #[test]
+    fn rules_match_executables_and_arguments() {
+        let any = rule("xdg-open", None);
+        assert!(any.matches(&argv("xdg-open /tmp")));
+        assert!(!any.matches(&argv("/usr/bin/xdg-open")));
+        assert!(!any.matches(&argv("./xdg-open")));
+        assert!(!any.matches(&argv("xdg-open-evil /tmp")));
+
+        let path = rule("/usr/bin/playerctl", Some(&["play-pause"]));
+        assert!(path.matches(&argv("/usr/bin/playerctl play-pause")));
+        assert!(!path.matches(&argv("playerctl play-pause")));
+        assert!(!path.matches(&argv("/usr/bin/playerctl play-pause now")));
+        assert!(!path.matches(&argv("/usr/bin/playerctl")));
+
+        let rest = rule("code", Some(&["--new-window", "**"]));
+        assert!(rest.matches(&argv("code --new-window")));
+        assert!(rest.matches(&argv("code --new-window a b")));
+        assert!(!rest.matches(&argv("code --reuse-window a")));
+    }
+
+    #[test]
+    fn evaluate_applies_plugin_grants_and_mode() {
+        let mut policy: CommandPolicy = serde_json::from_str(
+            r#"{
+                "mode": "deny",
+                "allow": [{ "executable": "xdg-open" }],
+                "plugins": {
+                    "media": [{ "executable": "playerctl", "args": ["play*"] }]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(policy.evaluate(&argv("xdg-open ."), None), Decision::Allow);
+        assert_eq!(
+            policy.evaluate(&argv("xdg-open ."), Some("media")),
+            Decision::Allow
+        );
+        assert_eq!(
+            policy.evaluate(&argv("playerctl play"), Some("media")),
+            Decision::Allow
+        );
+        assert_eq!(
+            policy.evaluate(&argv("playerctl stop"), Some("media")),
+            Decision::Deny
+        );
+        // Plugin grants don't extend to other plugins or the app itself
+        assert_eq!(
+            policy.evaluate(&argv("playerctl play"), Some("other")),
+            Decision::Deny
+        );
+        assert_eq!(
+            policy.evaluate(&argv("playerctl play"), None),
+            Decision::Deny
+        );
+
+        policy.mode = PolicyMode::Prompt;
+        assert_eq!(policy.evaluate(&argv("rm -rf ~"), None), Decision::Prompt);
+
+        policy.grant("notify-send");
+        assert_eq!(
+            policy.evaluate(&argv("notify-send hi"), None),
+            Decision::Allow
+        );
+        assert_eq!(
+            policy.evaluate(&argv("/tmp/notify-send hi"), None),
+            Decision::Prompt
+        );
+
+        policy.grant("/home/user/bin/tool");
+        assert_eq!(
+            policy.evaluate(&argv("/home/user/bin/tool -x"), None),
+            Decision::Allow
+        );
+        assert_eq!(policy.evaluate(&argv("tool -x"), None), Decision::Prompt);
+    }
+
+    #[test]
+    fn default_policy_allows_platform_executables() {
+        let policy = CommandPolicy::default();
+        assert_eq!(policy.mode, PolicyMode::Prompt);
+        assert_eq!(policy.evaluate(&argv("code ."), None), Decision::Allow);
+        assert_eq!(policy.evaluate(&argv("bash -c x"), None), Decision::Prompt);
+    }
+}
@@ -0,0 +1,23 @@
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Config directory shared with the renderer. Development builds use a
+/// separate directory so they don't clash with an installed version.
+pub fn config_dir() -> Option<PathBuf> {
+    let name = if cfg!(debug_assertions) {
+        "mrunner-dev"
+    } else {
+        "mrunner"
+    };
+    dirs::home_dir().map(|home| home.join(".config").join(name))
+}
+
+/// Reads `preferences.json`, returning `None` if it is missing or invalid.
+pub fn load() -> Option<Value> {
+    let path = config_dir()?.join("preferences.json");
+    let content = std::fs::read_to_string(&path).ok()?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| log::warn!("Invalid preferences file {}: {}", path.display(), e))
+        .ok()
+}
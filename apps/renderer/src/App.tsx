@@ -4,7 +4,7 @@ import { sendNotification } from '@tauri-apps/plugin-notification'
 import { lazy, useCallback, useEffect, useRef, useState } from 'react'
 import { useTranslation } from 'react-i18next'
 
-import type { Command } from '@/commands/types'
+import type { BookmarkChanges, Command } from '@/commands/types'
 import { isScriptableAction } from '@/commands/types'
 import { CommandPalette, PluginCommandView } from '@/components/command-palette'
 import { SettingsSheet } from '@/components/settings/settings-sheet'
@@ -16,9 +16,13 @@ import {
 	useCommands,
 	useDialogManager,
 	useKeyboardShortcuts,
+	useKeywordMatch,
+	useLaunchBoosts,
 	usePlugins,
 	useWindowManager,
 } from '@/hooks'
+import { KEYWORD_COMMAND_ID } from '@/lib/constants'
+import { launchKind, recordLaunch } from '@/lib/launch-history'
 import { getPluginEnvironment } from '@/lib/plugin-environment'
 
 const BookmarkDialog = lazy(() =>
@@ -52,8 +56,15 @@ function App() {
 	// Core data hooks
 	const { commands, executeCommand, folderActions } = useCommands()
 	const { plugins } = usePlugins()
-	const { bookmarks, refresh, remove, search, parseQuery } = useBookmarks()
+	const { bookmarks, hasMore, loadMore, refresh, remove, search, parseQuery } =
+		useBookmarks()
 	const { openBookmark } = useBookmarkActions(bookmarks)
+	const launchBoosts = useLaunchBoosts(query)
+	const keywordMatch = useKeywordMatch(query)
+
+	const openKeyword = useCallback(async () => {
+		await invoke('bookmark_open_keyword', { query })
+	}, [query])
 
 	// Dialog manager hook
 	const dialogManager = useDialogManager({
@@ -77,7 +88,10 @@ function App() {
 		commands,
 		plugins,
 		bookmarks,
+		boosts: launchBoosts,
+		keywordMatch,
 		onOpenBookmark: openBookmark,
+		onOpenKeyword: openKeyword,
 	})
 
 	// Bookmark search hook
@@ -118,16 +132,39 @@ function App() {
 		}
 	}, [dialogManager])
 
+	// Reload bookmarks written by other programs (buku, sync, another window)
+	useEffect(() => {
+		const unlisten = listen<BookmarkChanges>('bookmarks-changed', () => {
+			refresh()
+		})
+		return () => {
+			unlisten.then((fn) => fn())
+		}
+	}, [refresh])
+
 	const handleBookmarkSelect = useCallback(
 		async (commandId: string): Promise<boolean> => {
+			if (commandId === KEYWORD_COMMAND_ID) {
+				if (keywordMatch) {
+					void recordLaunch(
+						`bookmark-${keywordMatch.bookmark.index}`,
+						'bookmark',
+						query,
+					)
+				}
+				await openKeyword()
+				await hideWindow()
+				return true
+			}
 			if (!commandId.startsWith('bookmark-')) return false
 			const bookmarkIndex = parseInt(commandId.replace('bookmark-', ''), 10)
 			if (Number.isNaN(bookmarkIndex)) return false
+			void recordLaunch(commandId, 'bookmark', query)
 			await openBookmark(bookmarkIndex)
 			await hideWindow()
 			return true
 		},
-		[openBookmark, hideWindow],
+		[keywordMatch, openBookmark, openKeyword, hideWindow, query],
 	)
 
 	const handleSelect = useCallback(
@@ -136,6 +173,7 @@ function App() {
 
 			const command = allItems.find((c) => c.id === commandId)
 			if (!command) return
+			void recordLaunch(command.id, launchKind(command), query)
 
 			// Handle dialog actions
 			if (command.action.type === 'dialog') {
@@ -290,12 +328,14 @@ function App() {
 					onQueryChange={setQuery}
 					inputRef={inputRef}
 					bookmarks={bookmarks}
+					keywordMatch={keywordMatch}
 					groupedCommands={groupedCommands}
 					allItems={allItems}
 					commandFilter={commandFilter}
 					onSelect={handleSelect}
 					onAddBookmark={() => dialogManager.setIsAddBookmarkOpen(true)}
 					onOpenBookmark={openBookmark}
+					onLoadMoreBookmarks={hasMore ? loadMore : undefined}
 					onHideWindow={hideWindow}
 					executeCommand={executeCommand}
 					onOpenFolderManager={() => dialogManager.setIsFolderManagerOpen(true)}
@@ -1,5 +1,10 @@
 export type {
 	Bookmark,
+	BookmarkChanges,
+	BookmarkPage,
+	BookmarkPageRequest,
+	BookmarkSort,
+	KeywordMatch,
 	Command,
 	CommandAction,
 	CommandIcon,
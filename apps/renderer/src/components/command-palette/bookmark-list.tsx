@@ -1,15 +1,22 @@
 import { Kbd } from '@mrunner/ui'
 
-import type { Bookmark } from '@/commands/types'
+import type { Bookmark, KeywordMatch } from '@/commands/types'
 import { ListItem } from '@/components/list-item'
+import { KEYWORD_COMMAND_ID } from '@/lib/constants'
 
 export interface BookmarkListProps {
 	bookmarks: Bookmark[]
+	/** Bookmark keyword the query starts with, listed first */
+	keywordMatch?: KeywordMatch | null
 	onSelect: (commandId: string) => void
 }
 
-export function BookmarkList({ bookmarks, onSelect }: BookmarkListProps) {
-	return bookmarks.map((bm) => (
+export function BookmarkList({
+	bookmarks,
+	keywordMatch,
+	onSelect,
+}: BookmarkListProps) {
+	const items = bookmarks.map((bm) => (
 		<ListItem
 			key={`bookmark-${bm.index}`}
 			id={`bookmark-${bm.index}`}
@@ -26,4 +33,19 @@ export function BookmarkList({ bookmarks, onSelect }: BookmarkListProps) {
 			}
 		/>
 	))
+
+	if (!keywordMatch) return items
+
+	return [
+		<ListItem
+			key={KEYWORD_COMMAND_ID}
+			id={KEYWORD_COMMAND_ID}
+			value={keywordMatch.url}
+			title={keywordMatch.bookmark.title || keywordMatch.bookmark.uri}
+			description={keywordMatch.url}
+			icon="bookmark"
+			onSelect={onSelect}
+		/>,
+		...items,
+	]
 }
@@ -32,6 +32,7 @@ export function CommandGroups({
 								title={cmd.name}
 								description={cmd.description}
 								icon={cmd.icon}
+								themeIcon={cmd.themeIcon}
 								shortcut={cmd.shortcut}
 								onSelect={onSelect}
 							/>
@@ -11,7 +11,11 @@ import {
 import { lazy, type RefObject, Suspense, useCallback, useEffect } from 'react'
 import { useTranslation } from 'react-i18next'
 
-import type { Bookmark, Command as CommandType } from '@/commands/types'
+import type {
+	Bookmark,
+	Command as CommandType,
+	KeywordMatch,
+} from '@/commands/types'
 import type { SlashShortcut, ToolProvider } from '@/core/types/tools'
 import { CommandFooter } from '@/components/command-footer'
 import { UpdateBanner } from '@/components/update-banner'
@@ -21,6 +25,9 @@ import { AddBookmarkButton } from './add-bookmark-button'
 import { BookmarkList } from './bookmark-list'
 import { CommandGroups } from './command-groups'
 
+/** Distance from the bottom of the list at which more bookmarks load */
+const LOAD_MORE_THRESHOLD_PX = 200
+
 const AIChatView = lazy(() =>
 	import('@/components/ai-chat/ai-chat-view').then((mod) => ({
 		default: mod.AIChatView,
@@ -32,12 +39,15 @@ export interface CommandPaletteProps {
 	onQueryChange: (query: string) => void
 	inputRef: RefObject<HTMLInputElement | null>
 	bookmarks: Bookmark[]
+	keywordMatch?: KeywordMatch | null
 	groupedCommands: Record<string, CommandType[]>
 	allItems: CommandType[]
 	commandFilter: (value: string, search: string) => number
 	onSelect: (commandId: string) => void
 	onAddBookmark: () => void
 	onOpenBookmark: (index: number) => Promise<void>
+	/** Loads the next page of bookmarks; unset when all are loaded */
+	onLoadMoreBookmarks?: () => void
 	onHideWindow: () => Promise<void>
 	executeCommand: (command: CommandType) => Promise<unknown>
 	onOpenFolderManager: () => void
@@ -52,10 +62,12 @@ export function CommandPalette({
 	onQueryChange,
 	inputRef,
 	bookmarks,
+	keywordMatch,
 	groupedCommands,
 	commandFilter,
 	onSelect,
 	onAddBookmark,
+	onLoadMoreBookmarks,
 	isChatMode,
 	chatInitialMessage,
 	onStartChat,
@@ -87,6 +99,18 @@ export function CommandPalette({
 		[onQueryChange, onSelect],
 	)
 
+	// Load more bookmarks before the end of the list comes into view
+	const handleListScroll = useCallback(
+		(e: React.UIEvent<HTMLDivElement>) => {
+			const list = e.currentTarget
+			const remaining = list.scrollHeight - list.scrollTop - list.clientHeight
+			if (onLoadMoreBookmarks && remaining < LOAD_MORE_THRESHOLD_PX) {
+				onLoadMoreBookmarks()
+			}
+		},
+		[onLoadMoreBookmarks],
+	)
+
 	// Auto-activate shortcut when space is typed after an exact command match (e.g. "/gr ")
 	useEffect(() => {
 		if (matchedShortcut) {
@@ -188,7 +212,10 @@ export function CommandPalette({
 				autoFocus
 			/>
 
-			<CommandList className="flex-1 overflow-y-auto p-2">
+			<CommandList
+				className="flex-1 overflow-y-auto p-2"
+				onScroll={handleListScroll}
+			>
 				{isSlashMode && (
 					<CommandGroup heading={t('groups.Tools')}>
 						{filteredEntries.map((item) => {
@@ -242,7 +269,11 @@ export function CommandPalette({
 
 						<CommandGroup heading={t('groups.Bookmarks')}>
 							<AddBookmarkButton onSelect={onAddBookmark} />
-							<BookmarkList bookmarks={bookmarks} onSelect={onSelect} />
+							<BookmarkList
+								bookmarks={bookmarks}
+								keywordMatch={keywordMatch}
+								onSelect={onSelect}
+							/>
 						</CommandGroup>
 
 						<CommandGroups
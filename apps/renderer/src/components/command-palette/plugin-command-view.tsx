@@ -3,15 +3,17 @@ import type { RefObject } from 'react'
 import { Command, CommandInput, CommandItem, CommandList } from '@mrunner/ui'
 import { invoke } from '@tauri-apps/api/core'
 import { ChevronLeft, Terminal } from 'lucide-react'
-import { useCallback, useEffect, useRef, useState } from 'react'
+import { useCallback, useEffect, useMemo, useRef, useState } from 'react'
 import { useTranslation } from 'react-i18next'
 import ReactMarkdown from 'react-markdown'
 
 import type { Command as CommandType } from '@/commands/types'
 import { isScriptableAction } from '@/commands/types'
 import { CommandFooter } from '@/components/command-footer'
+import { useLaunchBoosts } from '@/hooks/use-launch-boosts'
 import { ICON_MAP } from '@/lib/constants'
 import { executePluginAction } from '@/lib/execute-plugin-action'
+import { launchBoostKey, recordLaunch } from '@/lib/launch-history'
 import { getPluginEnvironment } from '@/lib/plugin-environment'
 
 export interface PluginCommandViewProps {
@@ -60,8 +62,25 @@ export function PluginCommandView({
 	const [loading, setLoading] = useState(false)
 	const [error, setError] = useState<string | null>(null)
 	const debounceRef = useRef<ReturnType<typeof setTimeout> | null>(null)
+	const launchBoosts = useLaunchBoosts(query)
 
 	const mode = isScriptableAction(command.action) ? command.action.mode : 'list'
+	// Command IDs are `<plugin id>:<command>`
+	const pluginId = isScriptableAction(command.action)
+		? command.action.commandId.split(':')[0]
+		: undefined
+
+	// Items launched after typing this query before move up, keeping the
+	// plugin's own order among items with the same boost
+	const rankedItems = useMemo(() => {
+		if (!isScriptableAction(command.action) || launchBoosts.size === 0) {
+			return items
+		}
+		const prefix = `${command.action.commandId}/`
+		const boost = (item: ListItem) =>
+			launchBoosts.get(launchBoostKey('plugin', prefix + item.id)) ?? 0
+		return [...items].sort((a, b) => boost(b) - boost(a))
+	}, [command, items, launchBoosts])
 
 	const runCommand = useCallback(
 		async (
@@ -133,16 +152,26 @@ export function PluginCommandView({
 
 	const handleItemSelect = useCallback(
 		async (item: ListItem) => {
+			if (isScriptableAction(command.action)) {
+				void recordLaunch(
+					`${command.action.commandId}/${item.id}`,
+					'plugin',
+					query,
+				)
+			}
 			const firstAction = item.actions?.[0]
 			if (firstAction) {
-				await executePluginAction(firstAction, { onPush: onPushCommand })
+				await executePluginAction(firstAction, {
+					onPush: onPushCommand,
+					pluginId,
+				})
 				return
 			}
 			// No declared actions: delegate to the plugin's onItemSelect hook,
 			// which can return a new list or detail result to render.
 			await runCommand(query, { method: 'onItemSelect', itemId: item.id })
 		},
-		[onPushCommand, runCommand, query],
+		[command, onPushCommand, pluginId, runCommand, query],
 	)
 
 	return (
@@ -198,7 +227,10 @@ export function PluginCommandView({
 										key={i}
 										type="button"
 										onClick={() =>
-											executePluginAction(action, { onPush: onPushCommand })
+											executePluginAction(action, {
+												onPush: onPushCommand,
+												pluginId,
+											})
 										}
 										className="rounded-md bg-primary px-3 py-1.5 font-medium text-primary-foreground text-xs transition-colors hover:bg-primary/90"
 									>
@@ -215,7 +247,7 @@ export function PluginCommandView({
 						{t('search.empty')}
 					</div>
 				) : (
-					items.map((item) => {
+					rankedItems.map((item) => {
 						const IconComponent =
 							item.icon && item.icon in ICON_MAP
 								? ICON_MAP[item.icon as keyof typeof ICON_MAP]
@@ -1,9 +1,10 @@
-import type { ReactNode } from 'react'
+import { type ReactNode, useState } from 'react'
 import { CommandItem, Kbd } from '@mrunner/ui'
 import { Terminal } from 'lucide-react'
 
 import type { CommandIcon } from '@/commands/types'
 import { ICON_MAP } from '@/lib/constants'
+import { themeIconUrl } from '@/lib/theme-icon'
 
 interface ListItemProps {
 	id: string
@@ -11,6 +12,7 @@ interface ListItemProps {
 	title: string
 	description?: string
 	icon: CommandIcon
+	themeIcon?: string
 	shortcut?: string
 	actions?: ReactNode
 	onSelect: (id: string) => void
@@ -22,16 +24,28 @@ export const ListItem = ({
 	title,
 	description,
 	icon,
+	themeIcon,
 	shortcut,
 	actions,
 	onSelect,
 }: ListItemProps) => {
 	const IconComponent = ICON_MAP[icon] ?? Terminal
+	// Falls back to the built-in icon when the theme has no such icon
+	const [themeIconFailed, setThemeIconFailed] = useState(false)
 
 	return (
 		<CommandItem value={value} onSelect={() => onSelect(id)}>
 			<div className="flex size-8 shrink-0 items-center justify-center rounded-lg border border-border/40 bg-muted/80 text-muted-foreground transition-all duration-150 group-data-[selected=true]:border-primary/20 group-data-[selected=true]:bg-primary/10 group-data-[selected=true]:text-primary">
-				<IconComponent className="size-4" aria-hidden="true" />
+				{themeIcon && !themeIconFailed ? (
+					<img
+						src={themeIconUrl(themeIcon)}
+						alt=""
+						className="size-5"
+						onError={() => setThemeIconFailed(true)}
+					/>
+				) : (
+					<IconComponent className="size-4" aria-hidden="true" />
+				)}
 			</div>
 			<div className="flex min-w-0 flex-1 items-baseline gap-2">
 				<span className="truncate font-medium text-[13px]">{title}</span>
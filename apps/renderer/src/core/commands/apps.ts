@@ -9,16 +9,24 @@ export function getAppCommands(
 ): Command[] {
 	if (!platform) return []
 
+	// On Linux, installed applications come from their desktop entries
+	const vsCode: Command[] =
+		platform.os === 'linux'
+			? []
+			: [
+					{
+						id: 'app-code',
+						name: 'VS Code',
+						description: 'Open Visual Studio Code',
+						icon: 'code',
+						group: 'Applications',
+						keywords: ['editor', 'code', 'ide', 'vscode'],
+						action: { type: 'shell', command: 'code' },
+					},
+				]
+
 	return [
-		{
-			id: 'app-code',
-			name: 'VS Code',
-			description: 'Open Visual Studio Code',
-			icon: 'code',
-			group: 'Applications',
-			keywords: ['editor', 'code', 'ide', 'vscode'],
-			action: { type: 'shell', command: 'code' },
-		},
+		...vsCode,
 		{
 			id: 'app-file-manager',
 			name: platform.os === 'linux' ? t('commands.fileManager') : 'Explorer',
@@ -8,11 +8,21 @@ import {
 	searchCommands,
 } from './fuzzy-search'
 
+/**
+ * How much launch history can lift a match: a result launched most often
+ * for the query scores up to 50% higher than its fuzzy match alone
+ */
+const BOOST_WEIGHT = 0.5
+
 /**
  * Custom filter function for cmdk Command component
  * Integrates Fuse.js fuzzy search with cmdk's filtering system
+ * and blends in each command's launch history boost (0-1)
  */
-export function createCommandFilter(commands: Command[]) {
+export function createCommandFilter(
+	commands: Command[],
+	boostOf: (command: Command) => number = () => 0,
+) {
 	let fuse: Fuse<Command> | null = null
 	let lastCommands = commands
 
@@ -27,11 +37,13 @@ export function createCommandFilter(commands: Command[]) {
 			return 1
 		}
 
+		const command = commands.find((cmd) => buildCmdkValue(cmd) === value)
+		const boost = 1 + BOOST_WEIGHT * (command ? boostOf(command) : 0)
+
 		// Bookmarks are filtered by backend search, not by Fuse
 		// Always show bookmarks that are in the list
-		const command = commands.find((cmd) => buildCmdkValue(cmd) === value)
 		if (command?.group === 'Bookmarks') {
-			return 1
+			return boost
 		}
 
 		// Recreate Fuse instance if commands changed
@@ -61,7 +73,7 @@ export function createCommandFilter(commands: Command[]) {
 		}
 
 		// Return score for this value
-		return scoreMap.get(value) ?? 0
+		return (scoreMap.get(value) ?? 0) * boost
 	}
 }
 
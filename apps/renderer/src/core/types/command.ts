@@ -18,6 +18,43 @@ export interface Bookmark {
 	title: string
 	tags: string
 	description: string
+	keyword?: string | null
+	folder_id?: number | null
+}
+
+export type BookmarkSort =
+	| 'relevance'
+	| 'created'
+	| 'title'
+	| 'url'
+	| 'last_opened'
+	| 'frecency'
+
+export interface BookmarkPageRequest {
+	sort?: BookmarkSort
+	reverse?: boolean
+	limit?: number | null
+	cursor?: string | null
+}
+
+export interface BookmarkChanges {
+	added: number[]
+	updated: number[]
+	removed: number[]
+	folders: boolean
+}
+
+export interface BookmarkPage {
+	bookmarks: Bookmark[]
+	total: number
+	next_cursor: string | null
+}
+
+/** A query such as `drs serde` whose first word is a bookmark keyword */
+export interface KeywordMatch {
+	bookmark: Bookmark
+	/** The bookmark URL with the rest of the query filled in */
+	url: string
 }
 
 export interface Tag {
@@ -51,6 +88,7 @@ export interface ScriptableAction {
 export interface ShellAction {
 	type: 'shell'
 	command: string
+	plugin?: string
 }
 
 export interface OpenAction {
@@ -128,6 +166,7 @@ export interface Command {
 	name: string
 	description?: string
 	icon: CommandIcon
+	themeIcon?: string
 	group?: string
 	keywords?: string[]
 	shortcut?: string
@@ -239,6 +278,11 @@ export const UserPreferencesSchema = z.object({
 			disabledPlugins: z.array(z.string()).default([]),
 		})
 		.optional(),
+	bookmarks: z
+		.object({
+			database: z.enum(['mrunner', 'buku']).default('mrunner'),
+		})
+		.optional(),
 })
 
 export const FoldersConfigSchema = z.object({
@@ -54,7 +54,11 @@ export function pluginToCommand(plugin: PluginConfig): Command {
 
 	switch (plugin.action.type) {
 		case 'shell':
-			action = { type: 'shell', command: plugin.action.command ?? '' }
+			action = {
+				type: 'shell',
+				command: plugin.action.command ?? '',
+				plugin: plugin.id,
+			}
 			break
 		case 'open':
 			action = { type: 'open', path: plugin.action.path ?? '' }
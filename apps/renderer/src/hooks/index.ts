@@ -27,6 +27,8 @@ export { useCommandData } from './use-command-data'
 export { useCommands } from './use-commands'
 export { useDialogManager } from './use-dialog-manager'
 export { useKeyboardShortcuts } from './use-keyboard-shortcuts'
+export { useKeywordMatch } from './use-keyword-match'
+export { useLaunchBoosts } from './use-launch-boosts'
 export { usePlugins } from './use-plugins'
 export { useSlashCommands } from './use-slash-commands'
 export { useUpdater } from './use-updater'
@@ -0,0 +1,122 @@
+import type { TFunction } from 'i18next'
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+import { useCallback, useEffect, useState } from 'react'
+
+import type { Command } from '@/commands/types'
+import { createLogger } from '@/lib/logger'
+
+const logger = createLogger('applications')
+
+// Module-level cache to avoid re-scanning on component remounts
+let applicationsCache: DesktopApplication[] | null = null
+
+export interface DesktopAction {
+	id: string
+	name: string
+	icon: string | null
+	exec: string
+}
+
+export interface DesktopApplication {
+	id: string
+	name: string
+	generic_name: string | null
+	comment: string | null
+	keywords: string[]
+	icon: string | null
+	categories: string[]
+	actions: DesktopAction[]
+}
+
+interface UseApplicationsReturn {
+	applications: DesktopApplication[]
+	commands: Command[]
+	loading: boolean
+	error: string | null
+	refresh: () => Promise<void>
+}
+
+function applicationToCommand(
+	application: DesktopApplication,
+	t: TFunction,
+): Command {
+	return {
+		id: `app-desktop-${application.id}`,
+		name: application.name,
+		description:
+			application.comment ??
+			application.generic_name ??
+			t('commands.launchApplication'),
+		icon: 'monitor',
+		themeIcon: application.icon ?? undefined,
+		group: 'Applications',
+		keywords: [
+			...application.keywords,
+			...(application.generic_name ? [application.generic_name] : []),
+			application.id.replace(/\.desktop$/, ''),
+		].map((k) => k.toLowerCase()),
+		action: {
+			type: 'function',
+			fn: () => invoke<void>('launch_application', { id: application.id }),
+		},
+	}
+}
+
+export function useApplications(t: TFunction): UseApplicationsReturn {
+	const [applications, setApplications] = useState<DesktopApplication[]>([])
+	const [loading, setLoading] = useState(false)
+	const [error, setError] = useState<string | null>(null)
+
+	const refresh = useCallback(async () => {
+		if (applicationsCache) {
+			setApplications(applicationsCache)
+			return
+		}
+
+		setLoading(true)
+		setError(null)
+		try {
+			const results = await invoke<DesktopApplication[]>('list_applications')
+			logger.info('Applications loaded', { count: results.length })
+			applicationsCache = results
+			setApplications(results)
+		} catch (err) {
+			const message = err instanceof Error ? err.message : String(err)
+			setError(message)
+			logger.error('Applications error', { error: message })
+		} finally {
+			setLoading(false)
+		}
+	}, [])
+
+	useEffect(() => {
+		refresh()
+	}, [refresh])
+
+	// The launcher re-reads the application directories when they change
+	useEffect(() => {
+		const unlisten = listen<DesktopApplication[]>(
+			'applications-changed',
+			(event) => {
+				logger.info('Applications changed', { count: event.payload.length })
+				applicationsCache = event.payload
+				setApplications(event.payload)
+			},
+		)
+
+		return () => {
+			unlisten.then((fn) => fn())
+		}
+	}, [])
+
+	const commands = applications.map((a) => applicationToCommand(a, t))
+
+	return {
+		applications,
+		commands,
+		loading,
+		error,
+		refresh,
+	}
+}
@@ -1,9 +1,14 @@
 import { invoke } from '@tauri-apps/api/core'
 import { sendNotification } from '@tauri-apps/plugin-notification'
-import { useCallback, useState } from 'react'
+import { useCallback, useRef, useState } from 'react'
 import { useTranslation } from 'react-i18next'
 
-import type { Bookmark } from '@/commands/types'
+import type {
+	Bookmark,
+	BookmarkPage,
+	BookmarkPageRequest,
+} from '@/commands/types'
+import { BOOKMARK_PAGE_SIZE } from '@/lib/constants'
 import { createLogger } from '@/lib/logger'
 import { parseQuery } from '@/lib/parse-query'
 
@@ -11,16 +16,27 @@ const logger = createLogger('bookmarks')
 
 export type { Bookmark }
 
+interface PageQuery {
+	command: 'bookmark_list' | 'bookmark_search'
+	args: Record<string, unknown>
+	page: BookmarkPageRequest
+}
+
 interface UseBookmarksReturn {
 	bookmarks: Bookmark[]
+	/** Bookmarks matching the last list or search, loaded or not */
+	total: number
+	hasMore: boolean
 	loading: boolean
 	error: string | null
 	// Search & List
-	list: (limit?: number) => Promise<Bookmark[]>
+	loadMore: () => Promise<void>
+	list: (limit?: number, page?: BookmarkPageRequest) => Promise<Bookmark[]>
 	search: (
 		query: string,
 		tagFilter?: string,
 		tagOr?: boolean,
+		page?: BookmarkPageRequest,
 	) => Promise<Bookmark[]>
 	getById: (id: number) => Promise<Bookmark | null>
 	refresh: () => Promise<void>
@@ -50,55 +66,90 @@ interface UseBookmarksReturn {
 export function useBookmarks(): UseBookmarksReturn {
 	const { t } = useTranslation()
 	const [bookmarks, setBookmarks] = useState<Bookmark[]>([])
+	const [total, setTotal] = useState(0)
+	const [nextCursor, setNextCursor] = useState<string | null>(null)
 	const [loading, setLoading] = useState(false)
 	const [error, setError] = useState<string | null>(null)
+	// The list or search later pages continue, and a counter so a response
+	// to an older request never overwrites a newer one
+	const lastQuery = useRef<PageQuery | null>(null)
+	const requestId = useRef(0)
 
-	const list = useCallback(async (limit?: number): Promise<Bookmark[]> => {
-		setLoading(true)
-		setError(null)
-		try {
-			const results = await invoke<Bookmark[]>('bookmark_list', {
-				limit: limit ?? null,
-			})
-			setBookmarks(results)
-			return results
-		} catch (err) {
-			const message = err instanceof Error ? err.message : String(err)
-			setError(message)
-			logger.error('Bookmark list error', { error: message })
-			return []
-		} finally {
-			setLoading(false)
-		}
-	}, [])
-
-	const search = useCallback(
-		async (
-			query: string,
-			tagFilter?: string,
-			tagOr?: boolean,
-		): Promise<Bookmark[]> => {
+	const fetchPage = useCallback(
+		async (query: PageQuery, append: boolean): Promise<Bookmark[]> => {
+			const id = ++requestId.current
+			lastQuery.current = query
 			setLoading(true)
 			setError(null)
 			try {
-				const results = await invoke<Bookmark[]>('bookmark_search', {
-					query,
-					tagFilter: tagFilter ?? null,
-					tagOr: tagOr ?? false,
+				const result = await invoke<BookmarkPage>(query.command, {
+					...query.args,
+					page: query.page,
 				})
-				setBookmarks(results)
-				return results
+				if (id !== requestId.current) return result.bookmarks
+				setBookmarks((prev) =>
+					append ? [...prev, ...result.bookmarks] : result.bookmarks,
+				)
+				setTotal(result.total)
+				setNextCursor(result.next_cursor)
+				return result.bookmarks
 			} catch (err) {
 				const message = err instanceof Error ? err.message : String(err)
-				setError(message)
+				if (id === requestId.current) setError(message)
+				logger.error('Bookmark page error', { error: message })
 				return []
 			} finally {
-				setLoading(false)
+				if (id === requestId.current) setLoading(false)
 			}
 		},
 		[],
 	)
 
+	const list = useCallback(
+		(limit?: number, page?: BookmarkPageRequest): Promise<Bookmark[]> =>
+			fetchPage(
+				{
+					command: 'bookmark_list',
+					args: {},
+					page: page ?? { limit: limit ?? BOOKMARK_PAGE_SIZE },
+				},
+				false,
+			),
+		[fetchPage],
+	)
+
+	const search = useCallback(
+		(
+			query: string,
+			tagFilter?: string,
+			tagOr?: boolean,
+			page?: BookmarkPageRequest,
+		): Promise<Bookmark[]> =>
+			fetchPage(
+				{
+					command: 'bookmark_search',
+					args: {
+						query,
+						tagFilter: tagFilter ?? null,
+						tagOr: tagOr ?? false,
+					},
+					page: page ?? { limit: BOOKMARK_PAGE_SIZE },
+				},
+				false,
+			),
+		[fetchPage],
+	)
+
+	// Continues the last list or search from where its last page ended
+	const loadMore = useCallback(async () => {
+		const query = lastQuery.current
+		if (!query || !nextCursor || loading) return
+		await fetchPage(
+			{ ...query, page: { ...query.page, cursor: nextCursor } },
+			true,
+		)
+	}, [fetchPage, nextCursor, loading])
+
 	const getById = useCallback(async (id: number): Promise<Bookmark | null> => {
 		try {
 			const result = await invoke<Bookmark | null>('bookmark_get_by_id', { id })
@@ -172,6 +223,7 @@ export function useBookmarks(): UseBookmarksReturn {
 				await invoke('bookmark_delete', { id })
 				sendNotification({ title: 'MRunner', body: t('notifications.deleted') })
 				setBookmarks((prev) => prev.filter((b) => b.index !== id))
+				setTotal((prev) => Math.max(0, prev - 1))
 				return true
 			} catch (err) {
 				logger.error('Bookmark delete error', { error: String(err) })
@@ -183,8 +235,11 @@ export function useBookmarks(): UseBookmarksReturn {
 
 	return {
 		bookmarks,
+		total,
+		hasMore: nextCursor !== null,
 		loading,
 		error,
+		loadMore,
 		list,
 		search,
 		getById,
@@ -1,13 +1,19 @@
 import { useMemo } from 'react'
 
-import type { Bookmark, Command } from '@/commands/types'
+import type { Bookmark, Command, KeywordMatch } from '@/commands/types'
 import { createCommandFilter } from '@/core/search'
+import { KEYWORD_COMMAND_ID } from '@/lib/constants'
+import { launchBoostKey, launchKind } from '@/lib/launch-history'
 
 export interface UseCommandDataOptions {
 	commands: Command[]
 	plugins: Command[]
 	bookmarks: Bookmark[]
+	/** Launch boosts by `launchBoostKey`, from `useLaunchBoosts`. */
+	boosts?: Map<string, number>
+	keywordMatch?: KeywordMatch | null
 	onOpenBookmark: (index: number) => Promise<void>
+	onOpenKeyword?: () => Promise<void>
 }
 
 export interface UseCommandDataReturn {
@@ -20,7 +26,10 @@ export function useCommandData({
 	commands,
 	plugins,
 	bookmarks,
+	boosts,
+	keywordMatch,
 	onOpenBookmark,
+	onOpenKeyword,
 }: UseCommandDataOptions): UseCommandDataReturn {
 	const allCommands = useMemo(
 		() => [...commands, ...plugins],
@@ -44,9 +53,29 @@ export function useCommandData({
 		[bookmarks, onOpenBookmark],
 	)
 
+	// Matches the keyword item BookmarkList puts first
+	const keywordCommands: Command[] = useMemo(() => {
+		if (!keywordMatch) return []
+		const { bookmark, url } = keywordMatch
+		return [
+			{
+				id: KEYWORD_COMMAND_ID,
+				name: bookmark.title || bookmark.uri,
+				description: url,
+				icon: 'bookmark' as const,
+				group: 'Bookmarks',
+				keywords: [url],
+				action: {
+					type: 'function' as const,
+					fn: async () => onOpenKeyword?.(),
+				},
+			},
+		]
+	}, [keywordMatch, onOpenKeyword])
+
 	const allItems = useMemo(
-		() => [...bookmarkCommands, ...allCommands],
-		[bookmarkCommands, allCommands],
+		() => [...keywordCommands, ...bookmarkCommands, ...allCommands],
+		[keywordCommands, bookmarkCommands, allCommands],
 	)
 
 	const groupedCommands = useMemo(
@@ -67,7 +96,15 @@ export function useCommandData({
 		[allItems],
 	)
 
-	const commandFilter = useMemo(() => createCommandFilter(allItems), [allItems])
+	const commandFilter = useMemo(
+		() =>
+			createCommandFilter(
+				allItems,
+				(command) =>
+					boosts?.get(launchBoostKey(launchKind(command), command.id)) ?? 0,
+			),
+		[allItems, boosts],
+	)
 
 	return {
 		allItems,
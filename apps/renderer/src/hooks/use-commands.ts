@@ -10,6 +10,7 @@ import { createLogger } from '@/lib/logger'
 
 const logger = createLogger('commands')
 
+import { useApplications } from './use-applications'
 import { useChromeProfiles } from './use-chrome-profiles'
 import { useFolderSettings } from './use-folder-settings'
 import { usePlatform } from './use-platform'
@@ -32,6 +33,7 @@ export function useCommands(): UseCommandsReturn {
 	const { t } = useTranslation()
 	const { platform } = usePlatform()
 	const { commands: chromeCommands } = useChromeProfiles(platform, t)
+	const { commands: installedAppCommands } = useApplications(t)
 	const {
 		folders,
 		systemDirectories,
@@ -46,8 +48,14 @@ export function useCommands(): UseCommandsReturn {
 		const appCmds = getAppCommands(platform, t)
 		const fileCmds = getFileCommands(platform, folders, t)
 		const mrunnerCmds = getMRunnerCommands(platform, t)
-		return [...fileCmds, ...chromeCommands, ...appCmds, ...mrunnerCmds]
-	}, [platform, chromeCommands, folders, t])
+		return [
+			...fileCmds,
+			...chromeCommands,
+			...appCmds,
+			...installedAppCommands,
+			...mrunnerCmds,
+		]
+	}, [platform, chromeCommands, installedAppCommands, folders, t])
 
 	const executeCommand = useCallback(
 		async (command: Command): Promise<CommandResult> => {
@@ -58,6 +66,7 @@ export function useCommands(): UseCommandsReturn {
 					case 'shell': {
 						const output = await invoke<string>('run_shell_command', {
 							command: action.command,
+							plugin: action.plugin,
 						})
 						return { success: true, output }
 					}
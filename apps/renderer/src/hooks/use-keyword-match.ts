@@ -0,0 +1,40 @@
+import { invoke } from '@tauri-apps/api/core'
+import { useEffect, useState } from 'react'
+
+import type { KeywordMatch } from '@/commands/types'
+import { DEBOUNCE_MS } from '@/lib/constants'
+
+/**
+ * The bookmark `query` opens when its first word is a bookmark keyword,
+ * e.g. `drs serde`, or null.
+ */
+export function useKeywordMatch(query: string): KeywordMatch | null {
+	const [match, setMatch] = useState<KeywordMatch | null>(null)
+
+	useEffect(() => {
+		if (!query.trim()) {
+			setMatch(null)
+			return
+		}
+
+		let cancelled = false
+		const timeout = setTimeout(async () => {
+			try {
+				const result = await invoke<KeywordMatch | null>(
+					'bookmark_resolve_keyword',
+					{ query },
+				)
+				if (!cancelled) setMatch(result)
+			} catch {
+				if (!cancelled) setMatch(null)
+			}
+		}, DEBOUNCE_MS)
+
+		return () => {
+			cancelled = true
+			clearTimeout(timeout)
+		}
+	}, [query])
+
+	return match
+}
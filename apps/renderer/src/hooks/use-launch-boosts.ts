@@ -0,0 +1,44 @@
+import { invoke } from '@tauri-apps/api/core'
+import { useEffect, useState } from 'react'
+
+import { DEBOUNCE_MS } from '@/lib/constants'
+import { type LaunchBoost, launchBoostKey } from '@/lib/launch-history'
+
+/**
+ * Frecency boosts for what was launched after typing `query`, by
+ * `launchBoostKey(kind, command ID)`, from 0 (never launched) to 1 (the most
+ * launched result for the query).
+ */
+export function useLaunchBoosts(query: string): Map<string, number> {
+	const [boosts, setBoosts] = useState<Map<string, number>>(() => new Map())
+
+	useEffect(() => {
+		let cancelled = false
+		const timeout = setTimeout(async () => {
+			try {
+				const results = await invoke<LaunchBoost[]>('launch_boosts', {
+					query,
+				})
+				if (!cancelled) {
+					setBoosts(
+						new Map(
+							results.map((b) => [
+								launchBoostKey(b.kind, b.command_id),
+								b.score,
+							]),
+						),
+					)
+				}
+			} catch {
+				if (!cancelled) setBoosts(new Map())
+			}
+		}, DEBOUNCE_MS)
+
+		return () => {
+			cancelled = true
+			clearTimeout(timeout)
+		}
+	}, [query])
+
+	return boosts
+}
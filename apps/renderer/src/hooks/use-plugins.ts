@@ -3,8 +3,9 @@ import { homeDir } from '@tauri-apps/api/path'
 import { readDir, readTextFile } from '@tauri-apps/plugin-fs'
 import { useCallback, useEffect, useState } from 'react'
 
-import type { Command } from '@/commands/types'
+import type { Command, CommandIcon } from '@/commands/types'
 import { pluginToCommand, safeParsePluginConfig } from '@/commands/types'
+import { ICON_MAP } from '@/lib/constants'
 import { createLogger } from '@/lib/logger'
 
 const logger = createLogger('plugins')
@@ -36,11 +37,15 @@ function scriptableCommandToCommand(
 	plugin: ScriptableRegisteredPlugin,
 	cmd: ScriptableRegisteredCommand,
 ): Command {
+	// Names outside MRunner's icon set are looked up in the icon theme
+	const builtIn = cmd.icon in ICON_MAP
+
 	return {
 		id: cmd.id,
 		name: cmd.title,
 		description: cmd.description || undefined,
-		icon: 'terminal',
+		icon: builtIn ? (cmd.icon as CommandIcon) : 'terminal',
+		themeIcon: builtIn || !cmd.icon ? undefined : cmd.icon,
 		group: plugin.pluginName,
 		keywords: cmd.keywords,
 		action: {
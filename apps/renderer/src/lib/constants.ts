@@ -32,6 +32,12 @@ import type { CommandIcon } from '@/commands/types'
 
 export const DEBOUNCE_MS = 300
 
+/** Bookmarks fetched per page; more load as the list is scrolled */
+export const BOOKMARK_PAGE_SIZE = 50
+
+/** Command ID of the result opening a bookmark keyword query */
+export const KEYWORD_COMMAND_ID = 'bookmark-keyword'
+
 export const SYSTEM_ICON_TO_COMMAND_ICON: Record<string, CommandIcon> = {
 	download: 'download',
 	'file-text': 'file-text',
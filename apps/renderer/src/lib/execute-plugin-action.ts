@@ -7,6 +7,8 @@ import { open } from '@tauri-apps/plugin-shell'
 export interface ExecuteActionOptions {
 	/** Called when a 'push' action should navigate to another plugin command. */
 	onPush?: (commandId: string) => void
+	/** ID of the plugin the action comes from, for its command policy rules and the audit log. */
+	pluginId?: string
 }
 
 /**
@@ -28,7 +30,10 @@ export async function executePluginAction(
 			await writeText(action.content)
 			break
 		case 'shell':
-			await invoke('run_shell_command', { command: action.command })
+			await invoke('run_shell_command', {
+				command: action.command,
+				plugin: options?.pluginId,
+			})
 			break
 		case 'notification':
 			await sendNotification({ title: action.title, body: action.message })
@@ -0,0 +1,56 @@
+import { invoke } from '@tauri-apps/api/core'
+
+import type { Command } from '@/core/types'
+import { createLogger } from '@/lib/logger'
+
+const logger = createLogger('history')
+
+export type LaunchKind =
+	| 'application'
+	| 'bookmark'
+	| 'plugin'
+	| 'folder'
+	| 'command'
+
+export interface LaunchBoost {
+	command_id: string
+	kind: LaunchKind
+	score: number
+}
+
+/**
+ * Key of a launch boost. Different kinds may share a command ID, so both
+ * are part of it.
+ */
+export function launchBoostKey(kind: LaunchKind, commandId: string): string {
+	return `${kind}:${commandId}`
+}
+
+export function launchKind(command: Command): LaunchKind {
+	if (command.id.startsWith('bookmark-')) return 'bookmark'
+	if (command.id.startsWith('app-desktop-')) return 'application'
+	if (command.id.startsWith('files-')) return 'folder'
+	if (
+		command.action.type === 'scriptable' ||
+		(command.action.type === 'shell' && command.action.plugin)
+	) {
+		return 'plugin'
+	}
+	return 'command'
+}
+
+/**
+ * Records a launch so later searches for the same query rank it higher.
+ * Never throws: a broken history must not stop the launch itself.
+ */
+export async function recordLaunch(
+	commandId: string,
+	kind: LaunchKind,
+	query: string,
+): Promise<void> {
+	try {
+		await invoke('record_launch', { commandId, kind, query })
+	} catch (err) {
+		logger.warn('Failed to record launch', { commandId, error: String(err) })
+	}
+}
@@ -0,0 +1,12 @@
+import { convertFileSrc } from '@tauri-apps/api/core'
+
+/**
+ * URL of an icon from the desktop's icon theme, served by the launcher's
+ * `icon://` protocol. `name` is a theme icon name such as `firefox` or
+ * `text-x-python`, or the absolute path of a PNG or SVG file. The launcher
+ * answers 404 when the icon can't be found.
+ */
+export function themeIconUrl(name: string, size = 32): string {
+	const scale = Math.max(1, Math.round(window.devicePixelRatio || 1))
+	return `${convertFileSrc(name, 'icon')}?size=${size}&scale=${scale}`
+}
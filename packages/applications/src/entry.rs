@@ -0,0 +1,515 @@
+//! Parsing of `.desktop` files as described by the freedesktop.org Desktop
+//! Entry Specification.
+
+use crate::error::ApplicationError;
+use crate::exec;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const MAIN_GROUP: &str = "Desktop Entry";
+const ACTION_GROUP_PREFIX: &str = "Desktop Action ";
+
+/// A locale in the `lang_COUNTRY.ENCODING@MODIFIER` form used by `LANG`
+/// and friends, for picking translated keys such as `Name[pt_BR]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Locale {
+    lang: String,
+    country: Option<String>,
+    modifier: Option<String>,
+}
+
+impl Locale {
+    /// Parses a locale name; the encoding is ignored. `C` and `POSIX` mean
+    /// untranslated.
+    pub fn parse(name: &str) -> Self {
+        let (rest, modifier) = match name.split_once('@') {
+            Some((rest, modifier)) => (rest, Some(modifier.to_string())),
+            None => (name, None),
+        };
+        let rest = rest.split('.').next().unwrap_or_default();
+        let (lang, country) = match rest.split_once('_') {
+            Some((lang, country)) => (lang, Some(country.to_string())),
+            None => (rest, None),
+        };
+
+        if lang.is_empty() || lang == "C" || lang == "POSIX" {
+            return Self::default();
+        }
+        Self {
+            lang: lang.to_string(),
+            country,
+            modifier,
+        }
+    }
+
+    /// The locale of messages for this process, from `LC_ALL`,
+    /// `LC_MESSAGES` or `LANG`.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// Suffixes to try, best match first: `lang_COUNTRY@MODIFIER`,
+    /// `lang_COUNTRY`, `lang@MODIFIER`, `lang`.
+    fn candidates(&self) -> Vec<String> {
+        if self.lang.is_empty() {
+            return Vec::new();
+        }
+
+        let mut candidates = Vec::new();
+        if let Some(country) = &self.country {
+            if let Some(modifier) = &self.modifier {
+                candidates.push(format!("{}_{}@{}", self.lang, country, modifier));
+            }
+            candidates.push(format!("{}_{}", self.lang, country));
+        }
+        if let Some(modifier) = &self.modifier {
+            candidates.push(format!("{}@{}", self.lang, modifier));
+        }
+        candidates.push(self.lang.clone());
+        candidates
+    }
+}
+
+/// A group of a key file, by key including any `[locale]` suffix.
+type Group = HashMap<String, String>;
+
+/// Splits a key file into its groups. Comments and blank lines are skipped;
+/// the first occurrence of a group or key wins.
+pub(crate) fn parse_groups(content: &str) -> Result<Vec<(String, Group)>, String> {
+    let mut groups: Vec<(String, Group)> = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .trim_end()
+                .strip_suffix(']')
+                .ok_or_else(|| format!("line {}: unterminated group header", number + 1))?;
+            groups.push((name.to_string(), Group::new()));
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected key=value", number + 1))?;
+        let (_, group) = groups
+            .last_mut()
+            .ok_or_else(|| format!("line {}: key outside of a group", number + 1))?;
+        group
+            .entry(key.trim_end().to_string())
+            .or_insert_with(|| value.trim_start().to_string());
+    }
+
+    Ok(groups)
+}
+
+/// Undoes the escapes allowed in key file values. For lists, the value is
+/// split on `;`, which is kept when escaped as `\;`.
+pub(crate) fn unescape(value: &str, list: bool) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('s') => current.push(' '),
+                Some('n') => current.push('\n'),
+                Some('t') => current.push('\t'),
+                Some('r') => current.push('\r'),
+                Some('\\') => current.push('\\'),
+                Some(';') if list => current.push(';'),
+                Some(other) => {
+                    current.push('\\');
+                    current.push(other);
+                }
+                None => current.push('\\'),
+            },
+            ';' if list => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+
+    if !list || !current.is_empty() {
+        items.push(current);
+    }
+    items
+}
+
+/// Typed access to one group of a key file.
+struct Keys<'a> {
+    group: &'a Group,
+    locales: &'a [String],
+}
+
+impl Keys<'_> {
+    fn raw(&self, key: &str) -> Option<&str> {
+        self.group.get(key).map(String::as_str)
+    }
+
+    /// The best translation of `key` for the locale.
+    fn localized_raw(&self, key: &str) -> Option<&str> {
+        self.locales
+            .iter()
+            .find_map(|locale| self.raw(&format!("{}[{}]", key, locale)))
+            .or_else(|| self.raw(key))
+    }
+
+    fn string(&self, key: &str) -> Option<String> {
+        self.raw(key)
+            .and_then(|v| unescape(v, false).pop())
+            .filter(|v| !v.is_empty())
+    }
+
+    fn localized(&self, key: &str) -> Option<String> {
+        self.localized_raw(key)
+            .and_then(|v| unescape(v, false).pop())
+            .filter(|v| !v.is_empty())
+    }
+
+    fn list(&self, key: &str) -> Vec<String> {
+        self.raw(key).map(|v| unescape(v, true)).unwrap_or_default()
+    }
+
+    fn localized_list(&self, key: &str) -> Vec<String> {
+        self.localized_raw(key)
+            .map(|v| unescape(v, true))
+            .unwrap_or_default()
+    }
+
+    fn boolean(&self, key: &str) -> bool {
+        self.raw(key).is_some_and(|v| v.trim() == "true")
+    }
+}
+
+/// An additional way to start an application, such as "New Private Window",
+/// from a `[Desktop Action ...]` group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DesktopAction {
+    pub id: String,
+    pub name: String,
+    pub icon: Option<String>,
+    pub exec: String,
+}
+
+/// An installed application described by a `.desktop` file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DesktopEntry {
+    /// Desktop file ID: the file's path below its `applications` directory
+    /// with `/` replaced by `-`, such as `org.gnome.Nautilus.desktop`.
+    pub id: String,
+    pub path: PathBuf,
+    /// Translated to the locale the entry was loaded with.
+    pub name: String,
+    pub generic_name: Option<String>,
+    pub comment: Option<String>,
+    pub keywords: Vec<String>,
+    /// Icon name in the icon theme, or an absolute path.
+    pub icon: Option<String>,
+    pub exec: String,
+    pub try_exec: Option<String>,
+    /// Directory to run the application in.
+    pub working_dir: Option<PathBuf>,
+    pub terminal: bool,
+    pub no_display: bool,
+    pub only_show_in: Vec<String>,
+    pub not_show_in: Vec<String>,
+    pub categories: Vec<String>,
+    pub actions: Vec<DesktopAction>,
+}
+
+impl DesktopEntry {
+    /// Reads the entry with desktop file ID `id` from `path`. Returns `None`
+    /// for entries that are not applications, such as links, and for
+    /// deleted (`Hidden`) entries.
+    pub fn load(id: &str, path: &Path, locale: &Locale) -> Result<Option<Self>, ApplicationError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::parse(id, path, &content, locale)
+    }
+
+    /// Parses the contents of a `.desktop` file; see [`Self::load`].
+    pub fn parse(
+        id: &str,
+        path: &Path,
+        content: &str,
+        locale: &Locale,
+    ) -> Result<Option<Self>, ApplicationError> {
+        let invalid = |message: String| ApplicationError::InvalidEntry {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        let groups = parse_groups(content).map_err(invalid)?;
+        let locales = locale.candidates();
+        let keys = |group| Keys {
+            group,
+            locales: &locales,
+        };
+
+        let main = groups
+            .iter()
+            .find(|(name, _)| name == MAIN_GROUP)
+            .map(|(_, group)| keys(group))
+            .ok_or_else(|| invalid(format!("missing [{}] group", MAIN_GROUP)))?;
+
+        if main.boolean("Hidden") || main.raw("Type") != Some("Application") {
+            return Ok(None);
+        }
+        let name = main
+            .localized("Name")
+            .ok_or_else(|| invalid("missing Name".to_string()))?;
+        // D-Bus activatable applications may leave out Exec, but MRunner
+        // starts everything through Exec
+        let exec = main
+            .string("Exec")
+            .ok_or_else(|| invalid("missing Exec".to_string()))?;
+
+        let actions = main
+            .list("Actions")
+            .into_iter()
+            .filter_map(|action| {
+                let group_name = format!("{}{}", ACTION_GROUP_PREFIX, action);
+                let (_, group) = groups.iter().find(|(name, _)| *name == group_name)?;
+                let keys = keys(group);
+                Some(DesktopAction {
+                    name: keys.localized("Name")?,
+                    icon: keys.string("Icon"),
+                    exec: keys.string("Exec")?,
+                    id: action,
+                })
+            })
+            .collect();
+
+        Ok(Some(Self {
+            id: id.to_string(),
+            path: path.to_path_buf(),
+            name,
+            generic_name: main.localized("GenericName"),
+            comment: main.localized("Comment"),
+            keywords: main.localized_list("Keywords"),
+            icon: main.localized("Icon"),
+            exec,
+            try_exec: main.string("TryExec"),
+            working_dir: main.string("Path").map(PathBuf::from),
+            terminal: main.boolean("Terminal"),
+            no_display: main.boolean("NoDisplay"),
+            only_show_in: main.list("OnlyShowIn"),
+            not_show_in: main.list("NotShowIn"),
+            categories: main.list("Categories"),
+            actions,
+        }))
+    }
+
+    /// Whether the entry belongs in a menu on a desktop identifying itself
+    /// as `desktops` (the `XDG_CURRENT_DESKTOP` names): it is not marked
+    /// `NoDisplay`, its `OnlyShowIn`/`NotShowIn` allow the desktop and its `TryExec`
+    /// program is installed.
+    pub fn should_show(&self, desktops: &[String]) -> bool {
+        if self.no_display {
+            return false;
+        }
+
+        let on_desktop = |list: &[String]| desktops.iter().any(|d| list.contains(d));
+        if !self.only_show_in.is_empty() && !on_desktop(&self.only_show_in) {
+            return false;
+        }
+        if on_desktop(&self.not_show_in) {
+            return false;
+        }
+
+        self.try_exec
+            .as_deref()
+            .is_none_or(|program| exec::find_program(program).is_some())
+    }
+
+    /// The command lines that start the application, or one of its actions,
+    /// opening `targets` (files or URLs); see [`Self::launch`].
+    pub fn command_lines(
+        &self,
+        action: Option<&str>,
+        targets: &[String],
+    ) -> Result<Vec<Vec<String>>, ApplicationError> {
+        let exec = match action {
+            Some(action) => &self.action(action)?.exec,
+            None => &self.exec,
+        };
+        let values = exec::FieldValues {
+            name: &self.name,
+            icon: self.icon.as_deref(),
+            desktop_file: &self.path,
+        };
+
+        let lines = exec::command_lines(exec, &values, targets)?;
+        Ok(if self.terminal {
+            lines.into_iter().map(exec::in_terminal).collect()
+        } else {
+            lines
+        })
+    }
+
+    /// Starts the application, or one of its actions, opening `targets`.
+    /// Applications that take a single file are started once per target.
+    pub fn launch(&self, action: Option<&str>, targets: &[String]) -> Result<(), ApplicationError> {
+        for argv in self.command_lines(action, targets)? {
+            exec::spawn(&argv, self.working_dir.as_deref())?;
+        }
+        Ok(())
+    }
+
+    /// The action with id `action`.
+    pub fn action(&self, action: &str) -> Result<&DesktopAction, ApplicationError> {
+        self.actions.iter().find(|a| a.id == action).ok_or_else(|| {
+            ApplicationError::ActionNotFound {
+                app: self.id.clone(),
+                action: action.to_string(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIREFOX: &str = r#"
+# A comment
+[Desktop Entry]
+Version=1.0
+Type=Application
+Name=Firefox
+Name[de]=Firefox-Browser
+Name[pt_BR]=Navegador Firefox
+GenericName=Web Browser
+Comment=Browse the\sWorld Wide Web
+Keywords=Internet;WWW;Browser;Web\;Explorer;
+Keywords[de]=Internet;Netz;
+Exec=firefox %u
+Icon=firefox
+Terminal=false
+Categories=Network;WebBrowser;
+Actions=new-window;private;missing;
+
+[Desktop Action new-window]
+Name=New Window
+Name[de]=Neues Fenster
+Exec=firefox --new-window %u
+
+[Desktop Action private]
+Name=New Private Window
+Exec=firefox --private-window %u
+"#;
+
+    fn parse(content: &str, locale: &str) -> Option<DesktopEntry> {
+        DesktopEntry::parse(
+            "firefox.desktop",
+            Path::new("/usr/share/applications/firefox.desktop"),
+            content,
+            &Locale::parse(locale),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parses_keys_lists_and_actions() {
+        let entry = parse(FIREFOX, "C").unwrap();
+        assert_eq!(entry.name, "Firefox");
+        assert_eq!(entry.generic_name.as_deref(), Some("Web Browser"));
+        assert_eq!(entry.comment.as_deref(), Some("Browse the World Wide Web"));
+        assert_eq!(
+            entry.keywords,
+            ["Internet", "WWW", "Browser", "Web;Explorer"]
+        );
+        assert_eq!(entry.exec, "firefox %u");
+        assert_eq!(entry.categories, ["Network", "WebBrowser"]);
+        assert!(!entry.terminal);
+
+        // Actions without a group are ignored
+        let actions: Vec<&str> = entry.actions.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(actions, ["new-window", "private"]);
+        assert_eq!(
+            entry.action("private").unwrap().exec,
+            "firefox --private-window %u"
+        );
+        assert!(entry.action("missing").is_err());
+    }
+
+    #[test]
+    fn test_picks_best_translation() {
+        let entry = parse(FIREFOX, "de_AT.UTF-8").unwrap();
+        assert_eq!(entry.name, "Firefox-Browser");
+        assert_eq!(entry.keywords, ["Internet", "Netz"]);
+        assert_eq!(entry.actions[0].name, "Neues Fenster");
+        assert_eq!(entry.actions[1].name, "New Private Window");
+
+        assert_eq!(
+            parse(FIREFOX, "pt_BR.UTF-8").unwrap().name,
+            "Navegador Firefox"
+        );
+        assert_eq!(parse(FIREFOX, "pt_PT").unwrap().name, "Firefox");
+        assert_eq!(
+            Locale::parse("sr_RS.UTF-8@latin").candidates(),
+            ["sr_RS@latin", "sr_RS", "sr@latin", "sr"]
+        );
+    }
+
+    #[test]
+    fn test_skips_non_applications_and_hidden_entries() {
+        let link = "[Desktop Entry]\nType=Link\nName=Docs\nURL=https://example.com\n";
+        assert_eq!(parse(link, "C"), None);
+
+        let hidden = "[Desktop Entry]\nHidden=true\n";
+        assert_eq!(parse(hidden, "C"), None);
+
+        let no_exec = "[Desktop Entry]\nType=Application\nName=Broken\n";
+        let result = DesktopEntry::parse("x.desktop", Path::new("x"), no_exec, &Locale::default());
+        assert!(matches!(result, Err(ApplicationError::InvalidEntry { .. })));
+    }
+
+    #[test]
+    fn test_visibility() {
+        let entry = parse(FIREFOX, "C").unwrap();
+        let desktops = vec!["GNOME".to_string()];
+        assert!(entry.should_show(&desktops));
+
+        let only_kde = DesktopEntry {
+            only_show_in: vec!["KDE".to_string()],
+            ..entry.clone()
+        };
+        assert!(!only_kde.should_show(&desktops));
+        assert!(only_kde.should_show(&["ubuntu".to_string(), "KDE".to_string()]));
+
+        let not_gnome = DesktopEntry {
+            not_show_in: vec!["GNOME".to_string()],
+            ..entry.clone()
+        };
+        assert!(!not_gnome.should_show(&desktops));
+
+        let no_display = DesktopEntry {
+            no_display: true,
+            ..entry.clone()
+        };
+        assert!(!no_display.should_show(&desktops));
+
+        let missing_program = DesktopEntry {
+            try_exec: Some("/nonexistent/mrunner-test-program".to_string()),
+            ..entry.clone()
+        };
+        assert!(!missing_program.should_show(&desktops));
+
+        let installed_program = DesktopEntry {
+            try_exec: Some("sh".to_string()),
+            ..entry
+        };
+        assert!(installed_program.should_show(&desktops));
+    }
+}
@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ApplicationError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid desktop entry {path}: {message}")]
+    InvalidEntry { path: PathBuf, message: String },
+
+    #[error("Application not found: {0}")]
+    NotFound(String),
+
+    #[error("Application {app} has no action {action}")]
+    ActionNotFound { app: String, action: String },
+
+    #[error("Invalid Exec line {0:?}")]
+    InvalidExec(String),
+
+    #[error("Failed to launch {0}: {1}")]
+    Launch(String, String),
+
+    #[error("Invalid icon {path}: {message}")]
+    InvalidIcon { path: PathBuf, message: String },
+}
@@ -0,0 +1,341 @@
+//! `Exec` lines: splitting into arguments, field code expansion and
+//! starting the resulting processes.
+
+use crate::error::ApplicationError;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// One argument of an `Exec` line. Field codes are only expanded in
+/// unquoted arguments.
+#[derive(Debug, PartialEq)]
+struct Arg {
+    text: String,
+    quoted: bool,
+}
+
+/// Splits an `Exec` value on spaces. Double quotes group an argument, and
+/// inside them a backslash escapes `"`, `` ` ``, `$` and `\`.
+fn split(exec: &str) -> Result<Vec<Arg>, ApplicationError> {
+    let mut args = Vec::new();
+    let mut current: Option<Arg> = None;
+    let mut in_quotes = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current
+                    .get_or_insert_with(|| Arg {
+                        text: String::new(),
+                        quoted: true,
+                    })
+                    .quoted = true;
+            }
+            '\\' if in_quotes => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| ApplicationError::InvalidExec(exec.to_string()))?;
+                let arg = current.as_mut().expect("quotes open an argument");
+                if !matches!(escaped, '"' | '`' | '$' | '\\') {
+                    arg.text.push('\\');
+                }
+                arg.text.push(escaped);
+            }
+            ' ' | '\t' if !in_quotes => args.extend(current.take()),
+            _ => {
+                current
+                    .get_or_insert_with(|| Arg {
+                        text: String::new(),
+                        quoted: in_quotes,
+                    })
+                    .text
+                    .push(c);
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err(ApplicationError::InvalidExec(exec.to_string()));
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+/// What field codes expand to, besides the files or URLs being opened.
+pub(crate) struct FieldValues<'a> {
+    /// `%c`: the translated application name.
+    pub name: &'a str,
+    /// `%i`: the `Icon` key.
+    pub icon: Option<&'a str>,
+    /// `%k`: the desktop file.
+    pub desktop_file: &'a Path,
+}
+
+/// Decodes `%XX` escapes in the path of a `file://` URL.
+fn decode_percent(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// `target` as a local path for `%f`/`%F`, `None` for remote URLs.
+fn as_file(target: &str) -> Option<String> {
+    match target.split_once("://") {
+        Some(("file", rest)) => {
+            // Skip the host part, normally empty or `localhost`
+            let path = &rest[rest.find('/')?..];
+            Some(decode_percent(path))
+        }
+        Some(_) => None,
+        None => Some(target.to_string()),
+    }
+}
+
+/// Expands the field codes of `args` for `targets`, at most one of which
+/// may be taken by `%f` or `%u`.
+fn expand(
+    args: &[Arg],
+    values: &FieldValues,
+    targets: &[String],
+) -> Result<Vec<String>, ApplicationError> {
+    let files = || targets.iter().filter_map(|t| as_file(t));
+    let mut argv = Vec::new();
+
+    for arg in args {
+        if arg.quoted {
+            argv.push(arg.text.clone());
+            continue;
+        }
+
+        match arg.text.as_str() {
+            "%F" => argv.extend(files()),
+            "%U" => argv.extend(targets.iter().cloned()),
+            "%i" => {
+                if let Some(icon) = values.icon {
+                    argv.extend(["--icon".to_string(), icon.to_string()]);
+                }
+            }
+            text => {
+                let mut expanded = String::new();
+                let mut only_codes = true;
+                let mut chars = text.chars();
+                while let Some(c) = chars.next() {
+                    if c != '%' {
+                        expanded.push(c);
+                        only_codes = false;
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('%') => {
+                            expanded.push('%');
+                            only_codes = false;
+                        }
+                        Some('f') => expanded.extend(files().next()),
+                        Some('u') => expanded.extend(targets.first().cloned()),
+                        Some('c') => expanded.push_str(values.name),
+                        Some('k') => expanded.push_str(&values.desktop_file.to_string_lossy()),
+                        Some('i') => expanded.extend(values.icon),
+                        // Deprecated codes expand to nothing
+                        Some('d' | 'D' | 'n' | 'N' | 'v' | 'm') => {}
+                        _ => return Err(ApplicationError::InvalidExec(text.to_string())),
+                    }
+                }
+                // An argument made only of codes that expanded to nothing
+                // is left out rather than passed as ""
+                if !(only_codes && expanded.is_empty()) {
+                    argv.push(expanded);
+                }
+            }
+        }
+    }
+
+    Ok(argv)
+}
+
+/// The command lines that open `targets` (files or URLs) with `exec`. An
+/// application taking a single file (`%f`, `%u`) is started once per
+/// target; one taking a list (`%F`, `%U`) or none at all is started once.
+pub(crate) fn command_lines(
+    exec: &str,
+    values: &FieldValues,
+    targets: &[String],
+) -> Result<Vec<Vec<String>>, ApplicationError> {
+    let args = split(exec)?;
+    let single = args
+        .iter()
+        .any(|a| !a.quoted && (a.text.contains("%f") || a.text.contains("%u")));
+
+    let lines = if single && targets.len() > 1 {
+        targets
+            .iter()
+            .map(|target| expand(&args, values, std::slice::from_ref(target)))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![expand(&args, values, targets)?]
+    };
+
+    if lines.iter().any(|argv| argv.is_empty()) {
+        return Err(ApplicationError::InvalidExec(exec.to_string()));
+    }
+    Ok(lines)
+}
+
+/// Finds `program` the way `TryExec` is checked: as given if it contains a
+/// `/`, otherwise in `PATH`. Only executable files count.
+pub(crate) fn find_program(program: &str) -> Option<PathBuf> {
+    let executable = |path: &Path| {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            path.metadata()
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        }
+        #[cfg(not(unix))]
+        {
+            path.is_file()
+        }
+    };
+
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return executable(&path).then_some(path);
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| executable(path))
+}
+
+/// The command line running `argv` in a terminal emulator: `$TERMINAL`, or
+/// the Debian-style `x-terminal-emulator` alternative.
+pub(crate) fn in_terminal(argv: Vec<String>) -> Vec<String> {
+    let terminal = std::env::var("TERMINAL")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "x-terminal-emulator".to_string());
+    [terminal, "-e".to_string()]
+        .into_iter()
+        .chain(argv)
+        .collect()
+}
+
+/// Starts `argv` detached from MRunner, in `dir` when it exists.
+pub(crate) fn spawn(argv: &[String], dir: Option<&Path>) -> Result<(), ApplicationError> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| ApplicationError::InvalidExec(String::new()))?;
+
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(dir) = dir.filter(|d| d.is_dir()) {
+        command.current_dir(dir);
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| ApplicationError::Launch(program.clone(), e.to_string()))?;
+    // Reap the process when it exits so it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(exec: &str, targets: &[&str]) -> Vec<Vec<String>> {
+        let values = FieldValues {
+            name: "Text Editor",
+            icon: Some("accessories-text-editor"),
+            desktop_file: Path::new("/usr/share/applications/editor.desktop"),
+        };
+        let targets: Vec<String> = targets.iter().map(|t| t.to_string()).collect();
+        command_lines(exec, &values, &targets).unwrap()
+    }
+
+    #[test]
+    fn test_splits_quoted_arguments() {
+        assert_eq!(
+            lines(r#"sh -c "echo \"\$HOME\" \\ done" 'x'"#, &[]),
+            [["sh", "-c", r#"echo "$HOME" \ done"#, "'x'"]]
+        );
+        assert_eq!(
+            lines(r#"app --title="My %c" """#, &[]),
+            [["app", "--title=My %c", ""]]
+        );
+
+        let values = FieldValues {
+            name: "",
+            icon: None,
+            desktop_file: Path::new(""),
+        };
+        assert!(command_lines(r#"app "open"#, &values, &[]).is_err());
+        assert!(command_lines("app %z", &values, &[]).is_err());
+        assert!(command_lines("%f", &values, &[]).is_err());
+    }
+
+    #[test]
+    fn test_expands_field_codes() {
+        assert_eq!(
+            lines("editor %i --name=%c %k 100%%", &[]),
+            [[
+                "editor",
+                "--icon",
+                "accessories-text-editor",
+                "--name=Text Editor",
+                "/usr/share/applications/editor.desktop",
+                "100%",
+            ]]
+        );
+
+        // Codes without a target are left out
+        assert_eq!(lines("editor %F %U %f %u %d", &[]), [["editor"]]);
+
+        assert_eq!(
+            lines(
+                "editor %F",
+                &["/tmp/a b.txt", "file:///tmp/c%20d.txt", "https://x.org/"]
+            ),
+            [["editor", "/tmp/a b.txt", "/tmp/c d.txt"]]
+        );
+        assert_eq!(
+            lines("browser %U", &["https://x.org/", "/tmp/a"]),
+            [["browser", "https://x.org/", "/tmp/a"]]
+        );
+    }
+
+    #[test]
+    fn test_single_target_codes_start_one_process_per_target() {
+        assert_eq!(
+            lines("viewer --file=%f", &["/a.png", "/b.png"]),
+            [["viewer", "--file=/a.png"], ["viewer", "--file=/b.png"]]
+        );
+        assert_eq!(
+            lines("browser %u", &["https://x.org/"]),
+            [["browser", "https://x.org/"]]
+        );
+        // Applications that take no files just start
+        assert_eq!(lines("calculator", &["/a.png", "/b.png"]), [["calculator"]]);
+    }
+}
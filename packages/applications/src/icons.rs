@@ -0,0 +1,459 @@
+//! Icon lookup following the freedesktop Icon Theme specification.
+
+use crate::entry::parse_groups;
+use crate::index::data_dirs;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Image types looked up, in order of preference. XPM is left out since
+/// the launcher's webview cannot show it.
+const EXTENSIONS: [&str; 2] = ["png", "svg"];
+
+/// The theme every other theme falls back to.
+const FALLBACK_THEME: &str = "hicolor";
+
+/// Directories holding icon themes and unthemed icons, most important
+/// first: `~/.icons`, the `icons` directories of the XDG data directories,
+/// then their `pixmaps` directories.
+pub fn icon_dirs() -> Vec<PathBuf> {
+    let data_dirs = data_dirs();
+    dirs::home_dir()
+        .map(|home| home.join(".icons"))
+        .into_iter()
+        .chain(data_dirs.iter().map(|dir| dir.join("icons")))
+        .chain(data_dirs.iter().map(|dir| dir.join("pixmaps")))
+        .collect()
+}
+
+/// The icon theme picked in the desktop's settings: GTK's
+/// `gtk-icon-theme-name`, which GNOME, Xfce and others keep up to date, or
+/// KDE's `[Icons] Theme`.
+pub fn current_theme() -> Option<String> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+
+    let setting = |file: &str, group: &str, key: &str| {
+        let content = std::fs::read_to_string(config.join(file)).ok()?;
+        let groups = parse_groups(&content).ok()?;
+        let (_, values) = groups.into_iter().find(|(name, _)| name == group)?;
+        let value = values.get(key)?.trim().trim_matches('"');
+        (!value.is_empty()).then(|| value.to_string())
+    };
+
+    setting("gtk-4.0/settings.ini", "Settings", "gtk-icon-theme-name")
+        .or_else(|| setting("gtk-3.0/settings.ini", "Settings", "gtk-icon-theme-name"))
+        .or_else(|| setting("kdeglobals", "Icons", "Theme"))
+}
+
+/// The icon names for files of `mime_type`, most specific first: the type
+/// itself (`text-x-python`), then the generic icon of its media type
+/// (`text-x-generic`).
+fn mime_icon_names(mime_type: &str) -> Vec<String> {
+    let mut names = vec![mime_type.replace('/', "-")];
+    if let Some((media, _)) = mime_type.split_once('/') {
+        names.push(format!("{}-x-generic", media));
+    }
+    names
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SizeType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// A subdirectory of a theme and the icon sizes it holds.
+#[derive(Debug)]
+struct ThemeDir {
+    path: String,
+    size: u32,
+    scale: u32,
+    kind: SizeType,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+}
+
+impl ThemeDir {
+    /// The range of sizes the icons in this directory are meant for.
+    fn sizes(&self) -> (u32, u32) {
+        match self.kind {
+            SizeType::Fixed => (self.size, self.size),
+            SizeType::Scalable => (self.min_size, self.max_size),
+            SizeType::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size + self.threshold,
+            ),
+        }
+    }
+
+    fn matches(&self, size: u32, scale: u32) -> bool {
+        let (min, max) = self.sizes();
+        self.scale == scale && (min..=max).contains(&size)
+    }
+
+    /// How far off this directory's icons are, in device pixels.
+    fn distance(&self, size: u32, scale: u32) -> u32 {
+        let (min, max) = self.sizes();
+        let (min, max, wanted) = (min * self.scale, max * self.scale, size * scale);
+        if wanted < min {
+            min - wanted
+        } else {
+            wanted.saturating_sub(max)
+        }
+    }
+}
+
+/// A theme's `index.theme`.
+#[derive(Debug)]
+struct Theme {
+    name: String,
+    dirs: Vec<ThemeDir>,
+    inherits: Vec<String>,
+}
+
+impl Theme {
+    /// Reads the first `index.theme` of theme `name` found in `base_dirs`.
+    fn load(base_dirs: &[PathBuf], name: &str) -> Option<Self> {
+        let content = base_dirs
+            .iter()
+            .find_map(|dir| std::fs::read_to_string(dir.join(name).join("index.theme")).ok())?;
+        Self::parse(name, &content)
+    }
+
+    fn parse(name: &str, content: &str) -> Option<Self> {
+        let groups: HashMap<_, _> = parse_groups(content).ok()?.into_iter().collect();
+        let header = groups.get("Icon Theme")?;
+        let list = |key: &str| -> Vec<String> {
+            header
+                .get(key)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut names = list("Directories");
+        names.extend(list("ScaledDirectories"));
+        let dirs = names
+            .into_iter()
+            .filter_map(|path| {
+                let group = groups.get(&path)?;
+                let number = |key: &str| group.get(key).and_then(|v| v.trim().parse::<u32>().ok());
+                let size = number("Size")?;
+                let kind = match group.get("Type").map(|t| t.trim()) {
+                    Some("Fixed") => SizeType::Fixed,
+                    Some("Scalable") => SizeType::Scalable,
+                    _ => SizeType::Threshold,
+                };
+                Some(ThemeDir {
+                    size,
+                    scale: number("Scale").unwrap_or(1).max(1),
+                    kind,
+                    min_size: number("MinSize").unwrap_or(size),
+                    max_size: number("MaxSize").unwrap_or(size),
+                    threshold: number("Threshold").unwrap_or(2),
+                    path,
+                })
+            })
+            .collect();
+
+        Some(Self {
+            name: name.to_string(),
+            dirs,
+            inherits: list("Inherits"),
+        })
+    }
+}
+
+/// Finds icon files by name in the current icon theme, the themes it
+/// inherits from and hicolor. Themes are read on first use and results are
+/// remembered until [`Self::clear_cache`].
+#[derive(Debug)]
+pub struct IconResolver {
+    base_dirs: Vec<PathBuf>,
+    /// The theme set with [`Self::with_theme`], or else the desktop's, which
+    /// [`Self::clear_cache`] reads again.
+    theme: Mutex<String>,
+    follows_desktop: bool,
+    /// The theme followed by those it inherits from, once read.
+    themes: Mutex<Option<Vec<Theme>>>,
+    /// Earlier results by name, size and scale.
+    found: Mutex<HashMap<(String, u32, u32), Option<PathBuf>>>,
+}
+
+impl Default for IconResolver {
+    fn default() -> Self {
+        Self {
+            base_dirs: icon_dirs(),
+            theme: Mutex::new(desktop_theme()),
+            follows_desktop: true,
+            themes: Mutex::new(None),
+            found: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// The desktop's icon theme, or hicolor when none is set.
+fn desktop_theme() -> String {
+    current_theme().unwrap_or_else(|| FALLBACK_THEME.to_string())
+}
+
+impl IconResolver {
+    /// Uses the icon directories and theme of the current user.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Directories to search, most important first.
+    pub fn with_base_dirs(mut self, base_dirs: Vec<PathBuf>) -> Self {
+        self.base_dirs = base_dirs;
+        self.clear_cache();
+        self
+    }
+
+    /// Uses `theme` instead of the desktop's, even after a cache clear.
+    pub fn with_theme(mut self, theme: &str) -> Self {
+        self.theme = Mutex::new(theme.to_string());
+        self.follows_desktop = false;
+        self.clear_cache();
+        self
+    }
+
+    pub fn theme(&self) -> String {
+        self.theme
+            .lock()
+            .map(|theme| theme.clone())
+            .unwrap_or_else(|_| FALLBACK_THEME.to_string())
+    }
+
+    /// Forgets earlier results and the themes read, so icons and themes
+    /// installed since, or a theme newly picked on the desktop, are used.
+    pub fn clear_cache(&self) {
+        if self.follows_desktop {
+            if let Ok(mut theme) = self.theme.lock() {
+                *theme = desktop_theme();
+            }
+        }
+        if let Ok(mut themes) = self.themes.lock() {
+            *themes = None;
+        }
+        if let Ok(mut found) = self.found.lock() {
+            found.clear();
+        }
+    }
+
+    /// Reads the current theme followed by those it inherits from, depth
+    /// first, and hicolor.
+    fn load_themes(&self) -> Vec<Theme> {
+        let mut themes: Vec<Theme> = Vec::new();
+        let mut pending = vec![self.theme(), FALLBACK_THEME.to_string()];
+        pending.reverse();
+        while let Some(name) = pending.pop() {
+            if themes.iter().any(|theme| theme.name == name) {
+                continue;
+            }
+            if let Some(theme) = Theme::load(&self.base_dirs, &name) {
+                pending.extend(theme.inherits.iter().rev().cloned());
+                themes.push(theme);
+            }
+        }
+        themes
+    }
+
+    /// The file for icon `name` shown at `size` logical pixels on a display
+    /// scaled by `scale`: the closest match in the first theme that has the
+    /// icon, or else an unthemed icon. `name` may also be the absolute path
+    /// of a PNG or SVG file, as desktop entries' `Icon` key allows.
+    pub fn lookup(&self, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let path = Path::new(name);
+        if path.is_absolute() {
+            let supported = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| EXTENSIONS.contains(&ext));
+            return (supported && path.is_file()).then(|| path.to_path_buf());
+        }
+        // Some desktop entries name the icon with its extension
+        let name = EXTENSIONS
+            .iter()
+            .find_map(|ext| name.strip_suffix(&format!(".{}", ext)))
+            .unwrap_or(name);
+        if name.is_empty() || name.contains('/') {
+            return None;
+        }
+
+        let key = (name.to_string(), size, scale.max(1));
+        if let Some(found) = self.found.lock().ok().and_then(|f| f.get(&key).cloned()) {
+            return found;
+        }
+
+        let themed = match self.themes.lock() {
+            Ok(mut themes) => themes
+                .get_or_insert_with(|| self.load_themes())
+                .iter()
+                .find_map(|theme| self.lookup_in(theme, name, size, scale.max(1))),
+            Err(_) => None,
+        };
+        let found = themed.or_else(|| self.base_dirs.iter().find_map(|dir| icon_file(dir, name)));
+        if let Ok(mut cache) = self.found.lock() {
+            cache.insert(key, found.clone());
+        }
+        found
+    }
+
+    /// The icon for files of `mime_type`, such as `text/x-python`, falling
+    /// back to the generic icon of its media type.
+    pub fn lookup_mime(&self, mime_type: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        mime_icon_names(mime_type)
+            .iter()
+            .find_map(|name| self.lookup(name, size, scale))
+    }
+
+    /// An icon of `theme` in a directory matching the size, or else the one
+    /// in the directory closest to it.
+    fn lookup_in(&self, theme: &Theme, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let roots: Vec<PathBuf> = self
+            .base_dirs
+            .iter()
+            .map(|dir| dir.join(&theme.name))
+            .filter(|dir| dir.is_dir())
+            .collect();
+
+        let mut closest: Option<(u32, PathBuf)> = None;
+        for dir in &theme.dirs {
+            let exact = dir.matches(size, scale);
+            let distance = dir.distance(size, scale);
+            if !exact && closest.as_ref().is_some_and(|(d, _)| *d <= distance) {
+                continue;
+            }
+            if let Some(file) = roots
+                .iter()
+                .find_map(|root| icon_file(&root.join(&dir.path), name))
+            {
+                if exact {
+                    return Some(file);
+                }
+                closest = Some((distance, file));
+            }
+        }
+        closest.map(|(_, file)| file)
+    }
+}
+
+/// `name` with one of the supported extensions in `dir`.
+fn icon_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(path: PathBuf, content: &str) -> PathBuf {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_lookup_follows_the_icon_theme_spec() {
+        let (user, system) = (tempdir().unwrap(), tempdir().unwrap());
+        let (user, system) = (user.path(), system.path());
+        write(
+            system.join("Test/index.theme"),
+            "[Icon Theme]\nName=Test\nInherits=Base\n\
+             Directories=16x16/apps,48x48/apps,scalable/apps\n\
+             ScaledDirectories=16x16@2/apps\n\n\
+             [16x16/apps]\nSize=16\nType=Fixed\n\n\
+             [16x16@2/apps]\nSize=16\nScale=2\nType=Fixed\n\n\
+             [48x48/apps]\nSize=48\n\n\
+             [scalable/apps]\nSize=64\nType=Scalable\nMinSize=64\nMaxSize=256\n",
+        );
+        write(
+            system.join("Base/index.theme"),
+            "[Icon Theme]\nDirectories=apps\n\n[apps]\nSize=32\nType=Fixed\n",
+        );
+        write(
+            system.join("hicolor/index.theme"),
+            "[Icon Theme]\nDirectories=48x48/apps,48x48/mimetypes\n\n\
+             [48x48/apps]\nSize=48\nType=Fixed\n\n\
+             [48x48/mimetypes]\nSize=48\nType=Fixed\n",
+        );
+
+        let small = write(system.join("Test/16x16/apps/editor.png"), "");
+        let hidpi = write(system.join("Test/16x16@2/apps/editor.png"), "");
+        let medium = write(system.join("Test/48x48/apps/editor.png"), "");
+        let vector = write(system.join("Test/scalable/apps/editor.svg"), "");
+        // The theme's directories are searched in every base directory
+        let user_icon = write(user.join("Test/48x48/apps/terminal.svg"), "");
+        let inherited = write(system.join("Base/apps/files.png"), "");
+        let hicolor = write(system.join("hicolor/48x48/apps/game.png"), "");
+        let mime = write(
+            system.join("hicolor/48x48/mimetypes/text-x-generic.png"),
+            "",
+        );
+        let pixmap = write(system.join("legacy.png"), "");
+
+        let resolver = IconResolver::new()
+            .with_base_dirs(vec![user.to_path_buf(), system.to_path_buf()])
+            .with_theme("Test");
+        assert_eq!(resolver.lookup("editor", 16, 1), Some(small.clone()));
+        assert_eq!(resolver.lookup("editor", 16, 2), Some(hidpi));
+        // Threshold directories accept sizes close to theirs
+        assert_eq!(resolver.lookup("editor", 50, 1), Some(medium));
+        assert_eq!(resolver.lookup("editor", 128, 1), Some(vector.clone()));
+        // Otherwise the closest size wins
+        assert_eq!(resolver.lookup("editor", 24, 1), Some(small));
+        assert_eq!(resolver.lookup("editor", 512, 1), Some(vector.clone()));
+        assert_eq!(resolver.lookup("editor.svg", 128, 1), Some(vector));
+
+        assert_eq!(resolver.lookup("terminal", 48, 1), Some(user_icon));
+        assert_eq!(resolver.lookup("files", 48, 1), Some(inherited));
+        assert_eq!(resolver.lookup("game", 16, 1), Some(hicolor));
+        assert_eq!(resolver.lookup("legacy", 16, 1), Some(pixmap.clone()));
+        assert_eq!(
+            resolver.lookup(pixmap.to_str().unwrap(), 16, 1),
+            Some(pixmap)
+        );
+        assert_eq!(resolver.lookup("missing", 48, 1), None);
+        assert_eq!(resolver.lookup("/etc/passwd", 16, 1), None);
+        assert_eq!(resolver.lookup("../Test/16x16/apps/editor", 16, 1), None);
+
+        assert_eq!(resolver.lookup_mime("text/x-python", 48, 1), Some(mime));
+
+        // Results are remembered until the cache is cleared
+        let added = write(system.join("Test/48x48/apps/missing.png"), "");
+        assert_eq!(resolver.lookup("missing", 48, 1), None);
+        resolver.clear_cache();
+        assert_eq!(resolver.lookup("missing", 48, 1), Some(added));
+
+        // Clearing the cache also reads the themes again
+        write(
+            system.join("Test/index.theme"),
+            "[Icon Theme]\nInherits=Extra\nDirectories=48x48/apps\n\n\
+             [48x48/apps]\nSize=48\nType=Fixed\n",
+        );
+        write(
+            system.join("Extra/index.theme"),
+            "[Icon Theme]\nDirectories=apps\n\n[apps]\nSize=48\nType=Fixed\n",
+        );
+        let extra = write(system.join("Extra/apps/mail.png"), "");
+        resolver.clear_cache();
+        assert_eq!(resolver.lookup("mail", 48, 1), Some(extra));
+        assert_eq!(resolver.theme(), "Test");
+    }
+}
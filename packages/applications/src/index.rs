@@ -0,0 +1,207 @@
+//! Discovery of the applications installed for the current user.
+
+use crate::entry::{DesktopEntry, Locale};
+use crate::error::ApplicationError;
+use crate::live::LiveIndex;
+use std::path::{Path, PathBuf};
+
+/// The XDG base data directories, most important first: `$XDG_DATA_HOME`
+/// (`~/.local/share`) followed by `$XDG_DATA_DIRS`
+/// (`/usr/local/share:/usr/share`).
+pub(crate) fn data_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("share")));
+    let data_dirs = std::env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+
+    data_home
+        .into_iter()
+        .chain(std::env::split_paths(&data_dirs).filter(|p| p.is_absolute()))
+        .collect()
+}
+
+/// The `applications` directories of the XDG base directories, most
+/// important first.
+pub fn application_dirs() -> Vec<PathBuf> {
+    data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// The desktop environment names in `XDG_CURRENT_DESKTOP`, matched against
+/// `OnlyShowIn` and `NotShowIn`.
+pub fn current_desktops() -> Vec<String> {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|d| !d.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Finds the `.desktop` files below `dir` with their desktop file IDs.
+pub(crate) fn desktop_files(dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if path.is_dir() {
+            desktop_files(&path, &format!("{}{}-", prefix, name), files);
+        } else if name.ends_with(".desktop") {
+            files.push((format!("{}{}", prefix, name), path));
+        }
+    }
+}
+
+/// Reads the desktop entries of the installed applications.
+#[derive(Debug, Clone)]
+pub struct ApplicationIndex {
+    dirs: Vec<PathBuf>,
+    locale: Locale,
+    desktops: Vec<String>,
+}
+
+impl Default for ApplicationIndex {
+    fn default() -> Self {
+        Self {
+            dirs: application_dirs(),
+            locale: Locale::from_env(),
+            desktops: current_desktops(),
+        }
+    }
+}
+
+impl ApplicationIndex {
+    /// Uses the XDG directories, locale and desktop of this process.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Directories to search, most important first.
+    pub fn with_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.dirs = dirs;
+        self
+    }
+
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    pub fn with_desktops(mut self, desktops: Vec<String>) -> Self {
+        self.desktops = desktops;
+        self
+    }
+
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    pub fn locale(&self) -> &Locale {
+        &self.locale
+    }
+
+    pub fn desktops(&self) -> &[String] {
+        &self.desktops
+    }
+
+    /// Every application entry, including those not meant for menus; see
+    /// [`LiveIndex::entries`].
+    pub fn entries(&self) -> Vec<DesktopEntry> {
+        self.scan().entries().into_iter().cloned().collect()
+    }
+
+    /// The applications to offer in the launcher, sorted by name.
+    pub fn applications(&self) -> Vec<DesktopEntry> {
+        self.scan().applications()
+    }
+
+    /// The entry with desktop file ID `id`, shown in menus or not.
+    pub fn find(&self, id: &str) -> Result<DesktopEntry, ApplicationError> {
+        self.scan()
+            .find(id)
+            .cloned()
+            .ok_or_else(|| ApplicationError::NotFound(id.to_string()))
+    }
+
+    fn scan(&self) -> LiveIndex {
+        let mut live = LiveIndex::new(self.clone());
+        live.refresh();
+        live
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(dir: &Path, name: &str, content: &str) {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn app(name: &str) -> String {
+        format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={}\n",
+            name,
+            name.to_lowercase()
+        )
+    }
+
+    #[test]
+    fn test_ids_shadowing_and_visibility() {
+        let (user, system) = (tempdir().unwrap(), tempdir().unwrap());
+        write(system.path(), "editor.desktop", &app("Editor"));
+        write(system.path(), "kde/konsole.desktop", &app("Konsole"));
+        write(system.path(), "removed.desktop", &app("Removed"));
+        write(
+            system.path(),
+            "helper.desktop",
+            &format!("{}NoDisplay=true\n", app("Helper")),
+        );
+        write(system.path(), "broken.desktop", "not a desktop file");
+        write(system.path(), "notes.txt", &app("Notes"));
+        write(user.path(), "editor.desktop", &app("My Editor"));
+        write(
+            user.path(),
+            "removed.desktop",
+            "[Desktop Entry]\nHidden=true\n",
+        );
+
+        let index = ApplicationIndex::new()
+            .with_dirs(vec![user.path().to_path_buf(), system.path().to_path_buf()])
+            .with_locale(Locale::default())
+            .with_desktops(vec!["GNOME".to_string()]);
+
+        let names: Vec<(String, String)> = index
+            .applications()
+            .into_iter()
+            .map(|e| (e.id, e.name))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("kde-konsole.desktop".to_string(), "Konsole".to_string()),
+                ("editor.desktop".to_string(), "My Editor".to_string()),
+            ]
+        );
+
+        // Entries kept out of menus can still be looked up
+        assert_eq!(index.find("helper.desktop").unwrap().name, "Helper");
+        assert!(matches!(
+            index.find("removed.desktop"),
+            Err(ApplicationError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,18 @@
+mod entry;
+mod error;
+mod exec;
+mod icons;
+mod index;
+mod live;
+mod thumbnail;
+#[cfg(target_os = "linux")]
+mod watch;
+
+pub use entry::{DesktopAction, DesktopEntry, Locale};
+pub use error::ApplicationError;
+pub use icons::{current_theme, icon_dirs, IconResolver};
+pub use index::{application_dirs, current_desktops, ApplicationIndex};
+pub use live::LiveIndex;
+pub use thumbnail::IconCache;
+#[cfg(target_os = "linux")]
+pub use watch::DirectoryWatcher;
@@ -0,0 +1,279 @@
+//! An application index updated file by file as desktop entries change,
+//! with an on-disk cache so the launcher has its list right at startup.
+
+use crate::entry::{DesktopEntry, Locale};
+use crate::error::ApplicationError;
+use crate::index::{desktop_files, ApplicationIndex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Bumped whenever the cache layout or parsing changes, so stale caches
+/// are rebuilt instead of misread.
+const CACHE_VERSION: u32 = 1;
+
+/// Size and modification time, to tell whether a file needs parsing again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    len: u64,
+    modified: Option<(u64, u32)>,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| (d.as_secs(), d.subsec_nanos()));
+        Some(Self {
+            len: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// A desktop file as last read. `entry` is `None` for files that are not
+/// applications, deleted with `Hidden` or invalid; they still shadow files
+/// with the same ID in less important directories.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FileRecord {
+    id: String,
+    path: PathBuf,
+    stamp: Stamp,
+    entry: Option<DesktopEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cache {
+    version: u32,
+    dirs: Vec<PathBuf>,
+    locale: Locale,
+    files: Vec<Vec<FileRecord>>,
+}
+
+/// The desktop entries of an [`ApplicationIndex`], kept in memory and
+/// refreshed directory by directory, re-reading only files that changed.
+#[derive(Debug, Clone)]
+pub struct LiveIndex {
+    index: ApplicationIndex,
+    /// The desktop files of each of the index's directories, in order.
+    files: Vec<Vec<FileRecord>>,
+}
+
+impl LiveIndex {
+    /// An empty index; call [`Self::refresh`] to read the directories.
+    pub fn new(index: ApplicationIndex) -> Self {
+        let files = vec![Vec::new(); index.dirs().len()];
+        Self { index, files }
+    }
+
+    /// The index saved at `cache` by [`Self::save_cache`], or an empty one
+    /// if the cache is missing or was written for other directories or
+    /// another locale. The cached entries may be out of date until the next
+    /// [`Self::refresh`].
+    pub fn with_cache(index: ApplicationIndex, cache: &Path) -> Self {
+        let cached = std::fs::read(cache)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<Cache>(&data).ok())
+            .filter(|c| {
+                c.version == CACHE_VERSION
+                    && c.dirs == index.dirs()
+                    && c.locale == *index.locale()
+                    && c.files.len() == c.dirs.len()
+            });
+
+        match cached {
+            Some(cache) => Self {
+                index,
+                files: cache.files,
+            },
+            None => Self::new(index),
+        }
+    }
+
+    /// Writes the index to `path` for [`Self::with_cache`].
+    pub fn save_cache(&self, path: &Path) -> Result<(), ApplicationError> {
+        let cache = Cache {
+            version: CACHE_VERSION,
+            dirs: self.index.dirs().to_vec(),
+            locale: self.index.locale().clone(),
+            files: self.files.clone(),
+        };
+        let data = serde_json::to_vec(&cache).map_err(std::io::Error::other)?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write then rename, so a crash never leaves a truncated cache
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        std::fs::write(&partial, data)?;
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    pub fn index(&self) -> &ApplicationIndex {
+        &self.index
+    }
+
+    /// Re-reads every directory. Returns whether anything changed.
+    pub fn refresh(&mut self) -> bool {
+        let mut changed = false;
+        for i in 0..self.files.len() {
+            changed |= self.refresh_dir(i);
+        }
+        changed
+    }
+
+    /// Re-reads the directories containing any of `paths`, such as those
+    /// reported by a [`crate::DirectoryWatcher`]. Returns whether anything
+    /// changed.
+    pub fn refresh_paths(&mut self, paths: &[PathBuf]) -> bool {
+        let mut changed = false;
+        for i in 0..self.files.len() {
+            let dir = &self.index.dirs()[i];
+            if paths.iter().any(|p| p.starts_with(dir)) {
+                changed |= self.refresh_dir(i);
+            }
+        }
+        changed
+    }
+
+    fn refresh_dir(&mut self, i: usize) -> bool {
+        let mut found = Vec::new();
+        desktop_files(&self.index.dirs()[i], "", &mut found);
+
+        let old = &self.files[i];
+        let records: Vec<FileRecord> = found
+            .into_iter()
+            .filter_map(|(id, path)| {
+                let stamp = Stamp::of(&path)?;
+                if let Some(record) = old
+                    .iter()
+                    .find(|r| r.path == path && r.stamp == stamp && r.id == id)
+                {
+                    return Some(record.clone());
+                }
+                let entry = DesktopEntry::load(&id, &path, self.index.locale())
+                    .ok()
+                    .flatten();
+                Some(FileRecord {
+                    id,
+                    path,
+                    stamp,
+                    entry,
+                })
+            })
+            .collect();
+
+        let changed = records != *old;
+        self.files[i] = records;
+        changed
+    }
+
+    /// Every application entry, including those not meant for menus. An
+    /// entry in a more important directory shadows those with the same ID
+    /// further down, even when it is deleted with `Hidden`.
+    pub fn entries(&self) -> Vec<&DesktopEntry> {
+        let mut seen = HashSet::new();
+        self.files
+            .iter()
+            .flatten()
+            .filter(|record| seen.insert(record.id.as_str()))
+            .filter_map(|record| record.entry.as_ref())
+            .collect()
+    }
+
+    /// The applications to offer in the launcher, sorted by name.
+    pub fn applications(&self) -> Vec<DesktopEntry> {
+        let mut applications: Vec<DesktopEntry> = self
+            .entries()
+            .into_iter()
+            .filter(|entry| entry.should_show(self.index.desktops()))
+            .cloned()
+            .collect();
+        applications.sort_by_cached_key(|entry| (entry.name.to_lowercase(), entry.id.clone()));
+        applications
+    }
+
+    /// The entry with desktop file ID `id`, shown in menus or not.
+    pub fn find(&self, id: &str) -> Option<&DesktopEntry> {
+        self.entries().into_iter().find(|entry| entry.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn app(name: &str) -> String {
+        format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec=true\n",
+            name
+        )
+    }
+
+    fn names(index: &LiveIndex) -> Vec<String> {
+        index.applications().into_iter().map(|e| e.name).collect()
+    }
+
+    #[test]
+    fn test_refresh_picks_up_changes() {
+        let (user, system) = (tempdir().unwrap(), tempdir().unwrap());
+        std::fs::write(system.path().join("a.desktop"), app("Alpha")).unwrap();
+        std::fs::write(system.path().join("b.desktop"), app("Beta")).unwrap();
+
+        let index = ApplicationIndex::new()
+            .with_dirs(vec![user.path().to_path_buf(), system.path().to_path_buf()])
+            .with_locale(Locale::default())
+            .with_desktops(Vec::new());
+        let mut live = LiveIndex::new(index);
+        assert!(live.refresh());
+        assert_eq!(names(&live), ["Alpha", "Beta"]);
+        assert!(!live.refresh());
+
+        // Only the directory a change is reported in is read again
+        std::fs::write(system.path().join("c.desktop"), app("Gamma")).unwrap();
+        std::fs::write(user.path().join("a.desktop"), app("My Alpha")).unwrap();
+        assert!(live.refresh_paths(&[user.path().join("a.desktop")]));
+        assert_eq!(names(&live), ["Beta", "My Alpha"]);
+        assert!(live.refresh_paths(&[system.path().to_path_buf()]));
+        assert_eq!(names(&live), ["Beta", "Gamma", "My Alpha"]);
+
+        std::fs::write(system.path().join("b.desktop"), app("Beta 2")).unwrap();
+        std::fs::remove_file(user.path().join("a.desktop")).unwrap();
+        assert!(live.refresh());
+        assert_eq!(names(&live), ["Alpha", "Beta 2", "Gamma"]);
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let (apps, cache_dir) = (tempdir().unwrap(), tempdir().unwrap());
+        let cache = cache_dir.path().join("mrunner").join("applications.json");
+        std::fs::write(apps.path().join("a.desktop"), app("Alpha")).unwrap();
+
+        let index = ApplicationIndex::new()
+            .with_dirs(vec![apps.path().to_path_buf()])
+            .with_locale(Locale::default());
+        let mut live = LiveIndex::new(index.clone());
+        live.refresh();
+        live.save_cache(&cache).unwrap();
+
+        let cached = LiveIndex::with_cache(index.clone(), &cache);
+        assert_eq!(names(&cached), ["Alpha"]);
+
+        // A cache for another locale is not used
+        let german = index.clone().with_locale(Locale::parse("de_DE"));
+        assert!(LiveIndex::with_cache(german, &cache).entries().is_empty());
+
+        // Changes made while MRunner was not running show up on refresh
+        std::fs::write(apps.path().join("b.desktop"), app("Beta")).unwrap();
+        let mut cached = LiveIndex::with_cache(index, &cache);
+        assert!(cached.refresh());
+        assert_eq!(names(&cached), ["Alpha", "Beta"]);
+    }
+}
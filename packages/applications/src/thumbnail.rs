@@ -0,0 +1,212 @@
+//! Scaled-down copies of large PNG icons, kept on disk so themes that only
+//! ship 256px or 512px icons don't make the launcher decode them each time.
+
+use crate::error::ApplicationError;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+fn invalid(path: &Path, message: impl ToString) -> ApplicationError {
+    ApplicationError::InvalidIcon {
+        path: path.to_path_buf(),
+        message: message.to_string(),
+    }
+}
+
+/// FNV-1a, stable across builds unlike the standard library's hasher, so
+/// cached files keep their names.
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Decodes a PNG into 8-bit RGBA.
+fn read_png(path: &Path) -> Result<(u32, u32, Vec<u8>), ApplicationError> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| invalid(path, e))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| invalid(path, e))?;
+    buffer.truncate(frame.buffer_size());
+
+    let rgba = match frame.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return Err(invalid(path, "unexpanded palette")),
+    };
+    Ok((frame.width, frame.height, rgba))
+}
+
+/// Scales an RGBA image down to fit in `size`×`size`, keeping its aspect
+/// ratio. Each pixel averages the source pixels it covers, weighted by
+/// alpha so transparent edges don't darken.
+fn scale_down(width: u32, height: u32, pixels: &[u8], size: u32) -> (u32, u32, Vec<u8>) {
+    let (width, height, size) = (width as usize, height as usize, size as usize);
+    let (new_width, new_height) = if width >= height {
+        (size, (height * size / width).max(1))
+    } else {
+        ((width * size / height).max(1), size)
+    };
+
+    let span = |i: usize, from: usize, to: usize| {
+        let start = i * from / to;
+        start..((i + 1) * from / to).max(start + 1)
+    };
+    let mut scaled = Vec::with_capacity(new_width * new_height * 4);
+    for y in 0..new_height {
+        let rows = span(y, height, new_height);
+        for x in 0..new_width {
+            let mut sum = [0u64; 4];
+            let mut count = 0;
+            for sy in rows.clone() {
+                for sx in span(x, width, new_width) {
+                    let p = &pixels[(sy * width + sx) * 4..][..4];
+                    let alpha = u64::from(p[3]);
+                    for c in 0..3 {
+                        sum[c] += u64::from(p[c]) * alpha;
+                    }
+                    sum[3] += alpha;
+                    count += 1;
+                }
+            }
+            for c in 0..3 {
+                scaled.push(sum[c].checked_div(sum[3]).unwrap_or(0) as u8);
+            }
+            scaled.push((sum[3] / count) as u8);
+        }
+    }
+    (new_width as u32, new_height as u32, scaled)
+}
+
+fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), ApplicationError> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| invalid(path, e))?;
+    writer
+        .write_image_data(pixels)
+        .map_err(|e| invalid(path, e))?;
+    writer.finish().map_err(|e| invalid(path, e))
+}
+
+/// A directory of icons scaled down to the sizes they are shown at.
+#[derive(Debug, Clone)]
+pub struct IconCache {
+    dir: PathBuf,
+}
+
+impl IconCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The cache in MRunner's cache directory (`~/.cache/mrunner/icons`).
+    pub fn in_cache_dir() -> Option<Self> {
+        dirs::cache_dir().map(|dir| Self::new(dir.join("mrunner").join("icons")))
+    }
+
+    /// The file to show `icon` from at `size` device pixels. SVGs and PNGs
+    /// no larger than that are used as they are; larger PNGs are scaled
+    /// down once and the copy is reused until the icon changes.
+    pub fn get(&self, icon: &Path, size: u32) -> Result<PathBuf, ApplicationError> {
+        match icon.extension().and_then(|ext| ext.to_str()) {
+            Some("svg") => return Ok(icon.to_path_buf()),
+            Some("png") => {}
+            _ => return Err(invalid(icon, "unsupported image type")),
+        }
+
+        let decoder = png::Decoder::new(File::open(icon)?);
+        let info = decoder.read_info().map_err(|e| invalid(icon, e))?;
+        let (width, height) = (info.info().width, info.info().height);
+        if size == 0 || (width <= size && height <= size) {
+            return Ok(icon.to_path_buf());
+        }
+
+        // Named after the icon's path, size and modification time, so an
+        // updated icon gets a new copy
+        let metadata = std::fs::metadata(icon)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let key = fnv1a(&[
+            icon.as_os_str().as_encoded_bytes(),
+            &metadata.len().to_le_bytes(),
+            &modified.to_le_bytes(),
+            &size.to_le_bytes(),
+        ]);
+        let cached = self.dir.join(format!("{:016x}.png", key));
+        if cached.is_file() {
+            return Ok(cached);
+        }
+
+        let (width, height, pixels) = read_png(icon)?;
+        let (width, height, pixels) = scale_down(width, height, &pixels, size);
+        std::fs::create_dir_all(&self.dir)?;
+        // Write then rename, so a crash never leaves a truncated image
+        let partial = cached.with_extension("png.partial");
+        write_png(&partial, width, height, &pixels)?;
+        std::fs::rename(&partial, &cached)?;
+        Ok(cached)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_scales_down_large_icons_once() {
+        let (icons, cache_dir) = (tempdir().unwrap(), tempdir().unwrap());
+        let cache = IconCache::new(cache_dir.path().to_path_buf());
+
+        // Left half opaque red, right half transparent
+        let pixels: Vec<u8> = (0..64 * 32)
+            .flat_map(|i| {
+                if i % 64 < 32 {
+                    [255, 0, 0, 255]
+                } else {
+                    [0, 0, 0, 0]
+                }
+            })
+            .collect();
+        let large = icons.path().join("large.png");
+        write_png(&large, 64, 32, &pixels).unwrap();
+
+        let scaled = cache.get(&large, 16).unwrap();
+        assert!(scaled.starts_with(cache_dir.path()));
+        let (width, height, pixels) = read_png(&scaled).unwrap();
+        assert_eq!((width, height), (16, 8));
+        assert_eq!(&pixels[..4], [255, 0, 0, 255]);
+        assert_eq!(&pixels[15 * 4..16 * 4], [0, 0, 0, 0]);
+        assert_eq!(cache.get(&large, 16).unwrap(), scaled);
+
+        // Small PNGs and SVGs need no copy
+        assert_eq!(cache.get(&large, 64).unwrap(), large);
+        let svg = icons.path().join("icon.svg");
+        std::fs::write(&svg, "<svg/>").unwrap();
+        assert_eq!(cache.get(&svg, 16).unwrap(), svg);
+        assert!(matches!(
+            cache.get(Path::new("/icon.xpm"), 16),
+            Err(ApplicationError::InvalidIcon { .. })
+        ));
+    }
+}
@@ -0,0 +1,248 @@
+//! inotify watches on the application directories (Linux only).
+
+use crate::error::ApplicationError;
+use std::collections::HashMap;
+use std::ffi::{CString, OsStr};
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Changes that can add, remove or modify a file, including one kept open
+/// while written such as a SQLite database, or create a missing directory.
+const MASK: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MODIFY
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_ATTRIB
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVE_SELF
+    | libc::IN_ONLYDIR;
+
+/// How long to keep collecting events after the first one, so a package
+/// install touching many files is handled as one change.
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// Size of the fixed part of an inotify event; the name follows it.
+const EVENT_SIZE: usize = std::mem::size_of::<libc::inotify_event>();
+
+/// Watches directories and everything below them for changes, including
+/// directories that do not exist yet: their closest existing parent is
+/// watched until they are created.
+#[derive(Debug)]
+pub struct DirectoryWatcher {
+    fd: OwnedFd,
+    dirs: Vec<PathBuf>,
+    /// Watched directories by watch descriptor.
+    watches: HashMap<i32, PathBuf>,
+}
+
+impl DirectoryWatcher {
+    pub fn new(dirs: &[PathBuf]) -> Result<Self, ApplicationError> {
+        // SAFETY: inotify_init1 takes no pointers; the descriptor is owned
+        // by the returned OwnedFd
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        let mut watcher = Self {
+            // SAFETY: fd is a fresh descriptor nothing else owns
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            dirs: dirs.to_vec(),
+            watches: HashMap::new(),
+        };
+        watcher.add_watches();
+        Ok(watcher)
+    }
+
+    fn add_watch(&mut self, dir: &Path) {
+        let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+            return;
+        };
+        // SAFETY: path is a valid NUL-terminated string for the call
+        let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), path.as_ptr(), MASK) };
+        if wd >= 0 {
+            self.watches.insert(wd, dir.to_path_buf());
+        }
+    }
+
+    /// Watches every existing directory below the watched directories, and
+    /// the closest existing parent of those that are missing. Adding a
+    /// watch twice is harmless.
+    fn add_watches(&mut self) {
+        for dir in self.dirs.clone() {
+            if dir.is_dir() {
+                let mut pending = vec![dir];
+                while let Some(dir) = pending.pop() {
+                    if let Ok(entries) = std::fs::read_dir(&dir) {
+                        pending.extend(
+                            entries
+                                .filter_map(Result::ok)
+                                .map(|e| e.path())
+                                .filter(|p| p.is_dir()),
+                        );
+                    }
+                    self.add_watch(&dir);
+                }
+            } else if let Some(parent) = dir.ancestors().skip(1).find(|p| p.is_dir()) {
+                self.add_watch(parent);
+            }
+        }
+    }
+
+    /// Waits up to `timeout` for changes and returns the changed paths that
+    /// lie in a watched directory, or the watched directories that were
+    /// created, moved or deleted. Returns an empty list on timeout.
+    pub fn wait(&mut self, timeout: Duration) -> Result<Vec<PathBuf>, ApplicationError> {
+        let mut changed = Vec::new();
+        if !self.poll(timeout)? {
+            return Ok(changed);
+        }
+
+        self.read_events(&mut changed)?;
+        while self.poll(SETTLE_TIME)? {
+            self.read_events(&mut changed)?;
+        }
+
+        self.add_watches();
+        changed.sort();
+        changed.dedup();
+        Ok(changed)
+    }
+
+    fn poll(&self, timeout: Duration) -> Result<bool, ApplicationError> {
+        let mut pollfd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+        // SAFETY: pollfd is a valid array of one element
+        match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+            n if n < 0 => {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    Ok(false)
+                } else {
+                    Err(err.into())
+                }
+            }
+            n => Ok(n > 0),
+        }
+    }
+
+    fn read_events(&mut self, changed: &mut Vec<PathBuf>) -> Result<(), ApplicationError> {
+        let mut buffer = [0u8; 16 * 1024];
+        loop {
+            // SAFETY: buffer is valid for writes of its whole length
+            let len = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    buffer.as_mut_ptr().cast(),
+                    buffer.len(),
+                )
+            };
+            if len < 0 {
+                let err = io::Error::last_os_error();
+                return match err.kind() {
+                    io::ErrorKind::WouldBlock => Ok(()),
+                    io::ErrorKind::Interrupted => continue,
+                    _ => Err(err.into()),
+                };
+            }
+
+            let mut offset = 0;
+            while offset + EVENT_SIZE <= len as usize {
+                // SAFETY: the kernel wrote a whole event at offset; it may
+                // not be aligned in the byte buffer
+                let event: libc::inotify_event =
+                    unsafe { std::ptr::read_unaligned(buffer.as_ptr().add(offset).cast()) };
+                let name_start = offset + EVENT_SIZE;
+                let name = &buffer[name_start..name_start + event.len as usize];
+                let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+                offset = name_start + event.len as usize;
+
+                self.handle_event(&event, OsStr::from_bytes(name), changed);
+            }
+        }
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &libc::inotify_event,
+        name: &OsStr,
+        changed: &mut Vec<PathBuf>,
+    ) {
+        if event.mask & libc::IN_Q_OVERFLOW != 0 {
+            // Events were lost: treat everything as changed
+            changed.extend(self.dirs.iter().cloned());
+            return;
+        }
+        let Some(dir) = self.watches.get(&event.wd).cloned() else {
+            return;
+        };
+        if event.mask & libc::IN_IGNORED != 0 {
+            self.watches.remove(&event.wd);
+        }
+
+        let path = if name.is_empty() { dir } else { dir.join(name) };
+        for watched in &self.dirs {
+            // A change inside a watched directory, or to the directory
+            // itself or one of its parents
+            if path.starts_with(watched) {
+                changed.push(path.clone());
+            } else if watched.starts_with(&path) {
+                changed.push(watched.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn test_reports_changes_in_watched_directories() {
+        let root = tempdir().unwrap();
+        let apps = root.path().join("applications");
+        std::fs::create_dir(&apps).unwrap();
+        let mut watcher = DirectoryWatcher::new(std::slice::from_ref(&apps)).unwrap();
+
+        std::fs::write(apps.join("a.desktop"), "x").unwrap();
+        assert_eq!(watcher.wait(TIMEOUT).unwrap(), [apps.join("a.desktop")]);
+
+        // New subdirectories are watched too
+        std::fs::create_dir(apps.join("kde")).unwrap();
+        assert_eq!(watcher.wait(TIMEOUT).unwrap(), [apps.join("kde")]);
+        std::fs::write(apps.join("kde").join("b.desktop"), "x").unwrap();
+        assert_eq!(
+            watcher.wait(TIMEOUT).unwrap(),
+            [apps.join("kde").join("b.desktop")]
+        );
+
+        // Unrelated files next to the watched directory are not reported
+        std::fs::write(root.path().join("other"), "x").unwrap();
+        assert!(watcher.wait(Duration::from_millis(300)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_watches_directories_created_later() {
+        let root = tempdir().unwrap();
+        let apps = root.path().join("share").join("applications");
+        let mut watcher = DirectoryWatcher::new(std::slice::from_ref(&apps)).unwrap();
+
+        std::fs::create_dir_all(&apps).unwrap();
+        assert!(!watcher.wait(TIMEOUT).unwrap().is_empty());
+
+        std::fs::write(apps.join("a.desktop"), "x").unwrap();
+        assert_eq!(watcher.wait(TIMEOUT).unwrap(), [apps.join("a.desktop")]);
+    }
+}
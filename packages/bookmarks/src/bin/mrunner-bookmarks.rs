@@ -0,0 +1,417 @@
+//! Command line access to MRunner's bookmark database, for shell scripts
+//! and CI. Every command can print JSON instead of text with `--json`.
+
+use bookmarks::{
+    Bookmark, BookmarkError, BookmarkInput, BookmarkSort, BookmarkStore, ImportReport,
+    MetadataFetcher, PageRequest,
+};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: mrunner-bookmarks [OPTIONS] <COMMAND>
+
+Commands:
+  add <URL>               Add a bookmark
+      --title <TITLE>  --tag <TAG>...  --description <TEXT>
+      --keyword <KEYWORD>  --folder <A/B>  --fetch
+  search <QUERY>...       Search titles, URLs, descriptions and tags
+      --tag <TAG>...  --any-tag  --sort <SORT>  --reverse  --limit <N>
+  list                    List bookmarks, newest first
+      --sort <SORT>  --reverse  --limit <N>
+  open <ID | KEYWORD [ARGS]...>
+                          Open a bookmark in the browser
+  tag rename <OLD> <NEW>  Rename a tag on every bookmark
+  import <FILE>           Import a Netscape HTML, Chrome or Firefox file
+      --format <netscape|chrome|firefox>
+  export <FILE>           Export all bookmarks as Netscape HTML
+
+Options:
+  --db <PATH>  Use the database at PATH instead of MRunner's
+  --buku       Use buku's database
+  --encrypted  The database is encrypted; the passphrase is read from
+               MRUNNER_BOOKMARKS_PASSPHRASE or the keyring
+  --json       Print JSON
+  -h, --help   Print this help
+
+Sorts: created, title, url, last_opened, frecency, relevance (default)";
+
+/// Options taken by every command.
+const GLOBAL_VALUES: &[&str] = &["--db"];
+const GLOBAL_FLAGS: &[&str] = &["--buku", "--json", "--encrypted", "--help", "-h"];
+
+enum Error {
+    Usage(String),
+    Failed(String),
+}
+
+impl From<BookmarkError> for Error {
+    fn from(e: BookmarkError) -> Self {
+        Error::Failed(e.to_string())
+    }
+}
+
+/// Command line arguments split into positionals and options.
+struct Args {
+    positional: Vec<String>,
+    values: Vec<(String, String)>,
+    flags: HashSet<String>,
+}
+
+impl Args {
+    /// Parses `args`, accepting the global options plus `values` (options
+    /// taking a value) and `flags`. `--` ends the options.
+    fn parse(args: &[String], values: &[&str], flags: &[&str]) -> Result<Self, Error> {
+        let takes_value = |name: &str| GLOBAL_VALUES.contains(&name) || values.contains(&name);
+        let is_flag = |name: &str| GLOBAL_FLAGS.contains(&name) || flags.contains(&name);
+
+        let mut parsed = Args {
+            positional: Vec::new(),
+            values: Vec::new(),
+            flags: HashSet::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.positional.extend(args.cloned());
+                break;
+            }
+            if !arg.starts_with('-') || arg == "-" {
+                parsed.positional.push(arg.clone());
+                continue;
+            }
+
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if takes_value(name) {
+                let value = match inline {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| Error::Usage(format!("{} needs a value", name)))?,
+                };
+                parsed.values.push((name.to_string(), value));
+            } else if is_flag(name) && inline.is_none() {
+                parsed.flags.insert(name.to_string());
+            } else {
+                return Err(Error::Usage(format!("Unknown option {}", arg)));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn all(&self, name: &str) -> Vec<String> {
+        self.values
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+            .collect()
+    }
+
+    fn page(&self) -> Result<PageRequest, Error> {
+        let sort = match self.value("--sort") {
+            Some(sort) => serde_json::from_value::<BookmarkSort>(sort.into())
+                .map_err(|_| Error::Usage(format!("Unknown sort {:?}", sort)))?,
+            None => BookmarkSort::default(),
+        };
+        let mut page = PageRequest::new(sort).with_reverse(self.flag("--reverse"));
+        if let Some(limit) = self.value("--limit") {
+            let limit = limit
+                .parse()
+                .map_err(|_| Error::Usage(format!("Invalid limit {:?}", limit)))?;
+            page = page.with_limit(limit);
+        }
+        Ok(page)
+    }
+}
+
+/// Options taking a value and flags accepted by `command`.
+fn command_options(command: &str) -> (&'static [&'static str], &'static [&'static str]) {
+    match command {
+        "add" => (
+            &["--title", "--tag", "--description", "--keyword", "--folder"],
+            &["--fetch"],
+        ),
+        "search" => (&["--tag", "--sort", "--limit"], &["--any-tag", "--reverse"]),
+        "list" => (&["--sort", "--limit"], &["--reverse"]),
+        "import" => (&["--format"], &[]),
+        _ => (&[], &[]),
+    }
+}
+
+fn open_store(args: &Args) -> Result<BookmarkStore, Error> {
+    #[cfg(not(feature = "encryption"))]
+    if args.flag("--encrypted") {
+        return Err(Error::Failed(
+            "Built without support for encrypted databases".to_string(),
+        ));
+    }
+
+    if let Some(path) = args.value("--db") {
+        let path = PathBuf::from(path);
+        #[cfg(feature = "encryption")]
+        if args.flag("--encrypted") {
+            return Ok(BookmarkStore::with_path_encrypted(path, &passphrase()?)?);
+        }
+        return Ok(BookmarkStore::with_path(path)?);
+    }
+    if args.flag("--buku") {
+        return Ok(BookmarkStore::buku()?);
+    }
+
+    #[cfg(feature = "encryption")]
+    if args.flag("--encrypted") {
+        return Ok(BookmarkStore::new_encrypted(&passphrase()?)?);
+    }
+    Ok(BookmarkStore::new()?)
+}
+
+/// The passphrase of an encrypted database: `MRUNNER_BOOKMARKS_PASSPHRASE`
+/// when set, so CI can run without a keyring, or the one MRunner keeps in
+/// the keyring.
+#[cfg(feature = "encryption")]
+fn passphrase() -> Result<String, Error> {
+    if let Ok(passphrase) = std::env::var("MRUNNER_BOOKMARKS_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    bookmarks::Keyring::bookmarks()
+        .get()?
+        .ok_or_else(|| Error::Failed("No bookmark passphrase in the keyring".to_string()))
+}
+
+fn print_json(value: &impl Serialize) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).unwrap_or_default()
+    );
+}
+
+/// One tab-separated line per bookmark: id, title, URL and tags.
+fn print_bookmarks(bookmarks: &[Bookmark], json: bool) {
+    if json {
+        return print_json(&bookmarks);
+    }
+    for bookmark in bookmarks {
+        println!(
+            "{}\t{}\t{}\t{}",
+            bookmark.id,
+            bookmark.title,
+            bookmark.url,
+            bookmark.tags.join(",")
+        );
+    }
+}
+
+fn print_report(report: &ImportReport, json: bool) {
+    if json {
+        return print_json(report);
+    }
+    println!(
+        "Added {}, skipped {}, failed {}",
+        report.added, report.skipped, report.failed
+    );
+}
+
+fn add(store: &BookmarkStore, args: &Args) -> Result<(), Error> {
+    let [url] = &args.positional[1..] else {
+        return Err(Error::Usage("add takes one URL".to_string()));
+    };
+
+    let mut input = BookmarkInput::new(url.as_str()).with_tags(args.all("--tag"));
+    if let Some(title) = args.value("--title") {
+        input = input.with_title(title);
+    }
+    if let Some(description) = args.value("--description") {
+        input = input.with_description(description);
+    }
+    if let Some(keyword) = args.value("--keyword") {
+        input = input.with_keyword(keyword);
+    }
+    if let Some(folder) = args.value("--folder") {
+        input = input.with_folder(folder.split('/').map(str::to_string).collect());
+    }
+    if args.flag("--fetch") {
+        // A page that can't be fetched still gets bookmarked
+        if let Err(e) = MetadataFetcher::new().and_then(|f| f.fill(&mut input)) {
+            eprintln!("mrunner-bookmarks: {}", e);
+        }
+    }
+
+    let id = store.add(input)?;
+    let bookmark = store.get(id)?.ok_or(BookmarkError::NotFound(id))?;
+    if args.flag("--json") {
+        print_json(&bookmark);
+    } else {
+        println!("{}", id);
+    }
+    Ok(())
+}
+
+fn search(store: &BookmarkStore, args: &Args) -> Result<(), Error> {
+    let query = args.positional[1..].join(" ");
+    if query.trim().is_empty() {
+        return Err(Error::Usage("search needs a query".to_string()));
+    }
+
+    let tags = args.all("--tag");
+    let tags = (!tags.is_empty()).then_some(tags.as_slice());
+    let page = store.search_page(&query, tags, args.flag("--any-tag"), None, &args.page()?)?;
+    print_bookmarks(&page.bookmarks, args.flag("--json"));
+    Ok(())
+}
+
+fn list(store: &BookmarkStore, args: &Args) -> Result<(), Error> {
+    if args.positional.len() > 1 {
+        return Err(Error::Usage("list takes no arguments".to_string()));
+    }
+
+    let page = store.list_page(&args.page()?)?;
+    print_bookmarks(&page.bookmarks, args.flag("--json"));
+    Ok(())
+}
+
+fn open(store: &BookmarkStore, args: &Args) -> Result<(), Error> {
+    let target = args.positional[1..].join(" ");
+    if target.is_empty() {
+        return Err(Error::Usage(
+            "open needs a bookmark id or keyword".to_string(),
+        ));
+    }
+
+    let url = match target.parse::<i32>() {
+        Ok(id) => {
+            store.open(id)?;
+            store.get(id)?.ok_or(BookmarkError::NotFound(id))?.url
+        }
+        Err(_) => match store.open_keyword(&target)? {
+            Some(found) => found.url,
+            None => return Err(Error::Failed(format!("No bookmark for {:?}", target))),
+        },
+    };
+
+    if args.flag("--json") {
+        print_json(&serde_json::json!({ "url": url }));
+    } else {
+        println!("{}", url);
+    }
+    Ok(())
+}
+
+fn tag(store: &BookmarkStore, args: &Args) -> Result<(), Error> {
+    let [action, old, new] = &args.positional[1..] else {
+        return Err(Error::Usage("usage: tag rename <OLD> <NEW>".to_string()));
+    };
+    if action != "rename" {
+        return Err(Error::Usage(format!("Unknown tag command {:?}", action)));
+    }
+
+    store.rename_tag(old, new)?;
+    if args.flag("--json") {
+        print_json(&serde_json::json!({ "from": old, "to": new }));
+    }
+    Ok(())
+}
+
+fn import(store: &BookmarkStore, args: &Args) -> Result<(), Error> {
+    let [file] = &args.positional[1..] else {
+        return Err(Error::Usage("import takes one file".to_string()));
+    };
+    let path = Path::new(file);
+
+    // Guess from the file name: Chrome's file is plain `Bookmarks` JSON
+    let format =
+        args.value("--format")
+            .unwrap_or_else(|| match path.extension().and_then(|e| e.to_str()) {
+                Some("html" | "htm") => "netscape",
+                Some("sqlite") => "firefox",
+                _ => "chrome",
+            });
+    let report = match format {
+        "netscape" => store.import_netscape(path)?,
+        "chrome" => store.import_chrome(path)?,
+        "firefox" => store.import_firefox(path)?,
+        other => return Err(Error::Usage(format!("Unknown import format {:?}", other))),
+    };
+    print_report(&report, args.flag("--json"));
+    Ok(())
+}
+
+fn export(store: &BookmarkStore, args: &Args) -> Result<(), Error> {
+    let [file] = &args.positional[1..] else {
+        return Err(Error::Usage("export takes one file".to_string()));
+    };
+
+    let count = store.export_netscape(Path::new(file))?;
+    if args.flag("--json") {
+        print_json(&serde_json::json!({ "exported": count }));
+    } else {
+        println!("Exported {}", count);
+    }
+    Ok(())
+}
+
+fn run(raw: &[String]) -> Result<(), Error> {
+    // Global options may come before the command, so find it first
+    let mut after_value_option = false;
+    let command = raw
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .find(|arg| {
+            let found = !after_value_option && !arg.starts_with('-');
+            after_value_option = GLOBAL_VALUES.contains(&arg.as_str());
+            found
+        })
+        .cloned();
+
+    let (values, flags) = command_options(command.as_deref().unwrap_or(""));
+    let args = Args::parse(raw, values, flags)?;
+    if args.flag("--help") || args.flag("-h") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let handler = match command.as_deref() {
+        Some("add") => add,
+        Some("search") => search,
+        Some("list") => list,
+        Some("open") => open,
+        Some("tag") => tag,
+        Some("import") => import,
+        Some("export") => export,
+        Some(other) => return Err(Error::Usage(format!("Unknown command {:?}", other))),
+        None => return Err(Error::Usage("No command given".to_string())),
+    };
+    handler(&open_store(&args)?, &args)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::Usage(message)) => {
+            eprintln!("mrunner-bookmarks: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Error::Failed(message)) => {
+            eprintln!("mrunner-bookmarks: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! URL canonicalization used to detect duplicate bookmarks. The canonical
+//! form is only a comparison key; bookmarks keep the URL they were saved with.
+
+use url::{Position, Url};
+
+/// Query parameters that only track where a visit came from.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "mc_cid", "mc_eid", "igshid",
+    "yclid", "_ga", "_gl", "ref_src",
+];
+
+fn is_tracking_param(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
+}
+
+/// Expands a leading `~` and turns absolute local paths into `file://` URLs.
+fn local_path_url(input: &str) -> Option<Url> {
+    let path = if input == "~" {
+        dirs::home_dir()?
+    } else if let Some(rest) = input.strip_prefix("~/") {
+        dirs::home_dir()?.join(rest)
+    } else if input.starts_with('/') {
+        input.into()
+    } else {
+        return None;
+    };
+
+    Url::from_file_path(path).ok()
+}
+
+/// Returns the canonical form of `input`:
+///
+/// - scheme and host are lowercased and default ports dropped
+/// - `utm_*` and other tracking query parameters are removed
+/// - a trailing `/` is removed from the path
+/// - `~/...` and absolute paths become `file://` URLs
+///
+/// Input that is not a URL is only trimmed.
+pub(crate) fn canonicalize(input: &str) -> String {
+    let input = input.trim();
+
+    let Some(mut url) = local_path_url(input).or_else(|| Url::parse(input).ok()) else {
+        return input.to_string();
+    };
+
+    if url.query().is_some() {
+        let kept: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(name, _)| !is_tracking_param(name))
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+
+    // `Url` always renders at least "/" as the path, so rebuild the string
+    // around a path without trailing slashes
+    let path = url.path().trim_end_matches('/');
+    let mut canonical = format!("{}{}", &url[..Position::BeforePath], path);
+    if let Some(query) = url.query() {
+        canonical.push('?');
+        canonical.push_str(query);
+    }
+    if let Some(fragment) = url.fragment() {
+        canonical.push('#');
+        canonical.push_str(fragment);
+    }
+
+    canonical
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheme_host_case_and_default_port() {
+        assert_eq!(
+            canonicalize("HTTPS://Example.COM:443/Path"),
+            "https://example.com/Path"
+        );
+        assert_eq!(canonicalize("http://example.com:80/"), "http://example.com");
+        assert_eq!(
+            canonicalize("http://example.com:8080/"),
+            "http://example.com:8080"
+        );
+    }
+
+    #[test]
+    fn test_trailing_slash() {
+        assert_eq!(canonicalize("https://example.com/"), "https://example.com");
+        assert_eq!(canonicalize("https://example.com"), "https://example.com");
+        assert_eq!(
+            canonicalize("https://example.com/docs/"),
+            "https://example.com/docs"
+        );
+        assert_eq!(
+            canonicalize("https://example.com/docs/?q=1"),
+            "https://example.com/docs?q=1"
+        );
+        assert_eq!(
+            canonicalize("https://example.com/?q=1#top"),
+            "https://example.com?q=1#top"
+        );
+    }
+
+    #[test]
+    fn test_tracking_params_are_removed() {
+        assert_eq!(
+            canonicalize("https://example.com/a?utm_source=x&UTM_medium=y&id=3&fbclid=z"),
+            "https://example.com/a?id=3"
+        );
+        assert_eq!(
+            canonicalize("https://example.com/?utm_campaign=spring"),
+            "https://example.com"
+        );
+    }
+
+    #[test]
+    fn test_local_paths() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(
+            canonicalize("~/notes/todo.md"),
+            canonicalize(&home.join("notes/todo.md").to_string_lossy())
+        );
+        assert_eq!(canonicalize("/tmp/file.txt"), "file:///tmp/file.txt");
+        assert_eq!(canonicalize("file:///tmp/file.txt"), "file:///tmp/file.txt");
+    }
+
+    #[test]
+    fn test_non_urls_are_trimmed() {
+        assert_eq!(canonicalize("  not a url "), "not a url");
+    }
+}
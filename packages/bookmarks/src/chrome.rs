@@ -0,0 +1,132 @@
+use crate::error::BookmarkError;
+use crate::models::BookmarkInput;
+use serde_json::Value;
+
+/// Top-level folders of Chrome's `Bookmarks` file, in the order they are shown
+/// in the browser. They are not recreated as folders.
+const ROOTS: &[&str] = &["bookmark_bar", "other", "synced"];
+
+/// Parses the JSON `Bookmarks` file found in a Chrome profile directory.
+///
+/// Folders below the roots are kept, e.g. a bookmark in
+/// `Bookmarks bar > Work > Infra` is placed in folder `Work > Infra` and
+/// tagged `Work/Infra`.
+pub(crate) fn parse(json: &str) -> Result<Vec<BookmarkInput>, BookmarkError> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| BookmarkError::InvalidFile(e.to_string()))?;
+
+    let roots = value
+        .get("roots")
+        .and_then(|r| r.as_object())
+        .ok_or_else(|| BookmarkError::InvalidFile("missing \"roots\" object".to_string()))?;
+
+    let mut inputs = Vec::new();
+    for root in ROOTS.iter().filter_map(|name| roots.get(*name)) {
+        collect(root, &mut Vec::new(), &mut inputs);
+    }
+
+    Ok(inputs)
+}
+
+fn collect(node: &Value, path: &mut Vec<String>, inputs: &mut Vec<BookmarkInput>) {
+    let Some(children) = node.get("children").and_then(|c| c.as_array()) else {
+        return;
+    };
+
+    for child in children {
+        let name = child.get("name").and_then(|n| n.as_str()).unwrap_or("");
+
+        match child.get("type").and_then(|t| t.as_str()) {
+            Some("url") => {
+                let Some(url) = child.get("url").and_then(|u| u.as_str()) else {
+                    continue;
+                };
+
+                inputs.push(
+                    BookmarkInput::new(url)
+                        .with_title(name)
+                        .with_folder(path.clone())
+                        .with_folder_tag(),
+                );
+            }
+            Some("folder") => {
+                let name = name.trim();
+                if name.is_empty() {
+                    collect(child, path, inputs);
+                } else {
+                    path.push(name.to_string());
+                    collect(child, path, inputs);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"{
+        "checksum": "0",
+        "roots": {
+            "bookmark_bar": {
+                "name": "Bookmarks bar",
+                "type": "folder",
+                "children": [
+                    { "name": "Rust", "type": "url", "url": "https://www.rust-lang.org/" },
+                    {
+                        "name": "Work",
+                        "type": "folder",
+                        "children": [{
+                            "name": "Infra, Ops",
+                            "type": "folder",
+                            "children": [
+                                { "name": "Grafana", "type": "url", "url": "https://grafana.example" }
+                            ]
+                        }]
+                    }
+                ]
+            },
+            "other": {
+                "name": "Other bookmarks",
+                "type": "folder",
+                "children": [
+                    { "name": "Docs", "type": "url", "url": "https://docs.rs" }
+                ]
+            },
+            "synced": { "name": "Mobile bookmarks", "type": "folder", "children": [] }
+        },
+        "version": 1
+    }"#;
+
+    #[test]
+    fn test_parse_keeps_folders() {
+        let inputs = parse(FIXTURE).unwrap();
+
+        let urls: Vec<&str> = inputs.iter().map(|i| i.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.rust-lang.org/",
+                "https://grafana.example",
+                "https://docs.rs"
+            ]
+        );
+
+        assert_eq!(inputs[0].title.as_deref(), Some("Rust"));
+        assert!(inputs[0].folder.is_empty());
+        assert_eq!(inputs[1].tags, Some(vec!["Work/Infra Ops".to_string()]));
+        assert_eq!(inputs[1].folder, vec!["Work", "Infra, Ops"]);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_layout() {
+        assert!(matches!(
+            parse(r#"{"version": 1}"#),
+            Err(BookmarkError::InvalidFile(_))
+        ));
+        assert!(parse("not json").is_err());
+    }
+}
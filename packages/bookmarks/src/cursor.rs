@@ -0,0 +1,57 @@
+//! Opaque page cursors for [`crate::BookmarkStore::search_page`].
+
+use crate::error::BookmarkError;
+use crate::models::{BookmarkSort, PageRequest};
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+
+/// Where the next page starts. Orders computed in SQL resume after the last
+/// row's sort key and id, so rows added or removed meanwhile do not shift
+/// the pages; other orders fall back to an offset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Position {
+    After { key: serde_json::Value, id: i32 },
+    Offset(usize),
+}
+
+/// A position together with the order it belongs to, so a cursor is not
+/// reused with a different sort.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Cursor {
+    sort: BookmarkSort,
+    reverse: bool,
+    position: Position,
+}
+
+pub(crate) fn encode(page: &PageRequest, position: Position) -> String {
+    let cursor = Cursor {
+        sort: page.sort,
+        reverse: page.reverse,
+        position,
+    };
+    serde_json::to_string(&cursor).unwrap_or_default()
+}
+
+/// The position in `page.cursor`, if any.
+pub(crate) fn decode(page: &PageRequest) -> Result<Option<Position>, BookmarkError> {
+    let Some(raw) = page.cursor.as_deref() else {
+        return Ok(None);
+    };
+
+    match serde_json::from_str::<Cursor>(raw) {
+        Ok(cursor) if cursor.sort == page.sort && cursor.reverse == page.reverse => {
+            Ok(Some(cursor.position))
+        }
+        _ => Err(BookmarkError::InvalidCursor(raw.to_string())),
+    }
+}
+
+/// The SQL parameter for a keyset sort key.
+pub(crate) fn key_param(key: &serde_json::Value) -> Option<Value> {
+    match key {
+        serde_json::Value::Number(n) => n.as_i64().map(Value::Integer),
+        serde_json::Value::String(s) => Some(Value::Text(s.clone())),
+        _ => None,
+    }
+}
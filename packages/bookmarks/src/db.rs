@@ -1,361 +1,3194 @@
+use crate::canonical;
+use crate::chrome;
+use crate::cursor::{self, Position};
 use crate::error::BookmarkError;
-use crate::models::{Bookmark, BookmarkInput, Tag};
-use rusqlite::{Connection, Row};
-use std::collections::HashMap;
-use std::path::PathBuf;
-
-pub struct BookmarkStore {
-    conn: Connection,
+use crate::firefox;
+use crate::keyword;
+use crate::links::LinkChecker;
+use crate::models::{
+    Bookmark, BookmarkInput, BookmarkOrder, BookmarkPage, BookmarkSort, Folder, ImportReport,
+    KeywordMatch, LinkCheckProgress, LinkStatus, PageRequest, Tag, TrashedBookmark, UndoAction,
+    UndoEntry,
+};
+use crate::netscape;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, ErrorCode, OptionalExtension, Row};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use storage::frecency;
+
+/// Ordered schema migrations. Migration `n` (1-based) upgrades a database
+/// from `user_version = n - 1` to `user_version = n`. Never edit or reorder
+/// an entry once released; append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial buku-compatible schema
+    "CREATE TABLE IF NOT EXISTS bookmarks (
+        id INTEGER PRIMARY KEY,
+        URL TEXT NOT NULL UNIQUE,
+        metadata TEXT DEFAULT '',
+        tags TEXT DEFAULT ',',
+        desc TEXT DEFAULT '',
+        flags INTEGER DEFAULT 0
+    );",
+    // 2: full-text index over title, URL, tags and description
+    "CREATE VIRTUAL TABLE IF NOT EXISTS bookmarks_fts USING fts5(
+        metadata, URL, tags, desc,
+        content='bookmarks', content_rowid='id',
+        tokenize='unicode61'
+    );
+    CREATE TRIGGER IF NOT EXISTS bookmarks_fts_ai AFTER INSERT ON bookmarks BEGIN
+        INSERT INTO bookmarks_fts (rowid, metadata, URL, tags, desc)
+        VALUES (new.id, new.metadata, new.URL, new.tags, new.desc);
+    END;
+    CREATE TRIGGER IF NOT EXISTS bookmarks_fts_ad AFTER DELETE ON bookmarks BEGIN
+        INSERT INTO bookmarks_fts (bookmarks_fts, rowid, metadata, URL, tags, desc)
+        VALUES ('delete', old.id, old.metadata, old.URL, old.tags, old.desc);
+    END;
+    CREATE TRIGGER IF NOT EXISTS bookmarks_fts_au AFTER UPDATE ON bookmarks BEGIN
+        INSERT INTO bookmarks_fts (bookmarks_fts, rowid, metadata, URL, tags, desc)
+        VALUES ('delete', old.id, old.metadata, old.URL, old.tags, old.desc);
+        INSERT INTO bookmarks_fts (rowid, metadata, URL, tags, desc)
+        VALUES (new.id, new.metadata, new.URL, new.tags, new.desc);
+    END;
+    INSERT INTO bookmarks_fts (bookmarks_fts) VALUES ('rebuild');",
+    // 3: visit history for frecency ranking, kept outside `bookmarks` so
+    // the buku row layout stays untouched
+    "CREATE TABLE IF NOT EXISTS bookmark_visits (
+        id INTEGER PRIMARY KEY,
+        bookmark_id INTEGER NOT NULL,
+        visited_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS bookmark_visits_bookmark
+        ON bookmark_visits (bookmark_id, visited_at);
+    CREATE TRIGGER IF NOT EXISTS bookmark_visits_ad AFTER DELETE ON bookmarks BEGIN
+        DELETE FROM bookmark_visits WHERE bookmark_id = old.id;
+    END;",
+    // 4: MRunner-only per-bookmark data, also kept outside `bookmarks`.
+    // Rows inserted by other tools (buku) get a row through the trigger.
+    "CREATE TABLE IF NOT EXISTS bookmark_meta (
+        bookmark_id INTEGER PRIMARY KEY,
+        added_at INTEGER
+    );
+    INSERT OR IGNORE INTO bookmark_meta (bookmark_id) SELECT id FROM bookmarks;
+    CREATE TRIGGER IF NOT EXISTS bookmark_meta_ai AFTER INSERT ON bookmarks BEGIN
+        INSERT OR IGNORE INTO bookmark_meta (bookmark_id, added_at)
+        VALUES (new.id, CAST(strftime('%s', 'now') AS INTEGER));
+    END;
+    CREATE TRIGGER IF NOT EXISTS bookmark_meta_ad AFTER DELETE ON bookmarks BEGIN
+        DELETE FROM bookmark_meta WHERE bookmark_id = old.id;
+    END;",
+    // 5: normalized tags derived from buku's `tags` column, which stays the
+    // source of truth so buku keeps reading and writing it. Triggers cannot
+    // use recursive CTEs, so `,a,b,` is split by rewriting it as a JSON array.
+    r#"CREATE TABLE IF NOT EXISTS tags (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS bookmark_tags (
+        bookmark_id INTEGER NOT NULL,
+        tag_id INTEGER NOT NULL,
+        PRIMARY KEY (bookmark_id, tag_id)
+    ) WITHOUT ROWID;
+    CREATE INDEX IF NOT EXISTS bookmark_tags_tag ON bookmark_tags (tag_id);
+    CREATE TRIGGER IF NOT EXISTS bookmark_tags_ai AFTER INSERT ON bookmarks BEGIN
+        INSERT OR IGNORE INTO tags (name)
+        SELECT value FROM json_each((
+            SELECT CASE WHEN json_valid(j) THEN j ELSE '[]' END
+            FROM (SELECT '["' || replace(replace(replace(trim(new.tags, ','), '\', '\\'), '"', '\"'), ',', '","') || '"]' AS j)
+        )) WHERE value != '';
+        INSERT OR IGNORE INTO bookmark_tags (bookmark_id, tag_id)
+        SELECT new.id, id FROM tags WHERE instr(new.tags, ',' || name || ',') > 0;
+    END;
+    CREATE TRIGGER IF NOT EXISTS bookmark_tags_au AFTER UPDATE OF id, tags ON bookmarks BEGIN
+        DELETE FROM bookmark_tags WHERE bookmark_id = old.id;
+        INSERT OR IGNORE INTO tags (name)
+        SELECT value FROM json_each((
+            SELECT CASE WHEN json_valid(j) THEN j ELSE '[]' END
+            FROM (SELECT '["' || replace(replace(replace(trim(new.tags, ','), '\', '\\'), '"', '\"'), ',', '","') || '"]' AS j)
+        )) WHERE value != '';
+        INSERT OR IGNORE INTO bookmark_tags (bookmark_id, tag_id)
+        SELECT new.id, id FROM tags WHERE instr(new.tags, ',' || name || ',') > 0;
+        DELETE FROM tags WHERE NOT EXISTS (
+            SELECT 1 FROM bookmark_tags WHERE tag_id = tags.id
+        );
+    END;
+    CREATE TRIGGER IF NOT EXISTS bookmark_tags_ad AFTER DELETE ON bookmarks BEGIN
+        DELETE FROM bookmark_tags WHERE bookmark_id = old.id;
+        DELETE FROM tags WHERE NOT EXISTS (
+            SELECT 1 FROM bookmark_tags WHERE tag_id = tags.id
+        );
+    END;
+    INSERT OR IGNORE INTO tags (name)
+    SELECT j.value FROM bookmarks b, json_each((
+        SELECT CASE WHEN json_valid(x) THEN x ELSE '[]' END
+        FROM (SELECT '["' || replace(replace(replace(trim(b.tags, ','), '\', '\\'), '"', '\"'), ',', '","') || '"]' AS x)
+    )) j WHERE j.value != '';
+    INSERT OR IGNORE INTO bookmark_tags (bookmark_id, tag_id)
+    SELECT b.id, t.id FROM bookmarks b JOIN tags t
+    ON instr(b.tags, ',' || t.name || ',') > 0;"#,
+    // 6: canonical URL used to detect duplicates. Keys are computed in Rust,
+    // so rows written or edited by other tools get NULL and are filled lazily.
+    "ALTER TABLE bookmark_meta ADD COLUMN url_key TEXT;
+    CREATE INDEX IF NOT EXISTS bookmark_meta_url_key ON bookmark_meta (url_key);
+    CREATE TRIGGER IF NOT EXISTS bookmark_meta_au_url AFTER UPDATE OF URL ON bookmarks BEGIN
+        UPDATE bookmark_meta SET url_key = NULL WHERE bookmark_id = new.id;
+    END;",
+    // 7: trash and undo journal. Deleted rows leave `bookmarks`, so buku stops
+    // seeing them, and are kept here together with their MRunner data.
+    "CREATE TABLE IF NOT EXISTS bookmark_trash (
+        id INTEGER PRIMARY KEY,
+        bookmark_id INTEGER NOT NULL,
+        URL TEXT NOT NULL,
+        metadata TEXT DEFAULT '',
+        tags TEXT DEFAULT ',',
+        desc TEXT DEFAULT '',
+        flags INTEGER DEFAULT 0,
+        added_at INTEGER,
+        visits TEXT NOT NULL DEFAULT '[]',
+        deleted_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS bookmark_trash_deleted_at ON bookmark_trash (deleted_at);
+    CREATE TABLE IF NOT EXISTS undo_journal (
+        id INTEGER PRIMARY KEY,
+        action TEXT NOT NULL,
+        target TEXT NOT NULL,
+        trash_id INTEGER,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS undo_journal_tags (
+        entry_id INTEGER NOT NULL,
+        bookmark_id INTEGER NOT NULL,
+        tags TEXT NOT NULL,
+        PRIMARY KEY (entry_id, bookmark_id)
+    ) WITHOUT ROWID;
+    CREATE TRIGGER IF NOT EXISTS undo_journal_ad AFTER DELETE ON undo_journal BEGIN
+        DELETE FROM undo_journal_tags WHERE entry_id = old.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS bookmark_trash_ad AFTER DELETE ON bookmark_trash BEGIN
+        DELETE FROM undo_journal WHERE trash_id = old.id;
+    END;",
+    // 8: keyword bookmarks; NULLs do not conflict in a unique index
+    "ALTER TABLE bookmark_meta ADD COLUMN keyword TEXT;
+    CREATE UNIQUE INDEX IF NOT EXISTS bookmark_meta_keyword ON bookmark_meta (keyword);
+    ALTER TABLE bookmark_trash ADD COLUMN keyword TEXT;",
+    // 9: nested folders with manual ordering. Bookmarks added by buku have no
+    // folder or position and are listed at the top level after ordered ones.
+    "CREATE TABLE IF NOT EXISTS folders (
+        id INTEGER PRIMARY KEY,
+        parent_id INTEGER,
+        name TEXT NOT NULL,
+        position INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS folders_parent ON folders (parent_id, position);
+    ALTER TABLE bookmark_meta ADD COLUMN folder_id INTEGER;
+    ALTER TABLE bookmark_meta ADD COLUMN position INTEGER;
+    CREATE INDEX IF NOT EXISTS bookmark_meta_folder ON bookmark_meta (folder_id, position);
+    ALTER TABLE bookmark_trash ADD COLUMN folder_id INTEGER;",
+    // 10: last link-health check per bookmark, dropped when the URL changes
+    "CREATE TABLE IF NOT EXISTS bookmark_links (
+        bookmark_id INTEGER PRIMARY KEY,
+        status INTEGER,
+        redirect_url TEXT,
+        error TEXT,
+        checked_at INTEGER NOT NULL
+    );
+    CREATE TRIGGER IF NOT EXISTS bookmark_links_ad AFTER DELETE ON bookmarks BEGIN
+        DELETE FROM bookmark_links WHERE bookmark_id = old.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS bookmark_links_au_url AFTER UPDATE OF URL ON bookmarks
+    WHEN old.URL IS NOT new.URL BEGIN
+        DELETE FROM bookmark_links WHERE bookmark_id = new.id;
+    END;",
+    // 11: tags after a journaled tag change, so undo reverts only that change
+    // and keeps later edits. NULL for entries journaled before.
+    "ALTER TABLE undo_journal_tags ADD COLUMN new_tags TEXT;",
+    // 12: change counter per bookmark, raised by triggers on every edit by
+    // any program, so watchers read only the bookmarks that changed
+    "ALTER TABLE bookmark_meta ADD COLUMN changed INTEGER NOT NULL DEFAULT 0;
+    CREATE INDEX IF NOT EXISTS bookmark_meta_changed ON bookmark_meta (changed);
+    CREATE TRIGGER IF NOT EXISTS bookmark_meta_ai_changed AFTER INSERT ON bookmark_meta BEGIN
+        UPDATE bookmark_meta SET changed = (SELECT MAX(changed) + 1 FROM bookmark_meta)
+        WHERE bookmark_id = new.bookmark_id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS bookmark_meta_au_changed
+    AFTER UPDATE OF added_at, keyword, folder_id, position ON bookmark_meta BEGIN
+        UPDATE bookmark_meta SET changed = (SELECT MAX(changed) + 1 FROM bookmark_meta)
+        WHERE bookmark_id = new.bookmark_id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS bookmarks_au_changed AFTER UPDATE ON bookmarks BEGIN
+        UPDATE bookmark_meta SET changed = (SELECT MAX(changed) + 1 FROM bookmark_meta)
+        WHERE bookmark_id = new.id;
+    END;",
+];
+
+/// Column list and joins read by [`BookmarkStore::row_to_bookmark`]. The
+/// bookmarks table is aliased `b` so callers can add their own joins.
+const SELECT_BOOKMARKS: &str =
+    "SELECT b.id, b.URL, b.metadata, b.tags, b.desc, m.added_at, b.flags,
+    m.keyword, m.folder_id FROM bookmarks b LEFT JOIN bookmark_meta m ON m.bookmark_id = b.id";
+
+/// Columns of `bookmark_trash` in the order read by
+/// [`BookmarkStore::row_to_bookmark`], followed by `deleted_at`.
+const SELECT_TRASH: &str =
+    "SELECT bookmark_id, URL, metadata, tags, desc, added_at, flags, keyword,
+    folder_id, deleted_at FROM bookmark_trash";
+
+const SELECT_FOLDERS: &str = "SELECT id, parent_id, name, position FROM folders";
+
+const SELECT_LINKS: &str =
+    "SELECT bookmark_id, status, redirect_url, error, checked_at FROM bookmark_links";
+
+/// Manual order within a folder. Bookmarks without a position (added by buku)
+/// come last, newest first.
+const FOLDER_ORDER: &str = "m.position IS NULL, m.position, b.id DESC";
+
+/// Number of changes kept in the undo journal.
+const UNDO_JOURNAL_SIZE: i64 = 50;
+
+/// buku flag bit: the title was set by hand and must not be replaced by a
+/// fetched page title.
+pub(crate) const FLAG_IMMUTABLE_TITLE: i32 = 0x01;
+
+fn parse_tags(tags: &str) -> Vec<String> {
+    tags.trim_matches(',')
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim().to_string())
+        .collect()
 }
 
-impl BookmarkStore {
-    pub fn new() -> Result<Self, BookmarkError> {
-        let db_path = Self::get_db_path()?;
+/// Replaces `from` with `to` in `tag` if `tag` is `from` or one of its
+/// children. Tags compare case-insensitively, like `COLLATE NOCASE`.
+fn rename_in_hierarchy(tag: &str, from: &str, to: &str) -> String {
+    let rest = tag
+        .get(..from.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(from))
+        .map(|_| &tag[from.len()..]);
+
+    match rest {
+        Some("") => to.to_string(),
+        Some(rest) if rest.starts_with('/') => format!("{}{}", to, rest),
+        _ => tag.to_string(),
+    }
+}
 
-        if let Some(parent) = db_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
+/// SQL condition matching bookmarks `b` tagged with parameter `?{n}` or any
+/// tag below it in the hierarchy (`work` matches `work/infra/k8s`).
+/// Comparison is case-insensitive.
+fn tag_subtree_condition(n: usize) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM bookmark_tags bt JOIN tags t ON t.id = bt.tag_id
+         WHERE bt.bookmark_id = b.id
+         AND (t.name = ?{n} COLLATE NOCASE
+              OR substr(t.name, 1, length(?{n}) + 1) COLLATE NOCASE = ?{n} || '/'))",
+        n = n
+    )
+}
 
-        let conn = Connection::open(&db_path)?;
-        let store = Self { conn };
-        store.ensure_table()?;
+/// SQL selecting the ids of folder `?{n}` and every folder below it.
+fn folder_subtree(n: usize) -> String {
+    format!(
+        "WITH RECURSIVE subtree(id) AS (
+            SELECT CAST(?{n} AS INTEGER)
+            UNION ALL SELECT f.id FROM folders f JOIN subtree s ON f.parent_id = s.id
+         ) SELECT id FROM subtree",
+        n = n
+    )
+}
 
-        Ok(store)
+/// Reverts the tag change `before` → `after` in `current`: tags the change
+/// added are removed and tags it removed come back in their place, while
+/// tags added or removed since are left as they are.
+fn revert_tags(current: &[String], before: &[String], after: &[String]) -> Vec<String> {
+    let mut removed: Vec<&String> = before.iter().filter(|t| !after.contains(t)).collect();
+    let mut tags = Vec::new();
+    for tag in current {
+        if after.contains(tag) && !before.contains(tag) {
+            tags.extend(removed.drain(..).cloned());
+        } else {
+            tags.push(tag.clone());
+        }
     }
+    tags.extend(removed.into_iter().cloned());
 
-    pub fn with_path(path: PathBuf) -> Result<Self, BookmarkError> {
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
+    let mut unique = Vec::new();
+    for tag in tags {
+        if !unique.contains(&tag) {
+            unique.push(tag);
         }
+    }
+    unique
+}
 
-        let conn = Connection::open(&path)?;
-        let store = Self { conn };
-        store.ensure_table()?;
-
-        Ok(store)
+/// Trims a folder name, which must not end up empty.
+fn folder_name(name: &str) -> Result<&str, BookmarkError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(BookmarkError::InvalidFolder(
+            "folder names cannot be empty".to_string(),
+        ));
     }
+    Ok(name)
+}
 
-    fn get_db_path() -> Result<PathBuf, BookmarkError> {
-        #[cfg(target_os = "windows")]
-        {
-            dirs::data_dir()
-                .map(|p| p.join("mrunner").join("bookmarks.db"))
-                .ok_or(BookmarkError::DataDirNotFound)
-        }
+/// Drops the control characters in `tag`, which the tag triggers cannot
+/// put in a JSON array, turning tabs and line breaks into spaces.
+fn clean_tag(tag: &str) -> String {
+    let tag: String = tag
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_control() && c.is_whitespace() => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect();
+    tag.trim().to_string()
+}
 
-        #[cfg(not(target_os = "windows"))]
-        {
-            dirs::data_local_dir()
-                .map(|p| p.join("mrunner").join("bookmarks.db"))
-                .ok_or(BookmarkError::DataDirNotFound)
+/// Formats tags the way buku stores them: `,tag1,tag2,` with no surrounding
+/// whitespace, control characters, empty or duplicate entries (ignoring
+/// case), or `,` for no tags. Commas inside a tag split it into several
+/// tags, as they would in buku.
+fn format_tags(tags: &[String]) -> String {
+    let mut unique: Vec<String> = Vec::new();
+    for tag in tags.iter().flat_map(|t| t.split(',')).map(clean_tag) {
+        if !tag.is_empty() && !unique.iter().any(|u| u.eq_ignore_ascii_case(&tag)) {
+            unique.push(tag);
         }
     }
 
-    fn ensure_table(&self) -> Result<(), BookmarkError> {
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS bookmarks (
-                id INTEGER PRIMARY KEY,
-                URL TEXT NOT NULL UNIQUE,
-                metadata TEXT DEFAULT '',
-                tags TEXT DEFAULT ',',
-                desc TEXT DEFAULT '',
-                flags INTEGER DEFAULT 0
-            )",
-            [],
-        )?;
-        Ok(())
+    if unique.is_empty() {
+        ",".to_string()
+    } else {
+        format!(",{},", unique.join(","))
     }
+}
 
-    fn row_to_bookmark(row: &Row) -> rusqlite::Result<Bookmark> {
-        let tags_str: String = row.get(3)?;
-        let tags: Vec<String> = tags_str
-            .trim_matches(',')
-            .split(',')
-            .filter(|s| !s.is_empty())
-            .map(|s| s.trim().to_string())
-            .collect();
-
-        Ok(Bookmark {
-            id: row.get(0)?,
-            url: row.get(1)?,
-            title: row.get(2)?,
-            tags,
-            description: row.get(4)?,
-        })
+/// BM25 column weights for `bookmarks_fts`, in column order:
+/// title (metadata), URL, tags, description.
+const FTS_WEIGHTS: &str = "10.0, 5.0, 2.0, 1.0";
+
+pub(crate) use storage::unix_now;
+
+/// Turns free-form user input into an FTS5 query where every term is
+/// prefix-matched and all terms must match. Terms are quoted so characters
+/// such as `-`, `:` or `"` are never interpreted as FTS5 syntax.
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .filter(|term| term.chars().any(char::is_alphanumeric))
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
     }
+}
 
-    pub fn list(&self, limit: Option<u32>) -> Result<Vec<Bookmark>, BookmarkError> {
-        let query = match limit {
-            Some(n) => format!(
-                "SELECT id, URL, metadata, tags, desc FROM bookmarks ORDER BY id DESC LIMIT {}",
-                n
-            ),
-            None => {
-                "SELECT id, URL, metadata, tags, desc FROM bookmarks ORDER BY id DESC".to_string()
-            }
-        };
-
-        let mut stmt = self.conn.prepare(&query)?;
-        let bookmarks = stmt
-            .query_map([], Self::row_to_bookmark)?
-            .filter_map(|r| r.ok())
-            .collect();
-
-        Ok(bookmarks)
+/// SQL expression for a sort key compared in SQL, and whether its natural
+/// direction is descending. `None` for orders paged by offset: search
+/// relevance, which depends on the FTS match, and frecency, which changes
+/// with every visit.
+fn sort_key(sort: BookmarkSort, fts: bool) -> Option<(&'static str, bool)> {
+    match sort {
+        BookmarkSort::Relevance if fts => None,
+        BookmarkSort::Relevance => Some(("b.id", true)),
+        BookmarkSort::Created => Some(("IFNULL(m.added_at, 0)", true)),
+        BookmarkSort::Title => Some(("IFNULL(b.metadata, '') COLLATE NOCASE", false)),
+        BookmarkSort::Url => Some(("b.URL", false)),
+        BookmarkSort::LastOpened => Some((
+            "IFNULL((SELECT MAX(visited_at) FROM bookmark_visits v WHERE v.bookmark_id = b.id), 0)",
+            true,
+        )),
+        BookmarkSort::Frecency => None,
     }
+}
 
-    pub fn search(
-        &self,
-        query: &str,
-        tag_filter: Option<&[String]>,
-        tag_or: bool,
-    ) -> Result<Vec<Bookmark>, BookmarkError> {
-        if query.is_empty() && tag_filter.is_none() {
-            return self.list(None);
-        }
+/// Frecency score of `b`, read from the scores
+/// [`BookmarkStore::load_frecency`] left in `temp.frecency`.
+const FRECENCY_KEY: &str =
+    "IFNULL((SELECT score FROM temp.frecency f WHERE f.bookmark_id = b.id), 0)";
+
+/// Conditions and parameters selecting the bookmarks that match a search.
+struct Filter {
+    /// Whether the conditions include a full-text match, which joins
+    /// `bookmarks_fts`.
+    fts: bool,
+    conditions: Vec<String>,
+    params: Vec<Value>,
+}
 
-        let mut conditions: Vec<String> = Vec::new();
-        let mut params: Vec<String> = Vec::new();
+impl Filter {
+    fn new(query: &str, tag_filter: Option<&[String]>, tag_or: bool, folder: Option<i32>) -> Self {
+        let mut filter = Self {
+            fts: false,
+            conditions: Vec::new(),
+            params: Vec::new(),
+        };
 
-        if !query.is_empty() {
-            conditions.push(
-                "(URL LIKE ?1 OR metadata LIKE ?1 OR tags LIKE ?1 OR desc LIKE ?1)".to_string(),
-            );
-            params.push(format!("%{}%", query));
+        match fts_query(query) {
+            Some(fts) => {
+                filter.fts = true;
+                filter.conditions.push("bookmarks_fts MATCH ?1".to_string());
+                filter.params.push(Value::Text(fts));
+            }
+            // Input such as `--` has no terms to search for, so it matches
+            // nothing rather than everything
+            None if !query.trim().is_empty() => filter.conditions.push("0".to_string()),
+            None => {}
         }
 
         if let Some(tags) = tag_filter {
             let tag_conditions: Vec<String> = tags
                 .iter()
                 .enumerate()
-                .map(|(i, _)| format!("tags LIKE ?{}", params.len() + i + 2))
+                .map(|(i, _)| tag_subtree_condition(filter.params.len() + i + 1))
                 .collect();
 
             let connector = if tag_or { " OR " } else { " AND " };
-            conditions.push(format!("({})", tag_conditions.join(connector)));
+            filter
+                .conditions
+                .push(format!("({})", tag_conditions.join(connector)));
 
             for tag in tags {
-                params.push(format!("%,{},%", tag));
+                filter.params.push(Value::Text(tag.trim().to_string()));
             }
         }
 
-        let sql = format!(
-            "SELECT id, URL, metadata, tags, desc FROM bookmarks WHERE {} ORDER BY id DESC",
-            conditions.join(" AND ")
-        );
-
-        let mut stmt = self.conn.prepare(&sql)?;
-        let param_refs: Vec<&dyn rusqlite::ToSql> =
-            params.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
+        if let Some(folder) = folder {
+            filter.conditions.push(format!(
+                "m.folder_id IN ({})",
+                folder_subtree(filter.params.len() + 1)
+            ));
+            filter.params.push(Value::Integer(folder.into()));
+        }
 
-        let bookmarks = stmt
-            .query_map(param_refs.as_slice(), Self::row_to_bookmark)?
-            .filter_map(|r| r.ok())
-            .collect();
+        filter
+    }
 
-        Ok(bookmarks)
+    /// `SELECT_BOOKMARKS` restricted to the matching bookmarks.
+    fn select(&self) -> String {
+        let mut sql = SELECT_BOOKMARKS.to_string();
+        if self.fts {
+            sql.push_str(" JOIN bookmarks_fts ON bookmarks_fts.rowid = b.id");
+        }
+        if !self.conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&self.conditions.join(" AND "));
+        }
+        sql
     }
+}
 
-    pub fn get(&self, id: i32) -> Result<Option<Bookmark>, BookmarkError> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT id, URL, metadata, tags, desc FROM bookmarks WHERE id = ?1")?;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
 
-        let bookmark = stmt.query_row([id], Self::row_to_bookmark).ok();
-        Ok(bookmark)
+/// Search builds its SQL from the number of tag filters, so a few variants of
+/// each query end up cached alongside the fixed ones.
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+/// A transaction that nests: the outermost one begins a transaction and
+/// inner ones are savepoints within it, so store methods can run as part
+/// of a larger change such as applying a sync. Rolled back when dropped
+/// without [`Self::commit`].
+pub(crate) struct Transaction<'a> {
+    conn: &'a Connection,
+    done: bool,
+}
+
+impl<'a> Transaction<'a> {
+    fn begin(conn: &'a Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch("SAVEPOINT store")?;
+        Ok(Self { conn, done: false })
     }
 
-    pub fn open(&self, id: i32) -> Result<(), BookmarkError> {
-        let bookmark = self.get(id)?.ok_or(BookmarkError::NotFound(id))?;
-        open::that(&bookmark.url).map_err(|e| BookmarkError::OpenUrl(e.to_string()))?;
-        Ok(())
+    pub(crate) fn commit(mut self) -> rusqlite::Result<()> {
+        self.done = true;
+        self.conn.execute_batch("RELEASE store")
     }
+}
 
-    pub fn add(&self, input: BookmarkInput) -> Result<i32, BookmarkError> {
-        let tags_str = input
-            .tags
-            .map(|t| {
-                if t.is_empty() {
-                    ",".to_string()
-                } else {
-                    format!(",{},", t.join(","))
-                }
-            })
-            .unwrap_or_else(|| ",".to_string());
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.conn.execute_batch("ROLLBACK TO store; RELEASE store");
+        }
+    }
+}
 
-        self.conn.execute(
-            "INSERT INTO bookmarks (URL, metadata, tags, desc) VALUES (?1, ?2, ?3, ?4)",
-            (
-                &input.url,
-                input.title.as_deref().unwrap_or(""),
-                &tags_str,
-                input.description.as_deref().unwrap_or(""),
-            ),
-        )?;
+/// A connection to the bookmarks database.
+///
+/// The store is `Send` but not `Sync`: share a long-lived instance between
+/// threads behind a `Mutex` instead of reopening it for every call.
+pub struct BookmarkStore {
+    conn: Connection,
+}
 
-        Ok(self.conn.last_insert_rowid() as i32)
+impl BookmarkStore {
+    pub fn new() -> Result<Self, BookmarkError> {
+        Self::with_path(Self::get_db_path()?)
     }
 
-    pub fn update(
-        &self,
-        id: i32,
-        url: Option<&str>,
-        title: Option<&str>,
-        tags: Option<&[String]>,
-        description: Option<&str>,
-    ) -> Result<(), BookmarkError> {
-        let mut updates: Vec<String> = Vec::new();
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-
-        if let Some(u) = url {
-            updates.push(format!("URL = ?{}", params.len() + 1));
-            params.push(Box::new(u.to_string()));
+    /// Opens (creating if needed) the database at `path`. WAL mode lets
+    /// readers such as buku or a second window proceed while MRunner writes,
+    /// and the busy timeout waits out their short write locks.
+    pub fn with_path(path: PathBuf) -> Result<Self, BookmarkError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
         }
 
-        if let Some(t) = title {
-            updates.push(format!("metadata = ?{}", params.len() + 1));
-            params.push(Box::new(t.to_string()));
-        }
+        Self::from_connection(Connection::open(&path)?)
+    }
 
-        if let Some(tg) = tags {
-            let formatted = if tg.is_empty() {
-                ",".to_string()
-            } else {
-                format!(",{},", tg.join(","))
-            };
-            updates.push(format!("tags = ?{}", params.len() + 1));
-            params.push(Box::new(formatted));
-        }
+    /// Opens (creating if needed) MRunner's database encrypted with
+    /// `passphrase`; see [`Self::with_path_encrypted`].
+    #[cfg(feature = "encryption")]
+    pub fn new_encrypted(passphrase: &str) -> Result<Self, BookmarkError> {
+        Self::with_path_encrypted(Self::get_db_path()?, passphrase)
+    }
 
-        if let Some(d) = description {
-            updates.push(format!("desc = ?{}", params.len() + 1));
-            params.push(Box::new(d.to_string()));
+    /// Opens (creating if needed) the SQLCipher database at `path` with
+    /// `passphrase`. A plaintext database found at `path` is encrypted in
+    /// place first, so switching an existing install to encryption keeps its
+    /// bookmarks.
+    #[cfg(feature = "encryption")]
+    pub fn with_path_encrypted(path: PathBuf, passphrase: &str) -> Result<Self, BookmarkError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if Self::is_plaintext(&path)? {
+            Self::encrypt_file(&path, passphrase)?;
         }
 
-        if updates.is_empty() {
-            return Ok(());
+        let conn = Connection::open(&path)?;
+        conn.pragma_update(None, "key", passphrase)?;
+        // The key is only checked when the file is first read
+        match conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(())) {
+            Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::NotADatabase => {
+                return Err(BookmarkError::WrongPassphrase);
+            }
+            result => result?,
         }
 
-        params.push(Box::new(id));
-        let sql = format!(
-            "UPDATE bookmarks SET {} WHERE id = ?{}",
-            updates.join(", "),
-            params.len()
-        );
+        Self::from_connection(conn)
+    }
 
-        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|b| b.as_ref()).collect();
-        self.conn.execute(&sql, param_refs.as_slice())?;
+    /// Whether `path` holds an unencrypted SQLite database.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn is_plaintext(path: &Path) -> Result<bool, BookmarkError> {
+        use std::io::Read;
 
-        Ok(())
+        let mut header = [0; 16];
+        match std::fs::File::open(path) {
+            Ok(mut file) => {
+                Ok(file.read_exact(&mut header).is_ok() && &header == b"SQLite format 3\0")
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
     }
 
-    pub fn delete(&self, id: i32) -> Result<(), BookmarkError> {
-        self.conn
-            .execute("DELETE FROM bookmarks WHERE id = ?1", [id])?;
+    /// Replaces the plaintext database at `path` with an encrypted copy.
+    #[cfg(feature = "encryption")]
+    fn encrypt_file(path: &Path, passphrase: &str) -> Result<(), BookmarkError> {
+        let mut encrypted = path.as_os_str().to_owned();
+        encrypted.push(".encrypting");
+        let encrypted = PathBuf::from(encrypted);
+        if encrypted.exists() {
+            std::fs::remove_file(&encrypted)?;
+        }
+
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Fold the WAL into the file so nothing is left behind in it
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            (encrypted.to_string_lossy(), passphrase),
+        )?;
+        conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        conn.execute_batch(&format!("PRAGMA encrypted.user_version = {}", version))?;
+        conn.execute("DETACH DATABASE encrypted", [])?;
+        conn.close().map_err(|(_, e)| e)?;
+
+        for suffix in ["-wal", "-shm"] {
+            let mut file = path.as_os_str().to_owned();
+            file.push(suffix);
+            match std::fs::remove_file(PathBuf::from(file)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        std::fs::rename(&encrypted, path)?;
         Ok(())
     }
 
-    pub fn list_tags(&self) -> Result<Vec<Tag>, BookmarkError> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT tags FROM bookmarks WHERE tags != ','")?;
+    fn from_connection(conn: Connection) -> Result<Self, BookmarkError> {
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
 
-        let mut tag_counts: HashMap<String, i32> = HashMap::new();
+        let mut store = Self { conn };
+        store.migrate()?;
+        store.refresh_url_keys()?;
 
-        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        Ok(store)
+    }
 
-        for row in rows.flatten() {
-            for tag in row.trim_matches(',').split(',') {
-                let tag = tag.trim();
-                if !tag.is_empty() {
-                    *tag_counts.entry(tag.to_string()).or_insert(0) += 1;
-                }
-            }
-        }
+    /// Opens the database of the buku command line bookmark manager, whose
+    /// `bookmarks` table MRunner shares. MRunner keeps its own data (search
+    /// index, visits, ...) in separate tables, so buku keeps working on it.
+    pub fn buku() -> Result<Self, BookmarkError> {
+        Self::with_path(Self::get_buku_db_path()?)
+    }
 
-        let mut tags: Vec<Tag> = tag_counts
-            .into_iter()
-            .map(|(name, count)| Tag { name, count })
-            .collect();
+    /// buku stores its database under `$XDG_DATA_HOME/buku` (`%APPDATA%\buku`
+    /// on Windows).
+    pub fn get_buku_db_path() -> Result<PathBuf, BookmarkError> {
+        dirs::data_dir()
+            .map(|p| p.join("buku").join("bookmarks.db"))
+            .ok_or(BookmarkError::DataDirNotFound)
+    }
 
-        tags.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+    /// Where [`Self::new`] keeps MRunner's own database.
+    pub fn get_db_path() -> Result<PathBuf, BookmarkError> {
+        Ok(Self::data_dir()?.join("bookmarks.db"))
+    }
 
-        Ok(tags)
+    /// MRunner's data directory, holding the database and cached files.
+    pub(crate) fn data_dir() -> Result<PathBuf, BookmarkError> {
+        storage::data_dir().ok_or(BookmarkError::DataDirNotFound)
     }
 
-    pub fn rename_tag(&self, old_tag: &str, new_tag: &str) -> Result<(), BookmarkError> {
-        let old_pattern = format!(",{},", old_tag);
-        let new_pattern = format!(",{},", new_tag);
+    /// The database file, `None` for in-memory databases.
+    pub fn path(&self) -> Option<&Path> {
+        self.conn.path().filter(|p| !p.is_empty()).map(Path::new)
+    }
 
-        self.conn.execute(
-            "UPDATE bookmarks SET tags = REPLACE(tags, ?1, ?2) WHERE tags LIKE ?3",
-            (&old_pattern, &new_pattern, format!("%{}%", old_pattern)),
-        )?;
+    /// SQLite's `data_version` for this connection. It changes whenever
+    /// another connection, in this process or another, commits a change, but
+    /// not for changes made through this store.
+    pub fn data_version(&self) -> Result<i64, BookmarkError> {
+        let version = self
+            .conn
+            .query_row("PRAGMA data_version", [], |row| row.get(0))?;
+        Ok(version)
+    }
 
-        Ok(())
+    /// Ids of all bookmarks.
+    pub(crate) fn bookmark_ids(&self) -> Result<HashSet<i32>, BookmarkError> {
+        let mut stmt = self.conn.prepare_cached("SELECT id FROM bookmarks")?;
+        let ids = stmt.query_map([], |row| row.get(0))?;
+        Ok(ids.filter_map(|r| r.ok()).collect())
     }
 
-    pub fn delete_tag(&self, tag: &str) -> Result<(), BookmarkError> {
-        let tag_pattern = format!(",{},", tag);
+    /// The highest change counter of any bookmark. Triggers raise a
+    /// bookmark's counter above all others whenever it is edited.
+    pub(crate) fn last_change(&self) -> Result<i64, BookmarkError> {
+        let last = self.conn.query_row(
+            "SELECT IFNULL(MAX(changed), 0) FROM bookmark_meta",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(last)
+    }
 
-        self.conn.execute(
-            "UPDATE bookmarks SET tags = REPLACE(tags, ?1, ',') WHERE tags LIKE ?2",
-            (&tag_pattern, format!("%{}%", tag_pattern)),
+    /// Ids of the bookmarks edited after change counter `since`, up to and
+    /// including `until`.
+    pub(crate) fn changed_between(
+        &self,
+        since: i64,
+        until: i64,
+    ) -> Result<Vec<i32>, BookmarkError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT bookmark_id FROM bookmark_meta WHERE changed > ?1 AND changed <= ?2",
         )?;
+        let ids = stmt.query_map((since, until), |row| row.get(0))?;
+        Ok(ids.filter_map(|r| r.ok()).collect())
+    }
 
-        Ok(())
+    /// A hash of the whole folder tree.
+    pub(crate) fn folders_digest(&self) -> Result<u64, BookmarkError> {
+        let mut hasher = DefaultHasher::new();
+        for folder in self.list_folders()? {
+            (folder.id, folder.parent_id, folder.name, folder.position).hash(&mut hasher);
+        }
+        Ok(hasher.finish())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::tempdir;
+    /// Latest schema version this build knows how to read and write.
+    pub const SCHEMA_VERSION: i32 = MIGRATIONS.len() as i32;
 
-    fn create_test_store() -> BookmarkStore {
-        let dir = tempdir().unwrap();
-        let db_path = dir.path().join("test.db");
-        BookmarkStore::with_path(db_path).unwrap()
+    /// Starts a [`Transaction`], nested in the one already open if any.
+    pub(crate) fn transaction(&self) -> Result<Transaction<'_>, BookmarkError> {
+        Ok(Transaction::begin(&self.conn)?)
     }
 
-    #[test]
-    fn test_add_and_get_bookmark() {
-        let store = create_test_store();
+    pub fn schema_version(&self) -> Result<i32, BookmarkError> {
+        Ok(storage::schema_version(&self.conn)?)
+    }
 
-        let input = BookmarkInput::new("https://example.com")
-            .with_title("Example")
-            .with_tags(vec!["test".to_string(), "example".to_string()])
-            .with_description("A test bookmark");
+    fn migrate(&mut self) -> Result<(), BookmarkError> {
+        Ok(storage::migrate(&mut self.conn, MIGRATIONS)?)
+    }
 
-        let id = store.add(input).unwrap();
-        let bookmark = store.get(id).unwrap().unwrap();
+    fn row_to_bookmark(row: &Row) -> rusqlite::Result<Bookmark> {
+        let tags = parse_tags(&row.get::<_, String>(3)?);
 
-        assert_eq!(bookmark.url, "https://example.com");
-        assert_eq!(bookmark.title, "Example");
-        assert_eq!(bookmark.tags, vec!["test", "example"]);
-        assert_eq!(bookmark.description, "A test bookmark");
+        Ok(Bookmark {
+            id: row.get(0)?,
+            url: row.get(1)?,
+            title: row.get(2)?,
+            tags,
+            description: row.get(4)?,
+            added_at: row.get(5)?,
+            immutable_title: row.get::<_, Option<i32>>(6)?.unwrap_or(0) & FLAG_IMMUTABLE_TITLE != 0,
+            keyword: row.get(7)?,
+            folder_id: row.get(8)?,
+        })
     }
 
-    #[test]
-    fn test_list_bookmarks() {
-        let store = create_test_store();
-
-        store
-            .add(BookmarkInput::new("https://example1.com"))
-            .unwrap();
-        store
-            .add(BookmarkInput::new("https://example2.com"))
-            .unwrap();
+    pub fn list(&self, limit: Option<u32>) -> Result<Vec<Bookmark>, BookmarkError> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!("{} ORDER BY b.id DESC LIMIT ?1", SELECT_BOOKMARKS))?;
+        // A negative limit means no limit
+        let bookmarks = stmt
+            .query_map([limit.map_or(-1, i64::from)], Self::row_to_bookmark)?
+            .filter_map(|r| r.ok())
+            .collect();
 
-        let bookmarks = store.list(None).unwrap();
-        assert_eq!(bookmarks.len(), 2);
+        Ok(bookmarks)
     }
 
-    #[test]
-    fn test_delete_bookmark() {
-        let store = create_test_store();
+    pub fn list_by_frecency(&self, limit: Option<u32>) -> Result<Vec<Bookmark>, BookmarkError> {
+        self.load_frecency()?;
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "{} ORDER BY {} DESC, b.id DESC LIMIT ?1",
+            SELECT_BOOKMARKS, FRECENCY_KEY
+        ))?;
+        let bookmarks = stmt
+            .query_map([limit.map_or(-1, i64::from)], Self::row_to_bookmark)?
+            .filter_map(|r| r.ok())
+            .collect();
 
-        let id = store
+        Ok(bookmarks)
+    }
+
+    pub fn search(
+        &self,
+        query: &str,
+        tag_filter: Option<&[String]>,
+        tag_or: bool,
+    ) -> Result<Vec<Bookmark>, BookmarkError> {
+        self.search_ordered(query, tag_filter, tag_or, BookmarkOrder::Default)
+    }
+
+    pub fn search_ordered(
+        &self,
+        query: &str,
+        tag_filter: Option<&[String]>,
+        tag_or: bool,
+        order: BookmarkOrder,
+    ) -> Result<Vec<Bookmark>, BookmarkError> {
+        self.search_scoped(query, tag_filter, tag_or, order, None)
+    }
+
+    /// Like [`Self::search_ordered`], limited to bookmarks in `folder` or any
+    /// folder below it.
+    pub fn search_in_folder(
+        &self,
+        folder: i32,
+        query: &str,
+        tag_filter: Option<&[String]>,
+        tag_or: bool,
+        order: BookmarkOrder,
+    ) -> Result<Vec<Bookmark>, BookmarkError> {
+        self.require_folder(folder)?;
+        self.search_scoped(query, tag_filter, tag_or, order, Some(folder))
+    }
+
+    fn search_scoped(
+        &self,
+        query: &str,
+        tag_filter: Option<&[String]>,
+        tag_or: bool,
+        order: BookmarkOrder,
+        folder: Option<i32>,
+    ) -> Result<Vec<Bookmark>, BookmarkError> {
+        let filter = Filter::new(query, tag_filter, tag_or, folder);
+
+        if filter.conditions.is_empty() {
+            return match order {
+                BookmarkOrder::Default => self.list(None),
+                BookmarkOrder::Frecency => self.list_by_frecency(None),
+            };
+        }
+
+        // Frecency ties keep the default order
+        let mut keys = Vec::new();
+        if order == BookmarkOrder::Frecency {
+            self.load_frecency()?;
+            keys.push(format!("{} DESC", FRECENCY_KEY));
+        }
+        if filter.fts {
+            keys.push(format!("bm25(bookmarks_fts, {})", FTS_WEIGHTS));
+        }
+        keys.push("b.id DESC".to_string());
+
+        let sql = format!("{} ORDER BY {}", filter.select(), keys.join(", "));
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let bookmarks = stmt
+            .query_map(params_from_iter(&filter.params), Self::row_to_bookmark)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(bookmarks)
+    }
+
+    /// One page of all bookmarks; see [`Self::search_page`].
+    pub fn list_page(&self, page: &PageRequest) -> Result<BookmarkPage, BookmarkError> {
+        self.search_page("", None, false, None, page)
+    }
+
+    /// One page of the bookmarks matching `query` and `tag_filter`, within
+    /// `folder` and the folders below it when given. Pass the previous
+    /// page's `next_cursor` in `page` to continue.
+    pub fn search_page(
+        &self,
+        query: &str,
+        tag_filter: Option<&[String]>,
+        tag_or: bool,
+        folder: Option<i32>,
+        page: &PageRequest,
+    ) -> Result<BookmarkPage, BookmarkError> {
+        if let Some(folder) = folder {
+            self.require_folder(folder)?;
+        }
+
+        let mut filter = Filter::new(query, tag_filter, tag_or, folder);
+        let total: i64 = self
+            .conn
+            .prepare_cached(&format!("SELECT COUNT(*) FROM ({})", filter.select()))?
+            .query_row(params_from_iter(&filter.params), |row| row.get(0))?;
+        // A zero limit only asks for the total
+        if page.limit == Some(0) {
+            return Ok(BookmarkPage {
+                bookmarks: Vec::new(),
+                total: total as usize,
+                next_cursor: None,
+            });
+        }
+        let position = cursor::decode(page)?;
+        let invalid = || BookmarkError::InvalidCursor(page.cursor.clone().unwrap_or_default());
+
+        // Rows past the limit only tell whether there is a next page
+        let limit = page.limit.map(|n| n as usize);
+        let fetch = limit.map_or(-1, |n| n as i64 + 1);
+
+        let (mut bookmarks, next) = match sort_key(page.sort, filter.fts) {
+            Some((key, natural_desc)) => {
+                let (direction, after) = if natural_desc != page.reverse {
+                    ("DESC", "<")
+                } else {
+                    ("ASC", ">")
+                };
+                match position {
+                    Some(Position::After { key: value, id }) => {
+                        let value = cursor::key_param(&value).ok_or_else(invalid)?;
+                        let n = filter.params.len();
+                        filter.conditions.push(format!(
+                            "({}, b.id) {} (?{}, ?{})",
+                            key,
+                            after,
+                            n + 1,
+                            n + 2
+                        ));
+                        filter.params.push(value);
+                        filter.params.push(Value::Integer(id.into()));
+                    }
+                    Some(Position::Offset(_)) => return Err(invalid()),
+                    None => {}
+                }
+
+                filter.params.push(Value::Integer(fetch));
+                let sql = format!(
+                    "{} ORDER BY {} {dir}, b.id {dir} LIMIT ?{}",
+                    filter.select(),
+                    key,
+                    filter.params.len(),
+                    dir = direction
+                );
+                let bookmarks: Vec<Bookmark> = self
+                    .conn
+                    .prepare_cached(&sql)?
+                    .query_map(params_from_iter(&filter.params), Self::row_to_bookmark)?
+                    .filter_map(|r| r.ok())
+                    .collect();
+                (bookmarks, None)
+            }
+            None => {
+                let offset = match position {
+                    Some(Position::Offset(offset)) => offset,
+                    Some(Position::After { .. }) => return Err(invalid()),
+                    None => 0,
+                };
+
+                // Frecency scores change with every visit, so pages of either
+                // order continue from an offset rather than a key
+                let (key, natural_desc) = if page.sort == BookmarkSort::Frecency {
+                    self.load_frecency()?;
+                    (FRECENCY_KEY.to_string(), true)
+                } else {
+                    (format!("bm25(bookmarks_fts, {})", FTS_WEIGHTS), false)
+                };
+                let direction = if natural_desc != page.reverse {
+                    "DESC"
+                } else {
+                    "ASC"
+                };
+                let n = filter.params.len();
+                filter.params.push(Value::Integer(fetch));
+                filter.params.push(Value::Integer(offset as i64));
+                let sql = format!(
+                    "{} ORDER BY {} {dir}, b.id {dir} LIMIT ?{} OFFSET ?{}",
+                    filter.select(),
+                    key,
+                    n + 1,
+                    n + 2,
+                    dir = direction
+                );
+                let bookmarks: Vec<Bookmark> = self
+                    .conn
+                    .prepare_cached(&sql)?
+                    .query_map(params_from_iter(&filter.params), Self::row_to_bookmark)?
+                    .filter_map(|r| r.ok())
+                    .collect();
+                let next = Position::Offset(offset + limit.unwrap_or(0));
+                (bookmarks, Some(next))
+            }
+        };
+
+        let mut next_cursor = None;
+        if let Some(n) = limit.filter(|&n| bookmarks.len() > n) {
+            bookmarks.truncate(n);
+            let position = match next {
+                Some(position) => position,
+                None => {
+                    let last = &bookmarks[n - 1];
+                    Position::After {
+                        key: self.sort_value(page.sort, last)?,
+                        id: last.id,
+                    }
+                }
+            };
+            next_cursor = Some(cursor::encode(page, position));
+        }
+
+        Ok(BookmarkPage {
+            bookmarks,
+            total: total as usize,
+            next_cursor,
+        })
+    }
+
+    /// Value of the keyset sort key for `bookmark`, as compared by
+    /// [`sort_key`].
+    fn sort_value(
+        &self,
+        sort: BookmarkSort,
+        bookmark: &Bookmark,
+    ) -> Result<serde_json::Value, BookmarkError> {
+        Ok(match sort {
+            BookmarkSort::Created => bookmark.added_at.unwrap_or(0).into(),
+            BookmarkSort::Title => bookmark.title.clone().into(),
+            BookmarkSort::Url => bookmark.url.clone().into(),
+            BookmarkSort::LastOpened => self.last_opened(bookmark.id)?.unwrap_or(0).into(),
+            BookmarkSort::Relevance | BookmarkSort::Frecency => bookmark.id.into(),
+        })
+    }
+
+    /// Scores every visited bookmark into `temp.frecency`, so that SQL can
+    /// order and page by [`FRECENCY_KEY`].
+    fn load_frecency(&self) -> Result<(), BookmarkError> {
+        let scores = self.frecency_scores()?;
+
+        let tx = self.transaction()?;
+        self.conn.execute_batch(
+            "CREATE TEMP TABLE IF NOT EXISTS frecency (
+                bookmark_id INTEGER PRIMARY KEY,
+                score REAL NOT NULL
+            );
+            DELETE FROM temp.frecency;",
+        )?;
+        {
+            let mut insert = self
+                .conn
+                .prepare_cached("INSERT INTO temp.frecency (bookmark_id, score) VALUES (?1, ?2)")?;
+            for (id, score) in scores {
+                insert.execute((id, score))?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Scores of visited bookmarks. Only the latest [`frecency::SAMPLE_SIZE`]
+    /// visits of each are read, along with its visit count.
+    fn frecency_scores(&self) -> Result<HashMap<i32, f64>, BookmarkError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT bookmark_id, visit_count, visited_at FROM (
+                SELECT bookmark_id, visited_at,
+                    COUNT(*) OVER (PARTITION BY bookmark_id) AS visit_count,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY bookmark_id ORDER BY visited_at DESC
+                    ) AS position
+                FROM bookmark_visits
+             )
+             WHERE position <= ?1
+             ORDER BY bookmark_id, visited_at DESC",
+        )?;
+
+        let mut visits: HashMap<i32, (i64, Vec<i64>)> = HashMap::new();
+        let rows = stmt.query_map([frecency::SAMPLE_SIZE as i64], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get(1)?, row.get(2)?))
+        })?;
+        for (id, count, visited_at) in rows.flatten() {
+            let (total, times) = visits.entry(id).or_default();
+            *total = count;
+            times.push(visited_at);
+        }
+
+        let now = unix_now();
+        Ok(visits
+            .into_iter()
+            .map(|(id, (count, times))| (id, frecency::score(count, &times, now)))
+            .collect())
+    }
+
+    /// Records that a bookmark was opened, feeding the frecency ranking.
+    pub fn record_visit(&self, id: i32) -> Result<(), BookmarkError> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO bookmark_visits (bookmark_id, visited_at) VALUES (?1, ?2)",
+            )?
+            .execute((id, unix_now()))?;
+        Ok(())
+    }
+
+    pub fn visit_count(&self, id: i32) -> Result<i64, BookmarkError> {
+        let count = self.conn.query_row(
+            "SELECT COUNT(*) FROM bookmark_visits WHERE bookmark_id = ?1",
+            [id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Unix timestamp of the last time the bookmark was opened, if ever.
+    pub fn last_opened(&self, id: i32) -> Result<Option<i64>, BookmarkError> {
+        let last = self.conn.query_row(
+            "SELECT MAX(visited_at) FROM bookmark_visits WHERE bookmark_id = ?1",
+            [id],
+            |row| row.get(0),
+        )?;
+        Ok(last)
+    }
+
+    pub fn get(&self, id: i32) -> Result<Option<Bookmark>, BookmarkError> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!("{} WHERE b.id = ?1", SELECT_BOOKMARKS))?;
+
+        let bookmark = stmt.query_row([id], Self::row_to_bookmark).ok();
+        Ok(bookmark)
+    }
+
+    pub fn open(&self, id: i32) -> Result<(), BookmarkError> {
+        let bookmark = self.get(id)?.ok_or(BookmarkError::NotFound(id))?;
+        open::that(&bookmark.url).map_err(|e| BookmarkError::OpenUrl(e.to_string()))?;
+        self.record_visit(id)
+    }
+
+    /// Fills in canonical URL keys missing for rows added or edited outside
+    /// MRunner.
+    fn refresh_url_keys(&self) -> Result<(), BookmarkError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT b.id, b.URL FROM bookmarks b
+             JOIN bookmark_meta m ON m.bookmark_id = b.id
+             WHERE m.url_key IS NULL",
+        )?;
+
+        let stale: Vec<(i32, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        for (id, url) in stale {
+            self.conn.execute(
+                "UPDATE bookmark_meta SET url_key = ?1 WHERE bookmark_id = ?2",
+                (canonical::canonicalize(&url), id),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the id of a bookmark whose canonical URL equals `url`'s,
+    /// ignoring bookmark `except`.
+    pub fn find_by_url(
+        &self,
+        url: &str,
+        except: Option<i32>,
+    ) -> Result<Option<i32>, BookmarkError> {
+        self.refresh_url_keys()?;
+
+        let id = self
+            .conn
+            .query_row(
+                "SELECT bookmark_id FROM bookmark_meta
+                 WHERE url_key = ?1 AND bookmark_id IS NOT ?2 LIMIT 1",
+                (canonical::canonicalize(url), except),
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(id)
+    }
+
+    /// Adds a bookmark at the end of the folder named by `input.folder`,
+    /// failing with [`BookmarkError::Duplicate`] if the URL matches an
+    /// existing one after canonicalization (case, trailing slash, default
+    /// port, tracking parameters, `~`).
+    pub fn add(&self, input: BookmarkInput) -> Result<i32, BookmarkError> {
+        let tx = self.transaction()?;
+
+        if let Some(existing) = self.find_by_url(&input.url, None)? {
+            return Err(BookmarkError::Duplicate(existing));
+        }
+
+        let keyword = input
+            .keyword
+            .as_deref()
+            .map(keyword::normalize)
+            .transpose()?;
+        if let Some(keyword) = &keyword {
+            if self.find_by_keyword(keyword)?.is_some() {
+                return Err(BookmarkError::DuplicateKeyword(keyword.clone()));
+            }
+        }
+
+        let tags_str = format_tags(input.tags.as_deref().unwrap_or_default());
+        let flags = if input.immutable_title {
+            FLAG_IMMUTABLE_TITLE
+        } else {
+            0
+        };
+
+        self.conn.execute(
+            "INSERT INTO bookmarks (URL, metadata, tags, desc, flags) VALUES (?1, ?2, ?3, ?4, ?5)",
+            (
+                &input.url,
+                input.title.as_deref().unwrap_or(""),
+                &tags_str,
+                input.description.as_deref().unwrap_or(""),
+                flags,
+            ),
+        )?;
+
+        let id = self.conn.last_insert_rowid() as i32;
+
+        self.conn.execute(
+            "UPDATE bookmark_meta SET url_key = ?1, added_at = IFNULL(?2, added_at), keyword = ?3
+             WHERE bookmark_id = ?4",
+            (
+                canonical::canonicalize(&input.url),
+                input.added_at,
+                keyword,
+                id,
+            ),
+        )?;
+
+        let folder = self.ensure_folder(&input.folder)?;
+        self.place_bookmark(id, folder, None)?;
+
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Adds every input in a single transaction. URLs that already exist are
+    /// counted as skipped rather than failing the whole import.
+    pub fn import(
+        &self,
+        inputs: impl IntoIterator<Item = BookmarkInput>,
+    ) -> Result<ImportReport, BookmarkError> {
+        let tx = self.transaction()?;
+        let mut report = ImportReport::default();
+
+        for input in inputs {
+            self.import_one(input, &mut report);
+        }
+
+        tx.commit()?;
+        Ok(report)
+    }
+
+    /// Adds a single imported bookmark, returning its id if it was added. A
+    /// keyword that is invalid or already in use is dropped.
+    pub(crate) fn import_one(
+        &self,
+        mut input: BookmarkInput,
+        report: &mut ImportReport,
+    ) -> Option<i32> {
+        if input.url.trim().is_empty() {
+            report.failed += 1;
+            return None;
+        }
+
+        if let Some(keyword) = &input.keyword {
+            let usable = keyword::normalize(keyword)
+                .is_ok_and(|k| matches!(self.find_by_keyword(&k), Ok(None)));
+            if !usable {
+                input.keyword = None;
+            }
+        }
+
+        match self.add(input) {
+            Ok(id) => {
+                report.added += 1;
+                Some(id)
+            }
+            Err(BookmarkError::Duplicate(_)) => {
+                report.skipped += 1;
+                None
+            }
+            Err(BookmarkError::Database(rusqlite::Error::SqliteFailure(e, _)))
+                if e.code == ErrorCode::ConstraintViolation =>
+            {
+                report.skipped += 1;
+                None
+            }
+            Err(_) => {
+                report.failed += 1;
+                None
+            }
+        }
+    }
+
+    /// Imports a Chrome `Bookmarks` JSON file, usually found at
+    /// `<chrome config dir>/<profile directory>/Bookmarks`.
+    pub fn import_chrome(&self, bookmarks_file: &Path) -> Result<ImportReport, BookmarkError> {
+        let content = std::fs::read_to_string(bookmarks_file)?;
+        self.import(chrome::parse(&content)?)
+    }
+
+    /// Imports bookmarks from a Firefox profile's `places.sqlite`. The
+    /// original file is never opened, so Firefox may keep running. Browsing
+    /// history of newly added bookmarks is imported as visits.
+    pub fn import_firefox(&self, places: &Path) -> Result<ImportReport, BookmarkError> {
+        let entries = firefox::read(places)?;
+
+        let tx = self.transaction()?;
+        let mut report = ImportReport::default();
+
+        for entry in entries {
+            if let Some(id) = self.import_one(entry.input, &mut report) {
+                for visited_at in entry.visits {
+                    self.conn.execute(
+                        "INSERT INTO bookmark_visits (bookmark_id, visited_at) VALUES (?1, ?2)",
+                        (id, visited_at),
+                    )?;
+                }
+            }
+        }
+
+        tx.commit()?;
+        Ok(report)
+    }
+
+    /// Imports a Netscape `bookmarks.html` file as exported by browsers.
+    pub fn import_netscape(&self, path: &Path) -> Result<ImportReport, BookmarkError> {
+        let content = std::fs::read_to_string(path)?;
+        self.import(netscape::parse(&content))
+    }
+
+    /// Writes every folder and bookmark to `path` in the Netscape
+    /// `bookmarks.html` format, in folder order. Returns the number of
+    /// bookmarks written.
+    pub fn export_netscape(&self, path: &Path) -> Result<usize, BookmarkError> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!("{} ORDER BY {}", SELECT_BOOKMARKS, FOLDER_ORDER))?;
+        let bookmarks: Vec<Bookmark> = stmt
+            .query_map([], Self::row_to_bookmark)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        std::fs::write(path, netscape::write(&self.list_folders()?, &bookmarks))?;
+        Ok(bookmarks.len())
+    }
+
+    pub fn update(
+        &self,
+        id: i32,
+        url: Option<&str>,
+        title: Option<&str>,
+        tags: Option<&[String]>,
+        description: Option<&str>,
+    ) -> Result<(), BookmarkError> {
+        let mut updates: Vec<String> = Vec::new();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        if let Some(u) = url {
+            if let Some(existing) = self.find_by_url(u, Some(id))? {
+                return Err(BookmarkError::Duplicate(existing));
+            }
+            updates.push(format!("URL = ?{}", params.len() + 1));
+            params.push(Box::new(u.to_string()));
+        }
+
+        if let Some(t) = title {
+            updates.push(format!("metadata = ?{}", params.len() + 1));
+            params.push(Box::new(t.to_string()));
+        }
+
+        if let Some(tg) = tags {
+            updates.push(format!("tags = ?{}", params.len() + 1));
+            params.push(Box::new(format_tags(tg)));
+        }
+
+        if let Some(d) = description {
+            updates.push(format!("desc = ?{}", params.len() + 1));
+            params.push(Box::new(d.to_string()));
+        }
+
+        if updates.is_empty() {
+            return Ok(());
+        }
+
+        params.push(Box::new(id));
+        let sql = format!(
+            "UPDATE bookmarks SET {} WHERE id = ?{}",
+            updates.join(", "),
+            params.len()
+        );
+
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|b| b.as_ref()).collect();
+        self.conn.execute(&sql, param_refs.as_slice())?;
+
+        Ok(())
+    }
+
+    /// [`Self::update`] and, unless `keyword` is `None`, [`Self::set_keyword`]
+    /// in one transaction, so a rejected keyword leaves the bookmark as it
+    /// was.
+    pub fn update_with_keyword(
+        &self,
+        id: i32,
+        url: Option<&str>,
+        title: Option<&str>,
+        tags: Option<&[String]>,
+        description: Option<&str>,
+        keyword: Option<Option<&str>>,
+    ) -> Result<(), BookmarkError> {
+        let tx = self.transaction()?;
+        self.update(id, url, title, tags, description)?;
+        if let Some(keyword) = keyword {
+            self.set_keyword(id, keyword)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Groups bookmarks whose URLs are equal after canonicalization. Each
+    /// group is ordered oldest first.
+    pub fn find_duplicates(&self) -> Result<Vec<Vec<Bookmark>>, BookmarkError> {
+        self.refresh_url_keys()?;
+
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "{} WHERE m.url_key IN (
+                SELECT url_key FROM bookmark_meta GROUP BY url_key HAVING COUNT(*) > 1
+             ) ORDER BY m.url_key, b.id",
+            SELECT_BOOKMARKS
+        ))?;
+        let bookmarks: Vec<Bookmark> = stmt
+            .query_map([], Self::row_to_bookmark)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let mut groups: Vec<Vec<Bookmark>> = Vec::new();
+        let mut last_key: Option<String> = None;
+        for bookmark in bookmarks {
+            let key = canonical::canonicalize(&bookmark.url);
+            if last_key.as_ref() == Some(&key) {
+                if let Some(group) = groups.last_mut() {
+                    group.push(bookmark);
+                }
+            } else {
+                groups.push(vec![bookmark]);
+                last_key = Some(key);
+            }
+        }
+
+        Ok(groups)
+    }
+
+    /// Merges `others` into `keep`: tags are combined, distinct descriptions
+    /// are appended, an empty title is taken from the first other bookmark
+    /// that has one, and visits move over. The other bookmarks are deleted.
+    pub fn merge(&self, keep: i32, others: &[i32]) -> Result<Bookmark, BookmarkError> {
+        let tx = self.transaction()?;
+
+        let mut target = self.get(keep)?.ok_or(BookmarkError::NotFound(keep))?;
+        let mut earliest = target.added_at;
+
+        for &id in others.iter().filter(|&&id| id != keep) {
+            let other = self.get(id)?.ok_or(BookmarkError::NotFound(id))?;
+
+            for tag in other.tags {
+                if !target.tags.contains(&tag) {
+                    target.tags.push(tag);
+                }
+            }
+            if target.title.is_empty() {
+                target.title = other.title;
+            }
+            let description = other.description.trim();
+            if !description.is_empty() && !target.description.contains(description) {
+                if !target.description.is_empty() {
+                    target.description.push_str("\n\n");
+                }
+                target.description.push_str(description);
+            }
+            earliest = match (earliest, other.added_at) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+
+            self.conn.execute(
+                "UPDATE bookmark_visits SET bookmark_id = ?1 WHERE bookmark_id = ?2",
+                (keep, id),
+            )?;
+            self.remove(id)?;
+        }
+
+        self.update(
+            keep,
+            None,
+            Some(&target.title),
+            Some(&target.tags),
+            Some(&target.description),
+        )?;
+        self.conn.execute(
+            "UPDATE bookmark_meta SET added_at = ?1 WHERE bookmark_id = ?2",
+            (earliest, keep),
+        )?;
+
+        tx.commit()?;
+        self.get(keep)?.ok_or(BookmarkError::NotFound(keep))
+    }
+
+    /// Sets or clears buku's immutable-title flag, leaving other flag bits
+    /// untouched.
+    pub fn set_immutable_title(&self, id: i32, immutable: bool) -> Result<(), BookmarkError> {
+        let sql = if immutable {
+            "UPDATE bookmarks SET flags = IFNULL(flags, 0) | ?1 WHERE id = ?2"
+        } else {
+            "UPDATE bookmarks SET flags = IFNULL(flags, 0) & ~?1 WHERE id = ?2"
+        };
+
+        if self.conn.execute(sql, (FLAG_IMMUTABLE_TITLE, id))? == 0 {
+            return Err(BookmarkError::NotFound(id));
+        }
+        Ok(())
+    }
+
+    fn row_to_folder(row: &Row) -> rusqlite::Result<Folder> {
+        Ok(Folder {
+            id: row.get(0)?,
+            parent_id: row.get(1)?,
+            name: row.get(2)?,
+            position: row.get(3)?,
+        })
+    }
+
+    /// Every folder, top-level ones first, then by parent and position.
+    pub fn list_folders(&self) -> Result<Vec<Folder>, BookmarkError> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "{} ORDER BY parent_id IS NOT NULL, parent_id, position, id",
+            SELECT_FOLDERS
+        ))?;
+        let folders = stmt
+            .query_map([], Self::row_to_folder)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(folders)
+    }
+
+    pub fn get_folder(&self, id: i32) -> Result<Option<Folder>, BookmarkError> {
+        let folder = self
+            .conn
+            .prepare_cached(&format!("{} WHERE id = ?1", SELECT_FOLDERS))?
+            .query_row([id], Self::row_to_folder)
+            .optional()?;
+        Ok(folder)
+    }
+
+    fn require_folder(&self, id: i32) -> Result<Folder, BookmarkError> {
+        self.get_folder(id)?
+            .ok_or(BookmarkError::FolderNotFound(id))
+    }
+
+    /// Creates a folder at the end of `parent`, or of the top level.
+    pub fn create_folder(&self, name: &str, parent: Option<i32>) -> Result<i32, BookmarkError> {
+        let name = folder_name(name)?;
+        if let Some(parent) = parent {
+            self.require_folder(parent)?;
+        }
+
+        self.conn
+            .prepare_cached(
+                "INSERT INTO folders (parent_id, name, position) VALUES (?1, ?2,
+                    (SELECT IFNULL(MAX(position), -1) + 1 FROM folders WHERE parent_id IS ?1))",
+            )?
+            .execute((parent, name))?;
+        Ok(self.conn.last_insert_rowid() as i32)
+    }
+
+    /// Returns the folder reached by following `path` from the top level,
+    /// creating missing folders, or `None` for an empty path.
+    pub(crate) fn ensure_folder(&self, path: &[String]) -> Result<Option<i32>, BookmarkError> {
+        let mut parent = None;
+
+        for name in path.iter().map(|n| n.trim()).filter(|n| !n.is_empty()) {
+            let existing = self
+                .conn
+                .prepare_cached(
+                    "SELECT id FROM folders WHERE parent_id IS ?1 AND name = ?2
+                     ORDER BY position, id LIMIT 1",
+                )?
+                .query_row((parent, name), |row| row.get(0))
+                .optional()?;
+
+            parent = Some(match existing {
+                Some(id) => id,
+                None => self.create_folder(name, parent)?,
+            });
+        }
+
+        Ok(parent)
+    }
+
+    pub fn rename_folder(&self, id: i32, name: &str) -> Result<(), BookmarkError> {
+        let name = folder_name(name)?;
+        if self
+            .conn
+            .execute("UPDATE folders SET name = ?1 WHERE id = ?2", (name, id))?
+            == 0
+        {
+            return Err(BookmarkError::FolderNotFound(id));
+        }
+        Ok(())
+    }
+
+    /// Moves a folder, with everything in it, into `parent` (`None` for the
+    /// top level) at `position`, or at the end.
+    pub fn move_folder(
+        &self,
+        id: i32,
+        parent: Option<i32>,
+        position: Option<usize>,
+    ) -> Result<(), BookmarkError> {
+        self.require_folder(id)?;
+        if let Some(parent) = parent {
+            self.require_folder(parent)?;
+
+            let inside: bool = self.conn.query_row(
+                &format!("SELECT ?2 IN ({})", folder_subtree(1)),
+                (id, parent),
+                |row| row.get(0),
+            )?;
+            if inside {
+                return Err(BookmarkError::InvalidFolder(
+                    "cannot move a folder into itself".to_string(),
+                ));
+            }
+        }
+
+        let tx = self.transaction()?;
+        self.place_folder(id, parent, position)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Deletes a folder. Its bookmarks and subfolders move up to its parent
+    /// rather than being deleted.
+    pub fn delete_folder(&self, id: i32) -> Result<(), BookmarkError> {
+        let folder = self.require_folder(id)?;
+        let tx = self.transaction()?;
+
+        let children: Vec<i32> = self
+            .conn
+            .prepare_cached("SELECT id FROM folders WHERE parent_id = ?1 ORDER BY position, id")?
+            .query_map([id], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        for child in children {
+            self.place_folder(child, folder.parent_id, None)?;
+        }
+
+        for bookmark in self.list_folder(Some(id), false)? {
+            self.place_bookmark(bookmark.id, folder.parent_id, None)?;
+        }
+
+        self.conn
+            .execute("DELETE FROM folders WHERE id = ?1", [id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Puts folder `id` into `parent` at `position`, renumbering its new
+    /// siblings, or appends it when `position` is `None`.
+    fn place_folder(
+        &self,
+        id: i32,
+        parent: Option<i32>,
+        position: Option<usize>,
+    ) -> Result<(), BookmarkError> {
+        let Some(position) = position else {
+            self.conn
+                .prepare_cached(
+                    "UPDATE folders SET parent_id = ?1, position =
+                        (SELECT IFNULL(MAX(position), -1) + 1 FROM folders WHERE parent_id IS ?1)
+                     WHERE id = ?2",
+                )?
+                .execute((parent, id))?;
+            return Ok(());
+        };
+
+        let mut siblings: Vec<i32> = self
+            .conn
+            .prepare_cached(
+                "SELECT id FROM folders WHERE parent_id IS ?1 AND id != ?2 ORDER BY position, id",
+            )?
+            .query_map((parent, id), |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        siblings.insert(position.min(siblings.len()), id);
+
+        let mut stmt = self
+            .conn
+            .prepare_cached("UPDATE folders SET parent_id = ?1, position = ?2 WHERE id = ?3")?;
+        for (index, folder) in siblings.iter().enumerate() {
+            stmt.execute((parent, index as i32, folder))?;
+        }
+        Ok(())
+    }
+
+    /// Puts bookmark `id` into `folder` at `position`, renumbering the
+    /// folder, or appends it when `position` is `None`.
+    fn place_bookmark(
+        &self,
+        id: i32,
+        folder: Option<i32>,
+        position: Option<usize>,
+    ) -> Result<(), BookmarkError> {
+        let Some(position) = position else {
+            self.conn
+                .prepare_cached(
+                    "UPDATE bookmark_meta SET folder_id = ?1, position =
+                        (SELECT IFNULL(MAX(position), -1) + 1 FROM bookmark_meta
+                         WHERE folder_id IS ?1)
+                     WHERE bookmark_id = ?2",
+                )?
+                .execute((folder, id))?;
+            return Ok(());
+        };
+
+        let mut siblings: Vec<i32> = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT b.id FROM bookmarks b JOIN bookmark_meta m ON m.bookmark_id = b.id
+                 WHERE m.folder_id IS ?1 AND b.id != ?2 ORDER BY {}",
+                FOLDER_ORDER
+            ))?
+            .query_map((folder, id), |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        siblings.insert(position.min(siblings.len()), id);
+
+        let mut stmt = self.conn.prepare_cached(
+            "UPDATE bookmark_meta SET folder_id = ?1, position = ?2 WHERE bookmark_id = ?3",
+        )?;
+        for (index, bookmark) in siblings.iter().enumerate() {
+            stmt.execute((folder, index as i32, bookmark))?;
+        }
+        Ok(())
+    }
+
+    /// Moves a bookmark into `folder` (`None` for the top level) at
+    /// `position`, or at the end.
+    pub fn move_bookmark(
+        &self,
+        id: i32,
+        folder: Option<i32>,
+        position: Option<usize>,
+    ) -> Result<(), BookmarkError> {
+        if self.get(id)?.is_none() {
+            return Err(BookmarkError::NotFound(id));
+        }
+        if let Some(folder) = folder {
+            self.require_folder(folder)?;
+        }
+
+        let tx = self.transaction()?;
+        self.place_bookmark(id, folder, position)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Bookmarks directly in `folder` (`None` for the top level) in manual
+    /// order or, with `recursive`, every bookmark below it, newest first.
+    pub fn list_folder(
+        &self,
+        folder: Option<i32>,
+        recursive: bool,
+    ) -> Result<Vec<Bookmark>, BookmarkError> {
+        if let Some(folder) = folder {
+            self.require_folder(folder)?;
+        }
+
+        let sql = match (folder, recursive) {
+            (None, true) => return self.list(None),
+            (_, false) => format!(
+                "{} WHERE m.folder_id IS ?1 ORDER BY {}",
+                SELECT_BOOKMARKS, FOLDER_ORDER
+            ),
+            (Some(_), true) => format!(
+                "{} WHERE m.folder_id IN ({}) ORDER BY b.id DESC",
+                SELECT_BOOKMARKS,
+                folder_subtree(1)
+            ),
+        };
+
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let bookmarks = stmt
+            .query_map([folder], Self::row_to_bookmark)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(bookmarks)
+    }
+
+    /// Sets the keyword that opens the bookmark from the launcher, or clears
+    /// it with `None`. Keywords are unique and stored lowercase.
+    pub fn set_keyword(&self, id: i32, keyword: Option<&str>) -> Result<(), BookmarkError> {
+        let keyword = keyword.map(keyword::normalize).transpose()?;
+        if let Some(keyword) = &keyword {
+            if self
+                .find_by_keyword(keyword)?
+                .is_some_and(|other| other != id)
+            {
+                return Err(BookmarkError::DuplicateKeyword(keyword.clone()));
+            }
+        }
+
+        let updated = self.conn.execute(
+            "UPDATE bookmark_meta SET keyword = ?1 WHERE bookmark_id = ?2",
+            (keyword, id),
+        )?;
+        if updated == 0 {
+            return Err(BookmarkError::NotFound(id));
+        }
+        Ok(())
+    }
+
+    fn find_by_keyword(&self, keyword: &str) -> Result<Option<i32>, BookmarkError> {
+        let id = self
+            .conn
+            .prepare_cached("SELECT bookmark_id FROM bookmark_meta WHERE keyword = ?1")?
+            .query_row([keyword], |row| row.get(0))
+            .optional()?;
+        Ok(id)
+    }
+
+    /// Resolves launcher input such as `drs serde` whose first word is a
+    /// bookmark keyword. The rest of the input replaces the placeholders in
+    /// the bookmark URL: `%s` (percent-encoded), `%S` (verbatim) and `%1` to
+    /// `%9` (single words). Returns `None` if the first word is no keyword.
+    pub fn resolve_keyword(&self, input: &str) -> Result<Option<KeywordMatch>, BookmarkError> {
+        let Some((keyword, argument)) = keyword::split(input) else {
+            return Ok(None);
+        };
+
+        let bookmark = self
+            .conn
+            .prepare_cached(&format!("{} WHERE m.keyword = ?1", SELECT_BOOKMARKS))?
+            .query_row([keyword.to_lowercase()], Self::row_to_bookmark)
+            .optional()?;
+
+        Ok(bookmark.map(|bookmark| KeywordMatch {
+            url: keyword::expand(&bookmark.url, argument),
+            bookmark,
+        }))
+    }
+
+    /// Opens the URL [`Self::resolve_keyword`] resolves `input` to and
+    /// records a visit of the keyword bookmark.
+    pub fn open_keyword(&self, input: &str) -> Result<Option<KeywordMatch>, BookmarkError> {
+        let Some(resolved) = self.resolve_keyword(input)? else {
+            return Ok(None);
+        };
+
+        open::that(&resolved.url).map_err(|e| BookmarkError::OpenUrl(e.to_string()))?;
+        self.record_visit(resolved.bookmark.id)?;
+        Ok(Some(resolved))
+    }
+
+    /// Moves a bookmark to the trash, from where [`Self::restore`] or
+    /// [`Self::undo`] bring it back until it is purged.
+    pub fn delete(&self, id: i32) -> Result<(), BookmarkError> {
+        let Some(bookmark) = self.get(id)? else {
+            return Ok(());
+        };
+
+        let tx = self.transaction()?;
+        self.conn
+            .prepare_cached(
+                "INSERT INTO bookmark_trash
+                    (bookmark_id, URL, metadata, tags, desc, flags, added_at, keyword, folder_id,
+                     visits, deleted_at)
+                 SELECT b.id, b.URL, b.metadata, b.tags, b.desc, b.flags, m.added_at, m.keyword,
+                    m.folder_id,
+                    (SELECT json_group_array(visited_at) FROM bookmark_visits
+                     WHERE bookmark_id = b.id),
+                    ?2
+                 FROM bookmarks b LEFT JOIN bookmark_meta m ON m.bookmark_id = b.id
+                 WHERE b.id = ?1",
+            )?
+            .execute((id, unix_now()))?;
+
+        let trash_id = self.conn.last_insert_rowid();
+        let target = if bookmark.title.is_empty() {
+            &bookmark.url
+        } else {
+            &bookmark.title
+        };
+        self.journal(UndoAction::Delete, target, Some(trash_id), &[])?;
+        self.remove(id)?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Deletes a bookmark for good, bypassing the trash.
+    fn remove(&self, id: i32) -> Result<(), BookmarkError> {
+        self.conn
+            .execute("DELETE FROM bookmarks WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Bookmarks in the trash, most recently deleted first.
+    pub fn list_trash(&self) -> Result<Vec<TrashedBookmark>, BookmarkError> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "{} ORDER BY deleted_at DESC, id DESC",
+            SELECT_TRASH
+        ))?;
+
+        let trash = stmt
+            .query_map([], |row| {
+                Ok(TrashedBookmark {
+                    bookmark: Self::row_to_bookmark(row)?,
+                    deleted_at: row.get(9)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(trash)
+    }
+
+    /// Restores the most recently trashed bookmark that had id `id`, with its
+    /// tags, flags, creation date, keyword and visits, at the end of its old
+    /// folder if that still exists. Fails with
+    /// [`BookmarkError::Duplicate`] if its URL has been bookmarked again.
+    pub fn restore(&self, id: i32) -> Result<Bookmark, BookmarkError> {
+        let trash_id: i64 = self
+            .conn
+            .query_row(
+                "SELECT id FROM bookmark_trash WHERE bookmark_id = ?1
+                 ORDER BY deleted_at DESC, id DESC LIMIT 1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or(BookmarkError::NotFound(id))?;
+
+        let tx = self.transaction()?;
+        let bookmark = self.restore_from_trash(trash_id)?;
+        tx.commit()?;
+        Ok(bookmark)
+    }
+
+    /// Moves trash entry `trash_id` back into `bookmarks`. The bookmark keeps
+    /// its old id unless another bookmark took it meanwhile, and its keyword
+    /// unless another bookmark uses it now. Must run inside a transaction.
+    fn restore_from_trash(&self, trash_id: i64) -> Result<Bookmark, BookmarkError> {
+        let (old_id, url, added_at, folder): (i32, String, Option<i64>, Option<i32>) =
+            self.conn.query_row(
+                "SELECT bookmark_id, URL, added_at, folder_id FROM bookmark_trash WHERE id = ?1",
+                [trash_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )?;
+
+        if let Some(existing) = self.find_by_url(&url, None)? {
+            return Err(BookmarkError::Duplicate(existing));
+        }
+
+        let id_taken = self.get(old_id)?.is_some();
+        self.conn.execute(
+            "INSERT INTO bookmarks (id, URL, metadata, tags, desc, flags)
+             SELECT CASE WHEN ?2 THEN NULL ELSE bookmark_id END, URL, metadata, tags, desc, flags
+             FROM bookmark_trash WHERE id = ?1",
+            (trash_id, id_taken),
+        )?;
+        let id = self.conn.last_insert_rowid() as i32;
+
+        self.conn.execute(
+            "UPDATE bookmark_meta SET url_key = ?1, added_at = IFNULL(?2, added_at),
+                keyword = (SELECT t.keyword FROM bookmark_trash t WHERE t.id = ?4
+                           AND NOT EXISTS (SELECT 1 FROM bookmark_meta WHERE keyword = t.keyword))
+             WHERE bookmark_id = ?3",
+            (canonical::canonicalize(&url), added_at, id, trash_id),
+        )?;
+        self.conn.execute(
+            "INSERT INTO bookmark_visits (bookmark_id, visited_at)
+             SELECT ?1, value FROM json_each((SELECT visits FROM bookmark_trash WHERE id = ?2))",
+            (id, trash_id),
+        )?;
+        self.conn
+            .execute("DELETE FROM bookmark_trash WHERE id = ?1", [trash_id])?;
+
+        let folder = folder.filter(|&f| matches!(self.get_folder(f), Ok(Some(_))));
+        self.place_bookmark(id, folder, None)?;
+
+        self.get(id)?.ok_or(BookmarkError::NotFound(id))
+    }
+
+    /// Permanently deletes bookmarks that have been in the trash for at least
+    /// `age`, returning how many were purged. `Duration::ZERO` empties it.
+    pub fn purge_older_than(&self, age: Duration) -> Result<usize, BookmarkError> {
+        let cutoff = unix_now() - age.as_secs() as i64;
+        let purged = self.conn.execute(
+            "DELETE FROM bookmark_trash WHERE deleted_at <= ?1",
+            [cutoff],
+        )?;
+        Ok(purged)
+    }
+
+    /// Records a change in the undo journal. `tags` holds the `tags` column
+    /// of every affected bookmark as it was before and after the change.
+    fn journal(
+        &self,
+        action: UndoAction,
+        target: &str,
+        trash_id: Option<i64>,
+        tags: &[(i32, String, String)],
+    ) -> Result<(), BookmarkError> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO undo_journal (action, target, trash_id, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?
+            .execute((action.as_str(), target, trash_id, unix_now()))?;
+        let entry = self.conn.last_insert_rowid();
+
+        // A bookmark touched twice by one change keeps its original tags
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO undo_journal_tags (entry_id, bookmark_id, tags, new_tags)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (entry_id, bookmark_id) DO UPDATE SET new_tags = excluded.new_tags",
+        )?;
+        for (id, old_tags, new_tags) in tags {
+            stmt.execute((entry, id, old_tags, new_tags))?;
+        }
+
+        self.conn.execute(
+            "DELETE FROM undo_journal WHERE id <= ?1",
+            [entry - UNDO_JOURNAL_SIZE],
+        )?;
+        Ok(())
+    }
+
+    /// Changes that can be undone, most recent first.
+    pub fn undo_history(&self) -> Result<Vec<UndoEntry>, BookmarkError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, action, target, created_at FROM undo_journal ORDER BY id DESC",
+        )?;
+
+        let entries = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?
+            .filter_map(|r| r.ok())
+            .filter_map(|(id, action, target, created_at)| {
+                Some(UndoEntry {
+                    id,
+                    action: UndoAction::parse(&action)?,
+                    target,
+                    created_at,
+                })
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Reverts the most recent change in the undo journal: a deleted bookmark
+    /// is restored from the trash, and after a tag rename, merge or delete the
+    /// affected bookmarks get back the tags that change removed and lose the
+    /// ones it added. A deletion whose URL has been bookmarked again since
+    /// can no longer be undone: its entry is dropped, leaving the bookmark in
+    /// the trash, and the change before it is undone instead. Returns the
+    /// entry undone, or `None` if there is nothing to undo.
+    pub fn undo(&self) -> Result<Option<UndoEntry>, BookmarkError> {
+        let tx = self.transaction()?;
+
+        for entry in self.undo_history()? {
+            let restored = if entry.action == UndoAction::Delete {
+                let trash_id: Option<i64> = self.conn.query_row(
+                    "SELECT trash_id FROM undo_journal WHERE id = ?1",
+                    [entry.id],
+                    |row| row.get(0),
+                )?;
+                trash_id.map(|trash_id| self.restore_from_trash(trash_id))
+            } else {
+                self.undo_tags(entry.id)?;
+                None
+            };
+            self.conn
+                .execute("DELETE FROM undo_journal WHERE id = ?1", [entry.id])?;
+
+            match restored {
+                Some(Err(BookmarkError::Duplicate(_))) => continue,
+                Some(Err(e)) => return Err(e),
+                _ => {
+                    tx.commit()?;
+                    return Ok(Some(entry));
+                }
+            }
+        }
+
+        tx.commit()?;
+        Ok(None)
+    }
+
+    /// Reverts the tag change recorded in journal entry `entry_id`.
+    fn undo_tags(&self, entry_id: i64) -> Result<(), BookmarkError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT u.bookmark_id, u.tags, u.new_tags, b.tags FROM undo_journal_tags u
+             JOIN bookmarks b ON b.id = u.bookmark_id WHERE u.entry_id = ?1",
+        )?;
+        let changes: Vec<(i32, String, Option<String>, String)> = stmt
+            .query_map([entry_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        for (id, before, after, current) in changes {
+            // Entries journaled without the tags after the change
+            let tags = match after {
+                Some(after) => format_tags(&revert_tags(
+                    &parse_tags(&current),
+                    &parse_tags(&before),
+                    &parse_tags(&after),
+                )),
+                None => before,
+            };
+            self.conn
+                .execute("UPDATE bookmarks SET tags = ?1 WHERE id = ?2", (tags, id))?;
+        }
+        Ok(())
+    }
+
+    pub fn list_tags(&self) -> Result<Vec<Tag>, BookmarkError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT t.name, COUNT(*) AS count
+             FROM tags t JOIN bookmark_tags bt ON bt.tag_id = t.id
+             GROUP BY t.id ORDER BY count DESC, t.name",
+        )?;
+
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(Tag {
+                    name: row.get(0)?,
+                    count: row.get(1)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(tags)
+    }
+
+    /// Rewrites the tags of every bookmark tagged `tag` (and, with
+    /// `include_children`, any tag below it) through `map`, which returns the
+    /// replacement for a tag or `None` to drop it. Writing the `tags` column
+    /// keeps buku in sync; triggers update the normalized tables. Tags match
+    /// case-insensitively, as in search filters. Returns the `tags` column of
+    /// every bookmark changed, before and after.
+    fn retag(
+        &self,
+        tag: &str,
+        include_children: bool,
+        map: impl Fn(&str) -> Option<String>,
+    ) -> Result<Vec<(i32, String, String)>, BookmarkError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT b.id, b.tags FROM bookmarks b WHERE EXISTS (
+                SELECT 1 FROM bookmark_tags bt JOIN tags t ON t.id = bt.tag_id
+                WHERE bt.bookmark_id = b.id
+                AND (t.name = ?1 COLLATE NOCASE
+                     OR (?2 AND substr(t.name, 1, length(?1) + 1) COLLATE NOCASE = ?1 || '/'))
+            )",
+        )?;
+
+        let affected: Vec<(i32, String)> = stmt
+            .query_map((tag, include_children), |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let mut changed = Vec::with_capacity(affected.len());
+        for (id, old_tags) in affected {
+            let tags: Vec<String> = parse_tags(&old_tags)
+                .iter()
+                .filter_map(|t| map(t))
+                .collect();
+            self.update(id, None, None, Some(&tags), None)?;
+            changed.push((id, old_tags, format_tags(&tags)));
+        }
+
+        Ok(changed)
+    }
+
+    /// Renames a tag together with its children: renaming `work` to `job`
+    /// turns `work/infra` into `job/infra`. Renaming onto an existing tag
+    /// merges the two.
+    pub fn rename_tag(&self, old_tag: &str, new_tag: &str) -> Result<(), BookmarkError> {
+        let tx = self.transaction()?;
+        let changed = self.retag(old_tag, true, |t| {
+            Some(rename_in_hierarchy(t, old_tag, new_tag))
+        })?;
+        if !changed.is_empty() {
+            self.journal(UndoAction::RenameTag, old_tag, None, &changed)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Merges every tag in `sources` (with their children) into `target`.
+    pub fn merge_tags(&self, sources: &[String], target: &str) -> Result<(), BookmarkError> {
+        let tx = self.transaction()?;
+        let mut changed = Vec::new();
+        for source in sources.iter().filter(|s| s.as_str() != target) {
+            changed.extend(self.retag(source, true, |t| {
+                Some(rename_in_hierarchy(t, source, target))
+            })?);
+        }
+        if !changed.is_empty() {
+            self.journal(UndoAction::MergeTags, &sources.join(", "), None, &changed)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Removes a tag from every bookmark. Child tags are kept.
+    pub fn delete_tag(&self, tag: &str) -> Result<(), BookmarkError> {
+        let tx = self.transaction()?;
+        let changed = self.retag(tag, false, |t| {
+            (!t.eq_ignore_ascii_case(tag)).then(|| t.to_string())
+        })?;
+        if !changed.is_empty() {
+            self.journal(UndoAction::DeleteTag, tag, None, &changed)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn row_to_link_status(row: &Row) -> rusqlite::Result<LinkStatus> {
+        Ok(LinkStatus {
+            bookmark_id: row.get(0)?,
+            status: row.get(1)?,
+            redirect_url: row.get(2)?,
+            error: row.get(3)?,
+            checked_at: row.get(4)?,
+        })
+    }
+
+    /// Web bookmarks due for a link check, least recently checked first.
+    /// With `max_age`, links checked more recently than that are skipped.
+    pub fn links_to_check(
+        &self,
+        max_age: Option<Duration>,
+    ) -> Result<Vec<(i32, String)>, BookmarkError> {
+        let checked_before = match max_age {
+            Some(age) => unix_now() - age.as_secs() as i64,
+            None => i64::MAX,
+        };
+
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT b.id, b.URL FROM bookmarks b
+             LEFT JOIN bookmark_links l ON l.bookmark_id = b.id
+             WHERE (b.URL LIKE 'http://%' OR b.URL LIKE 'https://%')
+               AND (l.checked_at IS NULL OR l.checked_at <= ?1)
+             ORDER BY l.checked_at IS NOT NULL, l.checked_at, b.id",
+        )?;
+        let targets = stmt
+            .query_map([checked_before], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(targets)
+    }
+
+    /// Stores the result of a link check, replacing the previous one. Results
+    /// for bookmarks deleted while the check ran are dropped.
+    pub fn record_link_status(&self, status: &LinkStatus) -> Result<(), BookmarkError> {
+        self.conn
+            .prepare_cached(
+                "INSERT OR REPLACE INTO bookmark_links
+                    (bookmark_id, status, redirect_url, error, checked_at)
+                 SELECT id, ?2, ?3, ?4, ?5 FROM bookmarks WHERE id = ?1",
+            )?
+            .execute((
+                status.bookmark_id,
+                status.status,
+                &status.redirect_url,
+                &status.error,
+                status.checked_at,
+            ))?;
+        Ok(())
+    }
+
+    /// Result of the last link check, `None` if never checked since the URL
+    /// was last changed.
+    pub fn link_status(&self, id: i32) -> Result<Option<LinkStatus>, BookmarkError> {
+        let status = self
+            .conn
+            .prepare_cached(&format!("{} WHERE bookmark_id = ?1", SELECT_LINKS))?
+            .query_row([id], Self::row_to_link_status)
+            .optional()?;
+        Ok(status)
+    }
+
+    /// Last check results that are broken or redirect elsewhere, broken ones
+    /// first.
+    pub fn link_problems(&self) -> Result<Vec<LinkStatus>, BookmarkError> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "{} WHERE status IS NULL OR status >= 400 OR redirect_url IS NOT NULL
+             ORDER BY status IS NOT NULL AND status < 400, bookmark_id",
+            SELECT_LINKS
+        ))?;
+        let problems = stmt
+            .query_map([], Self::row_to_link_status)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(problems)
+    }
+
+    /// Checks links due per [`Self::links_to_check`], recording each result
+    /// as it arrives and reporting progress. Returns the number checked.
+    pub fn check_links(
+        &self,
+        checker: &LinkChecker,
+        max_age: Option<Duration>,
+        mut progress: impl FnMut(&LinkCheckProgress),
+    ) -> Result<usize, BookmarkError> {
+        let targets = self.links_to_check(max_age)?;
+        let total = targets.len();
+        let mut checked = 0;
+        let mut result = Ok(());
+
+        checker.check(targets, |status| {
+            checked += 1;
+            if result.is_ok() {
+                result = self.record_link_status(&status);
+            }
+            progress(&LinkCheckProgress {
+                checked,
+                total,
+                status,
+            });
+        });
+
+        result.map(|()| checked)
+    }
+
+    /// Replaces the bookmark's URL with the redirect target found by the
+    /// last link check.
+    pub fn fix_redirect(&self, id: i32) -> Result<Bookmark, BookmarkError> {
+        let status = self.link_status(id)?;
+        let Some((code, target)) = status.and_then(|s| Some((s.status, s.redirect_url?))) else {
+            return Err(BookmarkError::NoRedirect(id));
+        };
+
+        let tx = self.transaction()?;
+        self.update(id, Some(&target), None, None, None)?;
+        // The check that found the redirect also reached the target
+        self.record_link_status(&LinkStatus {
+            bookmark_id: id,
+            status: code,
+            redirect_url: None,
+            error: None,
+            checked_at: unix_now(),
+        })?;
+        tx.commit()?;
+
+        self.get(id)?.ok_or(BookmarkError::NotFound(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{tempdir, TempDir};
+
+    // The TempDir must outlive the store, otherwise the database is removed
+    fn create_test_store() -> (BookmarkStore, TempDir) {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        (BookmarkStore::with_path(db_path).unwrap(), dir)
+    }
+
+    #[test]
+    fn test_add_and_get_bookmark() {
+        let (store, _dir) = create_test_store();
+
+        let input = BookmarkInput::new("https://example.com")
+            .with_title("Example")
+            .with_tags(vec!["test".to_string(), "example".to_string()])
+            .with_description("A test bookmark");
+
+        let id = store.add(input).unwrap();
+        let bookmark = store.get(id).unwrap().unwrap();
+
+        assert_eq!(bookmark.url, "https://example.com");
+        assert_eq!(bookmark.title, "Example");
+        assert_eq!(bookmark.tags, vec!["test", "example"]);
+        assert_eq!(bookmark.description, "A test bookmark");
+    }
+
+    #[test]
+    fn test_list_bookmarks() {
+        let (store, _dir) = create_test_store();
+
+        store
+            .add(BookmarkInput::new("https://example1.com"))
+            .unwrap();
+        store
+            .add(BookmarkInput::new("https://example2.com"))
+            .unwrap();
+
+        let bookmarks = store.list(None).unwrap();
+        assert_eq!(bookmarks.len(), 2);
+    }
+
+    #[test]
+    fn test_migrations_set_schema_version() {
+        let (store, _dir) = create_test_store();
+        assert_eq!(
+            store.schema_version().unwrap(),
+            BookmarkStore::SCHEMA_VERSION
+        );
+    }
+
+    #[test]
+    fn test_migrations_keep_existing_data() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("legacy.db");
+
+        // Pre-migration database: table exists, user_version is still 0
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.execute(
+            "INSERT INTO bookmarks (URL, metadata) VALUES ('https://example.com', 'Example')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        let store = BookmarkStore::with_path(db_path).unwrap();
+        assert_eq!(
+            store.schema_version().unwrap(),
+            BookmarkStore::SCHEMA_VERSION
+        );
+        assert_eq!(store.list(None).unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypts_plaintext_database_on_open() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("bookmarks.db");
+
+        let store = BookmarkStore::with_path(db_path.clone()).unwrap();
+        let id = store
+            .add(BookmarkInput::new("https://internal.example/?token=secret").with_title("Dash"))
+            .unwrap();
+        store.record_visit(id).unwrap();
+        drop(store);
+
+        let store = BookmarkStore::with_path_encrypted(db_path.clone(), "hunter2").unwrap();
+        assert_eq!(store.get(id).unwrap().unwrap().title, "Dash");
+        assert_eq!(store.visit_count(id).unwrap(), 1);
+        assert_eq!(store.search("dash", None, false).unwrap().len(), 1);
+        assert_eq!(
+            store.schema_version().unwrap(),
+            BookmarkStore::SCHEMA_VERSION
+        );
+        drop(store);
+
+        let raw = std::fs::read(&db_path).unwrap();
+        assert!(!raw.starts_with(b"SQLite format 3"));
+        assert!(!raw.windows(6).any(|w| w == b"secret"));
+
+        assert!(matches!(
+            BookmarkStore::with_path_encrypted(db_path.clone(), "wrong"),
+            Err(BookmarkError::WrongPassphrase)
+        ));
+        assert!(BookmarkStore::with_path(db_path.clone()).is_err());
+
+        // Reopening with the right passphrase leaves the data alone
+        let store = BookmarkStore::with_path_encrypted(db_path, "hunter2").unwrap();
+        assert_eq!(store.list(None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_search_ranks_title_over_url_and_description() {
+        let (store, _dir) = create_test_store();
+
+        let desc_id = store
+            .add(BookmarkInput::new("https://a.example").with_description("rust tips"))
+            .unwrap();
+        let url_id = store
+            .add(BookmarkInput::new("https://rust-lang.org"))
+            .unwrap();
+        let title_id = store
+            .add(BookmarkInput::new("https://b.example").with_title("Rust book"))
+            .unwrap();
+        store
+            .add(BookmarkInput::new("https://unrelated.example"))
+            .unwrap();
+
+        let ids: Vec<i32> = store
+            .search("rust", None, false)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.id)
+            .collect();
+        assert_eq!(ids, vec![title_id, url_id, desc_id]);
+    }
+
+    #[test]
+    fn test_search_prefix_and_tag_filter() {
+        let (store, _dir) = create_test_store();
+
+        store
+            .add(
+                BookmarkInput::new("https://docs.rs")
+                    .with_title("Documentation")
+                    .with_tags(vec!["rust".to_string()]),
+            )
+            .unwrap();
+        store
+            .add(
+                BookmarkInput::new("https://devdocs.io")
+                    .with_title("Documents")
+                    .with_tags(vec!["web".to_string()]),
+            )
+            .unwrap();
+
+        assert_eq!(store.search("docum", None, false).unwrap().len(), 2);
+
+        let tagged = store
+            .search("docum", Some(&["rust".to_string()]), false)
+            .unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].url, "https://docs.rs");
+    }
+
+    #[test]
+    fn test_search_without_terms_matches_nothing() {
+        let (store, _dir) = create_test_store();
+        store
+            .add(BookmarkInput::new("https://docs.rs").with_title("Docs"))
+            .unwrap();
+
+        assert!(store.search("--", None, false).unwrap().is_empty());
+        assert!(store.search(" \" : ", None, false).unwrap().is_empty());
+        let page = store
+            .search_page("--", None, false, None, &PageRequest::default())
+            .unwrap();
+        assert_eq!(page.total, 0);
+        assert_eq!(store.search("  ", None, false).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_search_index_follows_updates_and_deletes() {
+        let (store, _dir) = create_test_store();
+
+        let id = store
+            .add(BookmarkInput::new("https://example.com").with_title("Old"))
+            .unwrap();
+        store.update(id, None, Some("Renamed"), None, None).unwrap();
+
+        assert!(store.search("old", None, false).unwrap().is_empty());
+        assert_eq!(store.search("renamed", None, false).unwrap().len(), 1);
+
+        store.delete(id).unwrap();
+        assert!(store.search("renamed", None, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_ignores_fts_syntax() {
+        let (store, _dir) = create_test_store();
+
+        store
+            .add(BookmarkInput::new("https://example.com").with_title("C++ \"notes\""))
+            .unwrap();
+
+        assert_eq!(store.search("\"notes", None, false).unwrap().len(), 1);
+        assert!(store.search("NOT -x:", None, false).is_ok());
+    }
+
+    #[test]
+    fn test_frecency_orders_by_visits() {
+        let (store, _dir) = create_test_store();
+
+        let rarely = store
+            .add(BookmarkInput::new("https://rarely.example").with_title("Docs rarely"))
+            .unwrap();
+        let often = store
+            .add(BookmarkInput::new("https://often.example").with_title("Docs often"))
+            .unwrap();
+        let never = store
+            .add(BookmarkInput::new("https://never.example").with_title("Docs never"))
+            .unwrap();
+
+        store.record_visit(rarely).unwrap();
+        for _ in 0..3 {
+            store.record_visit(often).unwrap();
+        }
+
+        assert_eq!(store.visit_count(often).unwrap(), 3);
+        assert!(store.last_opened(often).unwrap().is_some());
+        assert!(store.last_opened(never).unwrap().is_none());
+
+        let ids: Vec<i32> = store
+            .list_by_frecency(None)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.id)
+            .collect();
+        assert_eq!(ids, vec![often, rarely, never]);
+
+        let ids: Vec<i32> = store
+            .search_ordered("docs", None, false, BookmarkOrder::Frecency)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.id)
+            .collect();
+        assert_eq!(ids, vec![often, rarely, never]);
+    }
+
+    #[test]
+    fn test_store_methods_nest_in_a_transaction() {
+        let (store, _dir) = create_test_store();
+        let a = store.add(BookmarkInput::new("https://a.example")).unwrap();
+        let b = store.add(BookmarkInput::new("https://b.example")).unwrap();
+
+        let tx = store.transaction().unwrap();
+        store.delete(a).unwrap();
+        store.delete(b).unwrap();
+        drop(tx);
+        assert_eq!(store.list(None).unwrap().len(), 2);
+
+        let tx = store.transaction().unwrap();
+        store.delete(a).unwrap();
+        tx.commit().unwrap();
+        assert!(store.get(a).unwrap().is_none());
+        assert!(store.get(b).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_frecency_samples_latest_visits() {
+        let (store, _dir) = create_test_store();
+        let id = store
+            .add(BookmarkInput::new("https://example.com"))
+            .unwrap();
+
+        // Ten visits today sampled at weight 100, five from last year only
+        // counted
+        let now = unix_now();
+        let year_ago = now - 365 * 24 * 60 * 60;
+        for visited_at in (now - 10..now).chain(year_ago - 5..year_ago) {
+            store
+                .conn
+                .execute(
+                    "INSERT INTO bookmark_visits (bookmark_id, visited_at) VALUES (?1, ?2)",
+                    (id, visited_at),
+                )
+                .unwrap();
+        }
+
+        let scores = store.frecency_scores().unwrap();
+        assert_eq!(scores.get(&id), Some(&(15.0 * 100.0)));
+    }
+
+    /// Follows `next_cursor` to the end, returning each page's ids.
+    fn pages(store: &BookmarkStore, query: &str, mut page: PageRequest) -> Vec<Vec<i32>> {
+        let mut pages = Vec::new();
+        loop {
+            let result = store.search_page(query, None, false, None, &page).unwrap();
+            pages.push(result.bookmarks.iter().map(|b| b.id).collect());
+            match result.next_cursor {
+                Some(cursor) => page.cursor = Some(cursor),
+                None => return pages,
+            }
+        }
+    }
+
+    #[test]
+    fn test_pages_by_sort_key() {
+        let (store, _dir) = create_test_store();
+        let add = |url: &str, title: &str, added_at: i64| {
+            store
+                .add(
+                    BookmarkInput::new(url)
+                        .with_title(title)
+                        .with_added_at(added_at),
+                )
+                .unwrap()
+        };
+        let c = add("https://c.example", "charlie", 300);
+        let a = add("https://a.example", "Alpha", 100);
+        let d = add("https://d.example", "delta", 200);
+        let b = add("https://b.example", "bravo", 400);
+
+        let by = |sort| PageRequest::new(sort).with_limit(3);
+        assert_eq!(
+            pages(&store, "", by(BookmarkSort::Title)),
+            vec![vec![a, b, c], vec![d]]
+        );
+        assert_eq!(
+            pages(&store, "", by(BookmarkSort::Url).with_reverse(true)),
+            vec![vec![d, c, b], vec![a]]
+        );
+        assert_eq!(
+            pages(&store, "", by(BookmarkSort::Created)),
+            vec![vec![b, c, d], vec![a]]
+        );
+        assert_eq!(
+            pages(&store, "", by(BookmarkSort::Relevance)),
+            vec![vec![b, d, a], vec![c]]
+        );
+
+        let now = unix_now();
+        for (id, ago) in [(d, 10), (a, 20)] {
+            store
+                .conn
+                .execute(
+                    "INSERT INTO bookmark_visits (bookmark_id, visited_at) VALUES (?1, ?2)",
+                    (id, now - ago),
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            pages(&store, "", by(BookmarkSort::LastOpened)),
+            vec![vec![d, a, b], vec![c]]
+        );
+
+        // Rows added before the cursor do not shift the next page
+        let first = store
+            .list_page(&PageRequest::new(BookmarkSort::Title).with_limit(2))
+            .unwrap();
+        assert_eq!(first.total, 4);
+        add("https://aa.example", "aardvark", 500);
+        let second = store
+            .list_page(
+                &PageRequest::new(BookmarkSort::Title)
+                    .with_limit(2)
+                    .with_cursor(first.next_cursor.unwrap()),
+            )
+            .unwrap();
+        assert_eq!(
+            urls(second.bookmarks),
+            vec!["https://c.example", "https://d.example"]
+        );
+        assert_eq!(second.total, 5);
+        assert_eq!(second.next_cursor, None);
+
+        // Without a limit everything comes back in one page
+        let all = store.list_page(&PageRequest::default()).unwrap();
+        assert_eq!((all.bookmarks.len(), all.next_cursor), (5, None));
+    }
+
+    #[test]
+    fn test_search_pages_by_relevance_and_frecency() {
+        let (store, _dir) = create_test_store();
+        let url_match = store
+            .add(BookmarkInput::new("https://rust.example").with_title("Home"))
+            .unwrap();
+        let title_match = store
+            .add(BookmarkInput::new("https://a.example").with_title("Rust book"))
+            .unwrap();
+        let other = store
+            .add(BookmarkInput::new("https://b.example").with_title("Go"))
+            .unwrap();
+        store.record_visit(url_match).unwrap();
+
+        let page = PageRequest::new(BookmarkSort::Relevance).with_limit(1);
+        assert_eq!(
+            pages(&store, "rust", page.clone()),
+            vec![vec![title_match], vec![url_match]]
+        );
+        let first = store.search_page("rust", None, false, None, &page).unwrap();
+        assert_eq!(first.total, 2);
+
+        assert_eq!(
+            pages(
+                &store,
+                "",
+                PageRequest::new(BookmarkSort::Frecency).with_limit(2)
+            ),
+            vec![vec![url_match, other], vec![title_match]]
+        );
+
+        // Cursors only continue the order they came from
+        let cursor = first.next_cursor.unwrap();
+        let title_page = PageRequest::new(BookmarkSort::Title).with_cursor(cursor.clone());
+        assert!(matches!(
+            store.search_page("rust", None, false, None, &title_page),
+            Err(BookmarkError::InvalidCursor(_))
+        ));
+        let reversed = page.with_reverse(true).with_cursor(cursor);
+        assert!(matches!(
+            store.search_page("rust", None, false, None, &reversed),
+            Err(BookmarkError::InvalidCursor(_))
+        ));
+        assert!(matches!(
+            store.list_page(&PageRequest::default().with_cursor("garbage")),
+            Err(BookmarkError::InvalidCursor(_))
+        ));
+        assert!(matches!(
+            store.search_page("", None, false, Some(99), &PageRequest::default()),
+            Err(BookmarkError::FolderNotFound(99))
+        ));
+    }
+
+    #[test]
+    fn test_zero_limit_returns_only_total() {
+        let (store, _dir) = create_test_store();
+        store.add(BookmarkInput::new("https://a.example")).unwrap();
+        store.add(BookmarkInput::new("https://b.example")).unwrap();
+
+        for sort in [
+            BookmarkSort::Relevance,
+            BookmarkSort::Frecency,
+            BookmarkSort::Title,
+        ] {
+            let page = store
+                .list_page(&PageRequest::new(sort).with_limit(0))
+                .unwrap();
+            assert!(page.bookmarks.is_empty());
+            assert_eq!(page.total, 2);
+            assert_eq!(page.next_cursor, None);
+        }
+    }
+
+    #[test]
+    fn test_delete_removes_visits() {
+        let (store, _dir) = create_test_store();
+
+        let id = store
+            .add(BookmarkInput::new("https://example.com"))
+            .unwrap();
+        store.record_visit(id).unwrap();
+        store.delete(id).unwrap();
+
+        assert_eq!(store.visit_count(id).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_import_skips_existing_urls() {
+        let (store, _dir) = create_test_store();
+
+        store
+            .add(BookmarkInput::new("https://example.com"))
+            .unwrap();
+
+        let report = store
+            .import(vec![
+                BookmarkInput::new("https://example.com"),
+                BookmarkInput::new("https://docs.rs").with_tags(vec!["rust".to_string()]),
+                BookmarkInput::new("https://docs.rs"),
+                BookmarkInput::new(""),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            report,
+            ImportReport {
+                added: 1,
+                skipped: 2,
+                failed: 1,
+            }
+        );
+        assert_eq!(store.list(None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_added_at_defaults_to_now_and_can_be_set() {
+        let (store, _dir) = create_test_store();
+
+        let now = store
+            .add(BookmarkInput::new("https://now.example"))
+            .unwrap();
+        let dated = store
+            .add(BookmarkInput::new("https://dated.example").with_added_at(1_600_000_000))
+            .unwrap();
+
+        assert!(store.get(now).unwrap().unwrap().added_at.unwrap() > 1_600_000_000);
+        assert_eq!(
+            store.get(dated).unwrap().unwrap().added_at,
+            Some(1_600_000_000)
+        );
+    }
+
+    #[test]
+    fn test_netscape_export_then_import() {
+        let (store, dir) = create_test_store();
+
+        store
+            .add(
+                BookmarkInput::new("https://grafana.example")
+                    .with_title("Grafana")
+                    .with_tags(vec!["work/infra".to_string(), "ops".to_string()])
+                    .with_description("Dashboards")
+                    .with_added_at(1_700_000_000)
+                    .with_folder(vec!["Work".to_string(), "Infra".to_string()]),
+            )
+            .unwrap();
+        store
+            .add(BookmarkInput::new("https://docs.rs").with_title("Docs"))
+            .unwrap();
+
+        let html = dir.path().join("bookmarks.html");
+        assert_eq!(store.export_netscape(&html).unwrap(), 2);
+
+        let target = BookmarkStore::with_path(dir.path().join("target.db")).unwrap();
+        let report = target.import_netscape(&html).unwrap();
+        assert_eq!(report.added, 2);
+
+        let mut original = store.list(None).unwrap();
+        let mut imported = target.list(None).unwrap();
+        original.sort_by(|a, b| a.url.cmp(&b.url));
+        imported.sort_by(|a, b| a.url.cmp(&b.url));
+
+        for (a, b) in original.iter().zip(&imported) {
+            assert_eq!(a.url, b.url);
+            assert_eq!(a.title, b.title);
+            assert_eq!(a.tags, b.tags);
+            assert_eq!(a.description, b.description);
+            assert_eq!(a.added_at, b.added_at);
+        }
+
+        let folders: Vec<(Option<i32>, String)> = target
+            .list_folders()
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.parent_id, f.name))
+            .collect();
+        assert_eq!(folders.len(), 2);
+        assert_eq!(folders[0], (None, "Work".to_string()));
+        assert_eq!(folders[1].1, "Infra");
+        let grafana = target.search("grafana", None, false).unwrap().remove(0);
+        assert_eq!(
+            target
+                .get_folder(grafana.folder_id.unwrap())
+                .unwrap()
+                .unwrap()
+                .name,
+            "Infra"
+        );
+
+        // Importing the same file again only skips
+        assert_eq!(target.import_netscape(&html).unwrap().skipped, 2);
+    }
+
+    fn tagged(store: &BookmarkStore, url: &str, tags: &[&str]) -> i32 {
+        store
+            .add(BookmarkInput::new(url).with_tags(tags.iter().map(|t| t.to_string()).collect()))
+            .unwrap()
+    }
+
+    fn tag_counts(store: &BookmarkStore) -> Vec<(String, i32)> {
+        store
+            .list_tags()
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.name, t.count))
+            .collect()
+    }
+
+    #[test]
+    fn test_list_tags_counts() {
+        let (store, _dir) = create_test_store();
+
+        tagged(&store, "https://a.example", &["rust", "web"]);
+        let b = tagged(&store, "https://b.example", &["rust"]);
+        tagged(&store, "https://c.example", &[]);
+
+        assert_eq!(
+            tag_counts(&store),
+            vec![("rust".to_string(), 2), ("web".to_string(), 1)]
+        );
+
+        store.delete(b).unwrap();
+        store
+            .update(1, None, None, Some(&["go".to_string()]), None)
+            .unwrap();
+        assert_eq!(tag_counts(&store), vec![("go".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_parent_tag_filter_includes_children() {
+        let (store, _dir) = create_test_store();
+
+        tagged(&store, "https://k8s.example", &["work/infra/k8s"]);
+        tagged(&store, "https://wiki.example", &["work"]);
+        tagged(&store, "https://workshop.example", &["workshop"]);
+
+        let urls = |tag: &str| -> Vec<String> {
+            let mut urls: Vec<String> = store
+                .search("", Some(&[tag.to_string()]), false)
+                .unwrap()
+                .into_iter()
+                .map(|b| b.url)
+                .collect();
+            urls.sort();
+            urls
+        };
+
+        assert_eq!(
+            urls("work"),
+            vec!["https://k8s.example", "https://wiki.example"]
+        );
+        assert_eq!(urls("Work/Infra"), vec!["https://k8s.example"]);
+        assert_eq!(urls("workshop"), vec!["https://workshop.example"]);
+    }
+
+    #[test]
+    fn test_rename_tag_moves_children_and_merges() {
+        let (store, _dir) = create_test_store();
+
+        let a = tagged(&store, "https://a.example", &["work/infra", "job"]);
+        let b = tagged(&store, "https://b.example", &["work", "rust"]);
+        let c = tagged(&store, "https://c.example", &["workshop"]);
+
+        store.rename_tag("work", "job").unwrap();
+
+        assert_eq!(
+            store.get(a).unwrap().unwrap().tags,
+            vec!["job/infra", "job"]
+        );
+        assert_eq!(store.get(b).unwrap().unwrap().tags, vec!["job", "rust"]);
+        assert_eq!(store.get(c).unwrap().unwrap().tags, vec!["workshop"]);
+    }
+
+    #[test]
+    fn test_merge_and_delete_tags() {
+        let (store, _dir) = create_test_store();
+
+        let a = tagged(&store, "https://a.example", &["js", "javascript"]);
+        let b = tagged(&store, "https://b.example", &["ecmascript", "web"]);
+
+        store
+            .merge_tags(&["js".to_string(), "ecmascript".to_string()], "javascript")
+            .unwrap();
+        assert_eq!(store.get(a).unwrap().unwrap().tags, vec!["javascript"]);
+        assert_eq!(
+            store.get(b).unwrap().unwrap().tags,
+            vec!["javascript", "web"]
+        );
+
+        store.delete_tag("javascript").unwrap();
+        assert!(store.get(a).unwrap().unwrap().tags.is_empty());
+        assert_eq!(tag_counts(&store), vec![("web".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_tag_changes_ignore_case() {
+        let (store, _dir) = create_test_store();
+
+        let a = tagged(&store, "https://a.example", &["Work/Infra", "rust"]);
+        let b = tagged(&store, "https://b.example", &["WORK"]);
+
+        store.rename_tag("work", "job").unwrap();
+        assert_eq!(
+            store.get(a).unwrap().unwrap().tags,
+            vec!["job/Infra", "rust"]
+        );
+        assert_eq!(store.get(b).unwrap().unwrap().tags, vec!["job"]);
+
+        store.delete_tag("RUST").unwrap();
+        assert_eq!(store.get(a).unwrap().unwrap().tags, vec!["job/Infra"]);
+    }
+
+    #[test]
+    fn test_tags_drop_control_characters() {
+        let (store, _dir) = create_test_store();
+
+        let id = tagged(
+            &store,
+            "https://a.example",
+            &["two\twords", "bell\u{7}", "Rust", "rust"],
+        );
+        assert_eq!(
+            store.get(id).unwrap().unwrap().tags,
+            vec!["two words", "bell", "Rust"]
+        );
+        assert_eq!(
+            tag_counts(&store),
+            vec![
+                ("Rust".to_string(), 1),
+                ("bell".to_string(), 1),
+                ("two words".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_migration_indexes_existing_tags() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("legacy.db");
+
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.execute(
+            "INSERT INTO bookmarks (URL, tags) VALUES ('https://a.example', ',a,b\"q,')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        let store = BookmarkStore::with_path(db_path).unwrap();
+        assert_eq!(
+            tag_counts(&store),
+            vec![("a".to_string(), 1), ("b\"q".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_add_rejects_canonical_duplicates() {
+        let (store, _dir) = create_test_store();
+
+        let id = store
+            .add(BookmarkInput::new("https://example.com"))
+            .unwrap();
+
+        for url in [
+            "https://example.com/",
+            "HTTPS://EXAMPLE.com:443",
+            "https://example.com/?utm_source=newsletter",
+        ] {
+            assert!(matches!(
+                store.add(BookmarkInput::new(url)),
+                Err(BookmarkError::Duplicate(existing)) if existing == id
+            ));
+        }
+
+        let other = store
+            .add(BookmarkInput::new("https://example.com/docs"))
+            .unwrap();
+        assert!(matches!(
+            store.update(other, Some("https://example.com/"), None, None, None),
+            Err(BookmarkError::Duplicate(_))
+        ));
+        store
+            .update(
+                id,
+                Some("https://example.com/?utm_medium=x"),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_find_duplicates_and_merge() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("dupes.db");
+
+        // Duplicates can only come from before canonicalization or other tools
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.execute_batch(
+            "INSERT INTO bookmarks (URL, metadata, tags, desc) VALUES
+                ('https://example.com', '', ',a,', 'first'),
+                ('https://example.com/?utm_source=x', 'Example', ',b,a,', 'second'),
+                ('https://other.example', '', ',', '');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let store = BookmarkStore::with_path(db_path).unwrap();
+        store.record_visit(2).unwrap();
+
+        let groups = store.find_duplicates().unwrap();
+        assert_eq!(groups.len(), 1);
+        let ids: Vec<i32> = groups[0].iter().map(|b| b.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        let merged = store.merge(1, &[2]).unwrap();
+        assert_eq!(merged.title, "Example");
+        assert_eq!(merged.tags, vec!["a", "b"]);
+        assert_eq!(merged.description, "first\n\nsecond");
+        assert!(store.get(2).unwrap().is_none());
+        assert_eq!(store.visit_count(1).unwrap(), 1);
+        assert!(store.find_duplicates().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_store_is_send_and_uses_wal() {
+        fn assert_send<T: Send>() {}
+        assert_send::<BookmarkStore>();
+
+        let (store, _dir) = create_test_store();
+        let mode: String = store
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+    }
+
+    #[test]
+    fn test_delete_bookmark() {
+        let (store, _dir) = create_test_store();
+
+        let id = store
             .add(BookmarkInput::new("https://example.com"))
             .unwrap();
         store.delete(id).unwrap();
@@ -363,4 +3196,477 @@ mod tests {
         let bookmark = store.get(id).unwrap();
         assert!(bookmark.is_none());
     }
+
+    #[test]
+    fn test_delete_moves_to_trash_and_restores() {
+        let (store, _dir) = create_test_store();
+
+        let id = store
+            .add(
+                BookmarkInput::new("https://example.com")
+                    .with_title("Example")
+                    .with_tags(vec!["work/infra".to_string()])
+                    .with_added_at(1_600_000_000)
+                    .with_immutable_title(true),
+            )
+            .unwrap();
+        store.record_visit(id).unwrap();
+        store.delete(id).unwrap();
+
+        assert!(store.get(id).unwrap().is_none());
+        assert!(store.search("example", None, false).unwrap().is_empty());
+        let trash = store.list_trash().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].bookmark.id, id);
+        assert_eq!(trash[0].bookmark.title, "Example");
+
+        let restored = store.restore(id).unwrap();
+        assert_eq!(restored.id, id);
+        assert_eq!(restored.tags, vec!["work/infra"]);
+        assert_eq!(restored.added_at, Some(1_600_000_000));
+        assert!(restored.immutable_title);
+        assert_eq!(store.visit_count(id).unwrap(), 1);
+        assert_eq!(store.search("example", None, false).unwrap().len(), 1);
+        assert!(store.list_trash().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_restore_rejects_rebookmarked_url() {
+        let (store, _dir) = create_test_store();
+
+        let id = store
+            .add(BookmarkInput::new("https://example.com"))
+            .unwrap();
+        store.delete(id).unwrap();
+        let again = store
+            .add(BookmarkInput::new("https://example.com/"))
+            .unwrap();
+
+        assert!(matches!(
+            store.restore(id),
+            Err(BookmarkError::Duplicate(existing)) if existing == again
+        ));
+        assert!(matches!(
+            store.restore(999),
+            Err(BookmarkError::NotFound(999))
+        ));
+    }
+
+    #[test]
+    fn test_purge_older_than() {
+        let (store, _dir) = create_test_store();
+
+        let old = store
+            .add(BookmarkInput::new("https://old.example"))
+            .unwrap();
+        let new = store
+            .add(BookmarkInput::new("https://new.example"))
+            .unwrap();
+        store.delete(old).unwrap();
+        store.delete(new).unwrap();
+        store
+            .conn
+            .execute(
+                "UPDATE bookmark_trash SET deleted_at = deleted_at - 40 * 86400
+                 WHERE bookmark_id = ?1",
+                [old],
+            )
+            .unwrap();
+
+        let purged = store
+            .purge_older_than(Duration::from_secs(30 * 86400))
+            .unwrap();
+        assert_eq!(purged, 1);
+        let trash = store.list_trash().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].bookmark.id, new);
+        assert!(matches!(
+            store.restore(old),
+            Err(BookmarkError::NotFound(_))
+        ));
+
+        // Purged bookmarks can no longer be undone
+        assert_eq!(store.undo_history().unwrap().len(), 1);
+        assert_eq!(store.purge_older_than(Duration::ZERO).unwrap(), 1);
+        assert!(store.undo().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_undo_tag_changes_and_delete() {
+        let (store, _dir) = create_test_store();
+        let a = tagged(&store, "https://a.example", &["work/infra", "rust"]);
+        let b = tagged(&store, "https://b.example", &["work"]);
+
+        store.rename_tag("work", "job").unwrap();
+        store.delete_tag("rust").unwrap();
+        store.delete(b).unwrap();
+
+        let actions: Vec<UndoAction> = store
+            .undo_history()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.action)
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                UndoAction::Delete,
+                UndoAction::DeleteTag,
+                UndoAction::RenameTag
+            ]
+        );
+
+        let undone = store.undo().unwrap().unwrap();
+        assert_eq!(undone.action, UndoAction::Delete);
+        assert_eq!(store.get(b).unwrap().unwrap().tags, vec!["job"]);
+
+        store.undo().unwrap();
+        assert_eq!(
+            store.get(a).unwrap().unwrap().tags,
+            vec!["job/infra", "rust"]
+        );
+
+        store.undo().unwrap();
+        assert_eq!(
+            store.get(a).unwrap().unwrap().tags,
+            vec!["work/infra", "rust"]
+        );
+        assert_eq!(store.get(b).unwrap().unwrap().tags, vec!["work"]);
+        assert_eq!(
+            tag_counts(&store),
+            vec![
+                ("rust".to_string(), 1),
+                ("work".to_string(), 1),
+                ("work/infra".to_string(), 1)
+            ]
+        );
+
+        assert!(store.undo().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_undo_skips_deletion_of_rebookmarked_url() {
+        let (store, _dir) = create_test_store();
+        let a = tagged(&store, "https://a.example", &["rust"]);
+        store.rename_tag("rust", "lang").unwrap();
+        store.delete(a).unwrap();
+        let again = tagged(&store, "https://a.example", &[]);
+
+        // The deletion is dropped and the rename before it undone instead
+        let undone = store.undo().unwrap().unwrap();
+        assert_eq!(undone.action, UndoAction::RenameTag);
+        assert!(store.undo_history().unwrap().is_empty());
+        assert!(store.undo().unwrap().is_none());
+        assert_eq!(store.list_trash().unwrap().len(), 1);
+        assert!(store.get(again).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_undo_tag_change_keeps_later_edits() {
+        let (store, _dir) = create_test_store();
+        let a = tagged(&store, "https://a.example", &["work", "rust"]);
+
+        store.rename_tag("work", "job").unwrap();
+        store
+            .update(a, None, None, Some(&["job".into(), "cli".into()]), None)
+            .unwrap();
+
+        store.undo().unwrap();
+        assert_eq!(store.get(a).unwrap().unwrap().tags, vec!["work", "cli"]);
+    }
+
+    #[test]
+    fn test_keywords_are_unique_and_resolve() {
+        let (store, _dir) = create_test_store();
+
+        let docs = store
+            .add(BookmarkInput::new("https://docs.rs/%s").with_keyword("DRS"))
+            .unwrap();
+        let gh = store
+            .add(BookmarkInput::new("https://github.com/%1/%2"))
+            .unwrap();
+        assert_eq!(
+            store.get(docs).unwrap().unwrap().keyword.as_deref(),
+            Some("drs")
+        );
+
+        assert!(matches!(
+            store.add(BookmarkInput::new("https://other.example").with_keyword("drs")),
+            Err(BookmarkError::DuplicateKeyword(k)) if k == "drs"
+        ));
+        assert!(matches!(
+            store.set_keyword(gh, Some("drs")),
+            Err(BookmarkError::DuplicateKeyword(_))
+        ));
+        assert!(matches!(
+            store.set_keyword(gh, Some("g h")),
+            Err(BookmarkError::InvalidKeyword(_))
+        ));
+        store.set_keyword(gh, Some("gh")).unwrap();
+        store.set_keyword(docs, Some("drs")).unwrap();
+
+        let resolved = store.resolve_keyword("Drs serde json").unwrap().unwrap();
+        assert_eq!(resolved.bookmark.id, docs);
+        assert_eq!(resolved.url, "https://docs.rs/serde%20json");
+
+        let resolved = store
+            .resolve_keyword("gh rust-lang cargo")
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolved.url, "https://github.com/rust-lang/cargo");
+
+        assert!(store.resolve_keyword("nope serde").unwrap().is_none());
+        assert!(store.resolve_keyword("   ").unwrap().is_none());
+
+        store.set_keyword(gh, None).unwrap();
+        assert!(store.resolve_keyword("gh x").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_update_with_rejected_keyword_changes_nothing() {
+        let (store, _dir) = create_test_store();
+
+        store
+            .add(BookmarkInput::new("https://docs.rs/%s").with_keyword("drs"))
+            .unwrap();
+        let id = store
+            .add(BookmarkInput::new("https://example.com"))
+            .unwrap();
+
+        assert!(matches!(
+            store.update_with_keyword(
+                id,
+                Some("https://example.org"),
+                Some("Example"),
+                None,
+                None,
+                Some(Some("drs")),
+            ),
+            Err(BookmarkError::DuplicateKeyword(_))
+        ));
+        let bookmark = store.get(id).unwrap().unwrap();
+        assert_eq!(bookmark.url, "https://example.com");
+        assert_eq!(bookmark.keyword, None);
+
+        store
+            .update_with_keyword(id, None, Some("Example"), None, None, Some(Some("ex")))
+            .unwrap();
+        let bookmark = store.get(id).unwrap().unwrap();
+        assert_eq!(bookmark.title, "Example");
+        assert_eq!(bookmark.keyword.as_deref(), Some("ex"));
+
+        store
+            .update_with_keyword(id, None, None, None, None, Some(None))
+            .unwrap();
+        assert_eq!(store.get(id).unwrap().unwrap().keyword, None);
+    }
+
+    #[test]
+    fn test_restore_keeps_free_keyword() {
+        let (store, _dir) = create_test_store();
+
+        let a = store
+            .add(BookmarkInput::new("https://a.example").with_keyword("a"))
+            .unwrap();
+        let b = store
+            .add(BookmarkInput::new("https://b.example").with_keyword("b"))
+            .unwrap();
+        store.delete(a).unwrap();
+        store.delete(b).unwrap();
+        store
+            .add(BookmarkInput::new("https://c.example").with_keyword("b"))
+            .unwrap();
+
+        assert_eq!(store.restore(a).unwrap().keyword.as_deref(), Some("a"));
+        assert_eq!(store.restore(b).unwrap().keyword, None);
+    }
+
+    fn urls(bookmarks: Vec<Bookmark>) -> Vec<String> {
+        bookmarks.into_iter().map(|b| b.url).collect()
+    }
+
+    #[test]
+    fn test_folders_order_and_move() {
+        let (store, _dir) = create_test_store();
+        let work = store.create_folder("Work", None).unwrap();
+        let infra = store.create_folder("Infra", Some(work)).unwrap();
+
+        let path = vec!["Work".to_string(), "Infra".to_string()];
+        let a = store
+            .add(BookmarkInput::new("https://a.example").with_folder(path.clone()))
+            .unwrap();
+        let b = store
+            .add(BookmarkInput::new("https://b.example").with_folder(path))
+            .unwrap();
+        let c = store
+            .add(BookmarkInput::new("https://c.example").with_folder(vec!["Work".to_string()]))
+            .unwrap();
+        store
+            .add(BookmarkInput::new("https://top.example"))
+            .unwrap();
+
+        // Existing folders are reused
+        assert_eq!(store.list_folders().unwrap().len(), 2);
+        assert_eq!(store.get(a).unwrap().unwrap().folder_id, Some(infra));
+
+        assert_eq!(
+            urls(store.list_folder(Some(infra), false).unwrap()),
+            vec!["https://a.example", "https://b.example"]
+        );
+        store.move_bookmark(b, Some(infra), Some(0)).unwrap();
+        assert_eq!(
+            urls(store.list_folder(Some(infra), false).unwrap()),
+            vec!["https://b.example", "https://a.example"]
+        );
+
+        assert_eq!(
+            urls(store.list_folder(Some(work), true).unwrap()),
+            vec![
+                "https://c.example",
+                "https://b.example",
+                "https://a.example"
+            ]
+        );
+        assert_eq!(
+            urls(store.list_folder(None, false).unwrap()),
+            vec!["https://top.example"]
+        );
+        assert_eq!(
+            urls(
+                store
+                    .search_in_folder(work, "example", None, false, BookmarkOrder::Default)
+                    .unwrap()
+            )
+            .len(),
+            3
+        );
+        assert_eq!(
+            urls(
+                store
+                    .search_in_folder(infra, "", None, false, BookmarkOrder::Default)
+                    .unwrap()
+            )
+            .len(),
+            2
+        );
+
+        store.move_bookmark(c, None, Some(0)).unwrap();
+        assert_eq!(
+            urls(store.list_folder(None, false).unwrap()),
+            vec!["https://c.example", "https://top.example"]
+        );
+        assert!(matches!(
+            store.move_bookmark(c, Some(999), None),
+            Err(BookmarkError::FolderNotFound(999))
+        ));
+    }
+
+    #[test]
+    fn test_move_and_delete_folders() {
+        let (store, _dir) = create_test_store();
+        let work = store.create_folder("Work", None).unwrap();
+        let infra = store.create_folder("Infra", Some(work)).unwrap();
+        let home = store.create_folder("Home", None).unwrap();
+
+        assert!(matches!(
+            store.move_folder(work, Some(infra), None),
+            Err(BookmarkError::InvalidFolder(_))
+        ));
+        assert!(matches!(
+            store.create_folder("  ", None),
+            Err(BookmarkError::InvalidFolder(_))
+        ));
+
+        store.move_folder(home, None, Some(0)).unwrap();
+        store.rename_folder(infra, "Ops").unwrap();
+        let top: Vec<String> = store
+            .list_folders()
+            .unwrap()
+            .into_iter()
+            .filter(|f| f.parent_id.is_none())
+            .map(|f| f.name)
+            .collect();
+        assert_eq!(top, vec!["Home", "Work"]);
+
+        let id = store
+            .add(
+                BookmarkInput::new("https://a.example")
+                    .with_folder(vec!["Work".to_string(), "Ops".to_string()]),
+            )
+            .unwrap();
+        store.delete_folder(work).unwrap();
+
+        assert!(store.get_folder(work).unwrap().is_none());
+        assert_eq!(store.get_folder(infra).unwrap().unwrap().parent_id, None);
+        assert_eq!(store.get(id).unwrap().unwrap().folder_id, Some(infra));
+
+        // Bookmarks come back to their folder, or to the top level once the
+        // folder is gone
+        store.delete(id).unwrap();
+        assert_eq!(store.restore(id).unwrap().folder_id, Some(infra));
+        store.delete(id).unwrap();
+        store.delete_folder(infra).unwrap();
+        assert_eq!(store.restore(id).unwrap().folder_id, None);
+    }
+
+    #[test]
+    fn test_check_links_records_problems_and_fixes_redirects() {
+        use crate::http::stub::StubServer;
+
+        let server = StubServer::start(|_, path| match path {
+            "/ok" => (200, vec![], vec![]),
+            "/old" => (301, vec![("Location", "/ok".to_string())], vec![]),
+            _ => (404, vec![], vec![]),
+        });
+        let (store, _dir) = create_test_store();
+        let ok = store.add(BookmarkInput::new(server.url("/ok"))).unwrap();
+        let moved = store.add(BookmarkInput::new(server.url("/old"))).unwrap();
+        let gone = store.add(BookmarkInput::new(server.url("/gone"))).unwrap();
+        store
+            .add(BookmarkInput::new("file:///tmp/notes.txt"))
+            .unwrap();
+
+        let checker = LinkChecker::new().with_host_interval(Duration::ZERO);
+        let mut reports = Vec::new();
+        let checked = store
+            .check_links(&checker, None, |p| reports.push((p.checked, p.total)))
+            .unwrap();
+        assert_eq!(checked, 3);
+        assert_eq!(reports, vec![(1, 3), (2, 3), (3, 3)]);
+
+        assert_eq!(store.link_status(ok).unwrap().unwrap().status, Some(200));
+        let problems: Vec<i32> = store
+            .link_problems()
+            .unwrap()
+            .iter()
+            .map(|s| s.bookmark_id)
+            .collect();
+        assert_eq!(problems, vec![gone, moved]);
+
+        // Recently checked links are skipped
+        let day = Duration::from_secs(86_400);
+        assert!(store.links_to_check(Some(day)).unwrap().is_empty());
+
+        // The redirect target is already bookmarked
+        assert!(matches!(
+            store.fix_redirect(moved),
+            Err(BookmarkError::Duplicate(id)) if id == ok
+        ));
+        store.delete(ok).unwrap();
+        let fixed = store.fix_redirect(moved).unwrap();
+        assert_eq!(fixed.url, server.url("/ok"));
+        let status = store.link_status(moved).unwrap().unwrap();
+        assert_eq!((status.status, status.redirect_url), (Some(200), None));
+        assert!(matches!(
+            store.fix_redirect(moved),
+            Err(BookmarkError::NoRedirect(_))
+        ));
+
+        // Editing the URL forgets the old result
+        store
+            .update(gone, Some("https://new.example"), None, None, None)
+            .unwrap();
+        assert!(store.link_status(gone).unwrap().is_none());
+        assert!(store.link_problems().unwrap().is_empty());
+    }
 }
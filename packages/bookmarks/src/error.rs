@@ -14,6 +14,56 @@ pub enum BookmarkError {
     #[error("Bookmark not found: {0}")]
     NotFound(i32),
 
+    #[error("Folder not found: {0}")]
+    FolderNotFound(i32),
+
+    #[error("Invalid folder: {0}")]
+    InvalidFolder(String),
+
+    #[error("URL is already bookmarked as {0}")]
+    Duplicate(i32),
+
+    #[error("Invalid keyword: {0:?}")]
+    InvalidKeyword(String),
+
+    #[error("Keyword {0} is already in use")]
+    DuplicateKeyword(String),
+
+    #[error("Bookmark {0} has no known redirect")]
+    NoRedirect(i32),
+
+    #[error("Failed to fetch page: {0}")]
+    Fetch(String),
+
+    #[error("Invalid page cursor: {0:?}")]
+    InvalidCursor(String),
+
+    #[error("Sync failed: {0}")]
+    Sync(String),
+
+    #[error("Wrong passphrase, or not an encrypted bookmark database")]
+    WrongPassphrase,
+
+    #[error("Keyring error: {0}")]
+    Keyring(String),
+
     #[error("Failed to open URL: {0}")]
     OpenUrl(String),
+
+    #[error("Invalid bookmark file: {0}")]
+    InvalidFile(String),
+
+    #[error("Database schema version {found} is newer than supported version {supported}")]
+    SchemaTooNew { found: i32, supported: i32 },
+}
+
+impl From<storage::MigrationError> for BookmarkError {
+    fn from(err: storage::MigrationError) -> Self {
+        match err {
+            storage::MigrationError::Database(e) => BookmarkError::Database(e),
+            storage::MigrationError::SchemaTooNew { found, supported } => {
+                BookmarkError::SchemaTooNew { found, supported }
+            }
+        }
+    }
 }
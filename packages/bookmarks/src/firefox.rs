@@ -0,0 +1,349 @@
+use crate::error::BookmarkError;
+use crate::models::BookmarkInput;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Built-in folders that are not recreated as folders.
+const ROOT_GUIDS: &[&str] = &[
+    "root________",
+    "menu________",
+    "toolbar_____",
+    "unfiled_____",
+    "mobile______",
+];
+/// Children of this folder are Firefox tags rather than real folders.
+const TAGS_GUID: &str = "tags________";
+
+const TYPE_BOOKMARK: i64 = 1;
+const TYPE_FOLDER: i64 = 2;
+
+/// A bookmark read from `places.sqlite` together with its browsing history.
+pub(crate) struct FirefoxEntry {
+    pub input: BookmarkInput,
+    /// Unix timestamps of every recorded visit, newest first.
+    pub visits: Vec<i64>,
+}
+
+struct Folder {
+    parent: i64,
+    title: String,
+    guid: String,
+}
+
+/// Reads bookmarks from a Firefox profile's `places.sqlite`.
+///
+/// Firefox keeps the database locked while running, so the file (and its WAL,
+/// if any) is copied to a temporary directory first and only the copy is
+/// opened. Folders and Firefox tags are kept, folder paths are also added as
+/// a hierarchical tag, and visits from `moz_historyvisits` are returned for
+/// frecency.
+pub(crate) fn read(places: &Path) -> Result<Vec<FirefoxEntry>, BookmarkError> {
+    let dir = tempfile::tempdir()?;
+    let copy = dir.path().join("places.sqlite");
+    std::fs::copy(places, &copy)?;
+
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar = places.as_os_str().to_owned();
+        sidecar.push(suffix);
+        let sidecar = Path::new(&sidecar);
+        if sidecar.exists() {
+            std::fs::copy(sidecar, dir.path().join(format!("places.sqlite{}", suffix)))?;
+        }
+    }
+
+    let conn = Connection::open(&copy)?;
+    read_connection(&conn)
+}
+
+fn read_connection(conn: &Connection) -> Result<Vec<FirefoxEntry>, BookmarkError> {
+    let mut folders: HashMap<i64, Folder> = HashMap::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT id, parent, IFNULL(title, ''), IFNULL(guid, '')
+             FROM moz_bookmarks WHERE type = ?1",
+        )?;
+        let rows = stmt.query_map([TYPE_FOLDER], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                Folder {
+                    parent: row.get(1)?,
+                    title: row.get(2)?,
+                    guid: row.get(3)?,
+                },
+            ))
+        })?;
+        for (id, folder) in rows.flatten() {
+            folders.insert(id, folder);
+        }
+    }
+
+    let tags_root = folders
+        .iter()
+        .find(|(_, f)| f.guid == TAGS_GUID)
+        .map(|(id, _)| *id);
+
+    // Older profiles have no description column on moz_places
+    let description = if conn
+        .prepare("SELECT description FROM moz_places LIMIT 0")
+        .is_ok()
+    {
+        "IFNULL(p.description, '')"
+    } else {
+        "''"
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT b.fk, b.parent, IFNULL(b.title, ''), IFNULL(p.title, ''), p.url,
+                {}, IFNULL(b.dateAdded, 0)
+         FROM moz_bookmarks b JOIN moz_places p ON p.id = b.fk
+         WHERE b.type = ?1
+         ORDER BY b.parent, b.position",
+        description
+    ))?;
+
+    struct Row {
+        place: i64,
+        parent: i64,
+        title: String,
+        url: String,
+        description: String,
+        date_added: i64,
+    }
+
+    let rows: Vec<Row> = stmt
+        .query_map([TYPE_BOOKMARK], |row| {
+            let bookmark_title: String = row.get(2)?;
+            let place_title: String = row.get(3)?;
+            Ok(Row {
+                place: row.get(0)?,
+                parent: row.get(1)?,
+                title: if bookmark_title.is_empty() {
+                    place_title
+                } else {
+                    bookmark_title
+                },
+                url: row.get(4)?,
+                description: row.get(5)?,
+                date_added: row.get(6)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    // Tag assignments are bookmark rows whose parent is a tag folder
+    let mut place_tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in &rows {
+        let Some(folder) = folders.get(&row.parent) else {
+            continue;
+        };
+        if Some(folder.parent) == tags_root {
+            let tag = clean_segment(&folder.title);
+            if !tag.is_empty() {
+                place_tags.entry(row.place).or_default().push(tag);
+            }
+        }
+    }
+
+    let mut keywords = place_keywords(conn);
+    let mut visits = place_visits(conn)?;
+    let mut entries = Vec::new();
+
+    for row in &rows {
+        // Skip tag assignments and `place:` smart bookmarks
+        let in_tags = folders
+            .get(&row.parent)
+            .is_some_and(|f| Some(f.parent) == tags_root);
+        if in_tags || row.url.starts_with("place:") {
+            continue;
+        }
+
+        let mut tags = Vec::new();
+        for tag in place_tags.get(&row.place).into_iter().flatten() {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+
+        let mut input = BookmarkInput::new(&row.url)
+            .with_title(&row.title)
+            .with_folder(folder_path(&folders, row.parent));
+        if !tags.is_empty() {
+            input = input.with_tags(tags);
+        }
+        input = input.with_folder_tag();
+        if !row.description.is_empty() {
+            input = input.with_description(&row.description);
+        }
+        if row.date_added > 0 {
+            input = input.with_added_at(row.date_added / 1_000_000);
+        }
+        if let Some(keyword) = keywords.remove(&row.place) {
+            input = input.with_keyword(keyword);
+        }
+
+        entries.push(FirefoxEntry {
+            input,
+            visits: visits.remove(&row.place).unwrap_or_default(),
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Keywords per place. Profiles from before Firefox 39 kept them elsewhere
+/// and yield none.
+fn place_keywords(conn: &Connection) -> HashMap<i64, String> {
+    let Ok(mut stmt) = conn.prepare("SELECT place_id, keyword FROM moz_keywords") else {
+        return HashMap::new();
+    };
+
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map(|rows| rows.flatten().collect())
+        .unwrap_or_default()
+}
+
+/// Visit timestamps per place, converted from microseconds to seconds.
+fn place_visits(conn: &Connection) -> Result<HashMap<i64, Vec<i64>>, BookmarkError> {
+    let mut stmt = conn.prepare(
+        "SELECT v.place_id, v.visit_date FROM moz_historyvisits v
+         WHERE v.place_id IN (SELECT fk FROM moz_bookmarks WHERE type = ?1)
+         ORDER BY v.visit_date DESC",
+    )?;
+
+    let mut visits: HashMap<i64, Vec<i64>> = HashMap::new();
+    let rows = stmt.query_map([TYPE_BOOKMARK], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+    })?;
+    for (place, visit_date) in rows.flatten() {
+        visits
+            .entry(place)
+            .or_default()
+            .push(visit_date / 1_000_000);
+    }
+
+    Ok(visits)
+}
+
+fn folder_path(folders: &HashMap<i64, Folder>, mut id: i64) -> Vec<String> {
+    let mut path = Vec::new();
+
+    while let Some(folder) = folders.get(&id) {
+        if ROOT_GUIDS.contains(&folder.guid.as_str()) || folder.guid == TAGS_GUID {
+            break;
+        }
+        let name = folder.title.trim();
+        if !name.is_empty() {
+            path.push(name.to_string());
+        }
+        id = folder.parent;
+    }
+
+    path.reverse();
+    path
+}
+
+/// Commas separate tags in the database, so they cannot appear inside one.
+fn clean_segment(name: &str) -> String {
+    name.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal subset of the real places.sqlite schema.
+    fn fixture() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE moz_places (
+                id INTEGER PRIMARY KEY, url TEXT, title TEXT, description TEXT
+            );
+            CREATE TABLE moz_bookmarks (
+                id INTEGER PRIMARY KEY, type INTEGER, fk INTEGER, parent INTEGER,
+                position INTEGER, title TEXT, dateAdded INTEGER, guid TEXT
+            );
+            CREATE TABLE moz_historyvisits (
+                id INTEGER PRIMARY KEY, place_id INTEGER, visit_date INTEGER
+            );
+            CREATE TABLE moz_keywords (
+                id INTEGER PRIMARY KEY, keyword TEXT UNIQUE, place_id INTEGER
+            );
+
+            INSERT INTO moz_bookmarks (id, type, parent, position, title, guid) VALUES
+                (1, 2, 0, 0, '', 'root________'),
+                (2, 2, 1, 0, 'menu', 'menu________'),
+                (3, 2, 1, 1, 'toolbar', 'toolbar_____'),
+                (4, 2, 1, 2, 'tags', 'tags________'),
+                (10, 2, 3, 0, 'Work', 'work________'),
+                (11, 2, 10, 0, 'Infra', 'infra_______'),
+                (20, 2, 4, 0, 'k8s', 'tagk8s______');
+
+            INSERT INTO moz_places (id, url, title, description) VALUES
+                (100, 'https://grafana.example/', 'Grafana page', 'Dashboards'),
+                (101, 'https://docs.rs/', 'Docs', NULL),
+                (102, 'place:sort=8&maxResults=10', 'Recent', NULL);
+
+            INSERT INTO moz_bookmarks (id, type, fk, parent, position, title, dateAdded, guid) VALUES
+                (30, 1, 100, 11, 0, 'Grafana', 1700000000000000, 'bm1_________'),
+                (31, 1, 101, 2, 0, NULL, 0, 'bm2_________'),
+                (32, 1, 102, 3, 1, 'Recent', 0, 'bm3_________'),
+                (33, 1, 100, 20, 0, NULL, 0, 'bm4_________');
+
+            INSERT INTO moz_historyvisits (place_id, visit_date) VALUES
+                (100, 1700000100000000),
+                (100, 1700000200000000);
+
+            INSERT INTO moz_keywords (keyword, place_id) VALUES ('drs', 101);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_read_keeps_folders_tags_and_visits() {
+        let entries = read_connection(&fixture()).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let grafana = entries
+            .iter()
+            .find(|e| e.input.url == "https://grafana.example/")
+            .unwrap();
+        assert_eq!(grafana.input.title.as_deref(), Some("Grafana"));
+        assert_eq!(
+            grafana.input.tags,
+            Some(vec!["k8s".to_string(), "Work/Infra".to_string()])
+        );
+        assert_eq!(grafana.input.folder, vec!["Work", "Infra"]);
+        assert_eq!(grafana.input.description.as_deref(), Some("Dashboards"));
+        assert_eq!(grafana.input.added_at, Some(1_700_000_000));
+        assert_eq!(grafana.visits, vec![1_700_000_200, 1_700_000_100]);
+
+        let docs = entries
+            .iter()
+            .find(|e| e.input.url == "https://docs.rs/")
+            .unwrap();
+        assert_eq!(docs.input.title.as_deref(), Some("Docs"));
+        assert_eq!(docs.input.tags, None);
+        assert!(docs.input.folder.is_empty());
+        assert_eq!(docs.input.keyword.as_deref(), Some("drs"));
+        assert_eq!(grafana.input.keyword, None);
+        assert!(docs.visits.is_empty());
+    }
+
+    #[test]
+    fn test_read_copies_database_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let places = dir.path().join("places.sqlite");
+        fixture()
+            .execute("VACUUM INTO ?1", [places.to_str().unwrap()])
+            .unwrap();
+
+        let entries = read(&places).unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+}
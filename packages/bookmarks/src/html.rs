@@ -0,0 +1,140 @@
+//! Just enough HTML tokenizing to read bookmark files and page headers.
+//! Tag names are uppercased and attribute values unescaped; text is returned
+//! as written, for callers to [`unescape`].
+
+pub(crate) enum Token<'a> {
+    Tag {
+        name: String,
+        closing: bool,
+        attrs: &'a str,
+    },
+    Text(&'a str),
+}
+
+pub(crate) fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+        }
+
+        let after = &rest[start..];
+        if let Some(comment) = after.strip_prefix("<!--") {
+            rest = comment
+                .find("-->")
+                .map(|end| &comment[end + 3..])
+                .unwrap_or("");
+            continue;
+        }
+
+        let Some(end) = after.find('>') else {
+            tokens.push(Token::Text(after));
+            break;
+        };
+
+        let inner = &after[1..end];
+        let (closing, inner) = match inner.strip_prefix('/') {
+            Some(inner) => (true, inner),
+            None => (false, inner),
+        };
+        let name_end = inner
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(inner.len());
+
+        tokens.push(Token::Tag {
+            name: inner[..name_end].to_ascii_uppercase(),
+            closing,
+            attrs: &inner[name_end..],
+        });
+        rest = &after[end + 1..];
+    }
+
+    tokens
+}
+
+/// Reads `NAME="value"` (or single-quoted / bare) attribute values.
+pub(crate) fn attribute(attrs: &str, wanted: &str) -> Option<String> {
+    let mut rest = attrs.trim_start();
+
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+
+        let value = if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (value, remaining) = match after_eq.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let body = &after_eq[1..];
+                    let end = body.find(quote).unwrap_or(body.len());
+                    (&body[..end], body.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                    (&after_eq[..end], &after_eq[end..])
+                }
+            };
+            rest = remaining.trim_start();
+            value
+        } else {
+            ""
+        };
+
+        if name.eq_ignore_ascii_case(wanted) {
+            return Some(unescape(value));
+        }
+    }
+
+    None
+}
+
+pub(crate) fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start..];
+
+        let decoded = after.find(';').and_then(|end| {
+            let entity = &after[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                    .and_then(char::from_u32),
+            }?;
+            Some((c, end + 1))
+        });
+
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &after[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &after[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,95 @@
+//! Blocking HTTP client settings shared by everything that goes online.
+
+use std::time::Duration;
+
+const USER_AGENT: &str = concat!("MRunner/", env!("CARGO_PKG_VERSION"));
+
+/// An agent that follows up to `max_redirects` redirects and returns error
+/// statuses as responses rather than errors, so callers can inspect them.
+/// With no redirects allowed, 3xx responses are returned too.
+pub(crate) fn agent(timeout: Duration, max_redirects: u32) -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .max_redirects(max_redirects)
+        .http_status_as_error(false)
+        .user_agent(USER_AGENT)
+        .build()
+        .into()
+}
+
+/// A minimal HTTP/1.1 server on localhost for tests.
+#[cfg(test)]
+pub(crate) mod stub {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    /// Status, extra headers and body returned for a request.
+    pub(crate) type Reply = (u16, Vec<(&'static str, String)>, Vec<u8>);
+
+    pub(crate) struct StubServer {
+        addr: SocketAddr,
+        /// Method, path and arrival time of every request, in order.
+        pub requests: Arc<Mutex<Vec<(String, String, Instant)>>>,
+    }
+
+    impl StubServer {
+        /// Serves requests with `handler(method, path)` until the test ends.
+        pub(crate) fn start(handler: impl Fn(&str, &str) -> Reply + Send + Sync + 'static) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let handler = Arc::new(handler);
+
+            let log = requests.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let handler = handler.clone();
+                    let log = log.clone();
+                    std::thread::spawn(move || {
+                        let mut reader = BufReader::new(&stream);
+                        let mut request_line = String::new();
+                        if reader.read_line(&mut request_line).is_err() {
+                            return;
+                        }
+                        let mut line = String::new();
+                        while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                            line.clear();
+                        }
+
+                        let mut parts = request_line.split_whitespace();
+                        let method = parts.next().unwrap_or("").to_string();
+                        let path = parts.next().unwrap_or("").to_string();
+                        log.lock()
+                            .unwrap()
+                            .push((method.clone(), path.clone(), Instant::now()));
+
+                        let (status, headers, body) = handler(&method, &path);
+                        let mut response = format!(
+                            "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\nConnection: close\r\n",
+                            status,
+                            body.len()
+                        );
+                        for (name, value) in headers {
+                            response.push_str(&format!("{}: {}\r\n", name, value));
+                        }
+                        response.push_str("\r\n");
+
+                        let mut stream = &stream;
+                        let _ = stream.write_all(response.as_bytes());
+                        if method != "HEAD" {
+                            let _ = stream.write_all(&body);
+                        }
+                    });
+                }
+            });
+
+            Self { addr, requests }
+        }
+
+        pub(crate) fn url(&self, path: &str) -> String {
+            format!("http://{}{}", self.addr, path)
+        }
+    }
+}
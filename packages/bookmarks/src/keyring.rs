@@ -0,0 +1,158 @@
+//! Passphrase storage in the desktop keyring through the freedesktop Secret
+//! Service, using libsecret's `secret-tool`.
+
+use crate::db::BookmarkStore;
+use crate::error::BookmarkError;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Bytes of randomness in a generated passphrase.
+const PASSPHRASE_BYTES: usize = 32;
+
+/// A secret in the Secret Service keyring (GNOME Keyring, KWallet, ...),
+/// identified by its attributes.
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    program: String,
+    label: String,
+    attributes: Vec<(String, String)>,
+}
+
+impl Keyring {
+    /// The passphrase of MRunner's encrypted bookmark database.
+    pub fn bookmarks() -> Self {
+        Self {
+            program: "secret-tool".to_string(),
+            label: "MRunner bookmarks".to_string(),
+            attributes: vec![
+                ("application".to_string(), "mrunner".to_string()),
+                ("database".to_string(), "bookmarks".to_string()),
+            ],
+        }
+    }
+
+    fn command(&self, action: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        command.arg(action);
+        if action == "store" {
+            command.arg(format!("--label={}", self.label));
+        }
+        for (key, value) in &self.attributes {
+            command.arg(key).arg(value);
+        }
+        command
+    }
+
+    fn run(&self, mut command: Command, input: Option<&str>) -> Result<String, BookmarkError> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                BookmarkError::Keyring(format!("Failed to run {}: {}", self.program, e))
+            })?;
+
+        // Dropping stdin closes it, which secret-tool takes as the end of input
+        let mut stdin = child.stdin.take();
+        if let (Some(stdin), Some(input)) = (stdin.as_mut(), input) {
+            stdin.write_all(input.as_bytes())?;
+        }
+        drop(stdin);
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(BookmarkError::Keyring(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// The stored secret, `None` if there is none.
+    pub fn get(&self) -> Result<Option<String>, BookmarkError> {
+        // secret-tool fails without a message when nothing matches
+        match self.run(self.command("lookup"), None) {
+            Ok(secret) => Ok(Some(secret).filter(|s| !s.is_empty())),
+            Err(BookmarkError::Keyring(message)) if message.is_empty() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stores `secret`, replacing any previous one.
+    pub fn set(&self, secret: &str) -> Result<(), BookmarkError> {
+        self.run(self.command("store"), Some(secret)).map(|_| ())
+    }
+
+    /// The stored passphrase of the database at `database`, generating and
+    /// storing a random one the first time, while the database is missing or
+    /// not yet encrypted. A locked keyring looks empty to `secret-tool`, so
+    /// for an encrypted database without a secret this fails rather than
+    /// replace the passphrase it was encrypted with.
+    pub fn get_or_create(&self, database: &Path) -> Result<String, BookmarkError> {
+        if let Some(secret) = self.get()? {
+            return Ok(secret);
+        }
+        if database.exists() && !BookmarkStore::is_plaintext(database)? {
+            return Err(BookmarkError::Keyring(format!(
+                "No passphrase for {} in the keyring; is it locked?",
+                database.display()
+            )));
+        }
+
+        let mut bytes = [0; PASSPHRASE_BYTES];
+        std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+        let secret: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        self.set(&secret)?;
+        Ok(secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_creates_stores_and_reads_back_a_passphrase() {
+        // A stand-in for secret-tool keeping the secret in a file
+        let dir = tempdir().unwrap();
+        let store = dir.path().join("secret");
+        let program = dir.path().join("secret-tool");
+        std::fs::write(
+            &program,
+            format!(
+                "#!/bin/sh\ncase \"$1\" in\n  lookup) [ -f {0} ] && cat {0} || exit 1 ;;\n  store) cat > {0} ;;\nesac\n",
+                store.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let keyring = Keyring {
+            program: program.to_string_lossy().into_owned(),
+            ..Keyring::bookmarks()
+        };
+        assert_eq!(keyring.get().unwrap(), None);
+
+        // An encrypted database never gets a new passphrase
+        let encrypted = dir.path().join("encrypted.db");
+        std::fs::write(&encrypted, [0x5a; 64]).unwrap();
+        assert!(matches!(
+            keyring.get_or_create(&encrypted),
+            Err(BookmarkError::Keyring(_))
+        ));
+        assert!(!store.exists());
+
+        let database = dir.path().join("bookmarks.db");
+        let passphrase = keyring.get_or_create(&database).unwrap();
+        assert_eq!(passphrase.len(), PASSPHRASE_BYTES * 2);
+        assert_eq!(keyring.get().unwrap().as_deref(), Some(passphrase.as_str()));
+        assert_eq!(keyring.get_or_create(&encrypted).unwrap(), passphrase);
+
+        keyring.set("changed").unwrap();
+        assert_eq!(keyring.get().unwrap().as_deref(), Some("changed"));
+    }
+}
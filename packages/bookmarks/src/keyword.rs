@@ -0,0 +1,137 @@
+//! Firefox-style keyword bookmarks: a bookmark with keyword `drs` and URL
+//! `https://docs.rs/%s` turns the launcher input `drs serde` into
+//! `https://docs.rs/serde`.
+
+use crate::error::BookmarkError;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// Everything except RFC 3986 unreserved characters is encoded, so an
+/// argument is safe anywhere in a URL, path or query.
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+fn encode(text: &str) -> String {
+    utf8_percent_encode(text, COMPONENT).to_string()
+}
+
+/// Trims and lowercases a keyword. Keywords are matched against the first
+/// word of the input, so they cannot be empty or contain whitespace.
+pub(crate) fn normalize(keyword: &str) -> Result<String, BookmarkError> {
+    let keyword = keyword.trim();
+    if keyword.is_empty() || keyword.chars().any(char::is_whitespace) {
+        return Err(BookmarkError::InvalidKeyword(keyword.to_string()));
+    }
+    Ok(keyword.to_lowercase())
+}
+
+/// Splits launcher input into its first word, the candidate keyword, and the
+/// rest, the argument.
+pub(crate) fn split(input: &str) -> Option<(&str, &str)> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+
+    Some(match input.split_once(char::is_whitespace) {
+        Some((keyword, argument)) => (keyword, argument.trim()),
+        None => (input, ""),
+    })
+}
+
+/// Replaces the placeholders in `template` with `argument`:
+///
+/// - `%s` is the whole argument, percent-encoded
+/// - `%S` is the whole argument as typed
+/// - `%1` to `%9` are its individual words, percent-encoded, or nothing if
+///   the argument has fewer words
+///
+/// A `%` followed by a digit and a hex digit is an existing escape such as
+/// `%20` and is left alone, as is any other `%`.
+pub(crate) fn expand(template: &str, argument: &str) -> String {
+    let words: Vec<&str> = argument.split_whitespace().collect();
+    let mut out = String::with_capacity(template.len() + argument.len());
+    let mut rest = template;
+
+    while let Some(pos) = rest.find('%') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let mut chars = after.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some('s'), _) => out.push_str(&encode(argument)),
+            (Some('S'), _) => out.push_str(argument),
+            (Some(digit @ '1'..='9'), next) if !next.is_some_and(|c| c.is_ascii_hexdigit()) => {
+                let index = digit as usize - '1' as usize;
+                if let Some(word) = words.get(index) {
+                    out.push_str(&encode(word));
+                }
+            }
+            _ => {
+                out.push('%');
+                rest = after;
+                continue;
+            }
+        }
+        rest = &after[1..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(" DRS ").unwrap(), "drs");
+        assert!(matches!(
+            normalize("two words"),
+            Err(BookmarkError::InvalidKeyword(_))
+        ));
+        assert!(normalize("  ").is_err());
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split("drs serde json"), Some(("drs", "serde json")));
+        assert_eq!(split("  drs  "), Some(("drs", "")));
+        assert_eq!(split(""), None);
+    }
+
+    #[test]
+    fn test_expand_whole_argument() {
+        assert_eq!(
+            expand("https://docs.rs/%s", "serde"),
+            "https://docs.rs/serde"
+        );
+        assert_eq!(
+            expand("https://duckduckgo.com/?q=%s", "c++ & rust/wasm"),
+            "https://duckduckgo.com/?q=c%2B%2B%20%26%20rust%2Fwasm"
+        );
+        assert_eq!(
+            expand("https://x.example/%S", "a/b c"),
+            "https://x.example/a/b c"
+        );
+        assert_eq!(
+            expand("https://x.example/", "ignored"),
+            "https://x.example/"
+        );
+    }
+
+    #[test]
+    fn test_expand_positional_words() {
+        assert_eq!(
+            expand("https://github.com/%1/%2/issues?q=%3", "rust-lang cargo"),
+            "https://github.com/rust-lang/cargo/issues?q="
+        );
+        assert_eq!(
+            expand("https://x.example/%2?sep=%2F&sp=%20&p=%", "a b"),
+            "https://x.example/b?sep=%2F&sp=%20&p=%"
+        );
+    }
+}
@@ -1,7 +1,32 @@
+mod canonical;
+mod chrome;
+mod cursor;
 mod db;
 mod error;
+mod firefox;
+mod html;
+mod http;
+// The Secret Service and `/dev/urandom` are only found on Unix desktops
+#[cfg(all(feature = "encryption", unix))]
+mod keyring;
+mod keyword;
+mod links;
+mod metadata;
 mod models;
+mod netscape;
+mod sync;
+mod watch;
 
 pub use db::BookmarkStore;
 pub use error::BookmarkError;
-pub use models::{Bookmark, BookmarkInput, Tag};
+#[cfg(all(feature = "encryption", unix))]
+pub use keyring::Keyring;
+pub use links::LinkChecker;
+pub use metadata::MetadataFetcher;
+pub use models::{
+    Bookmark, BookmarkChanges, BookmarkInput, BookmarkOrder, BookmarkPage, BookmarkSort, Folder,
+    ImportReport, KeywordMatch, LinkCheckProgress, LinkStatus, PageMetadata, PageRequest,
+    SyncConflict, SyncReport, Tag, TrashedBookmark, UndoAction, UndoEntry,
+};
+pub use sync::GitSync;
+pub use watch::DatabaseWatcher;
@@ -0,0 +1,316 @@
+//! Concurrent link-health checks for bookmark URLs.
+
+use crate::db::unix_now;
+use crate::http;
+use crate::models::LinkStatus;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Redirects followed before a link is reported as a redirect loop.
+const MAX_REDIRECTS: usize = 5;
+
+/// Statuses from servers that reject `HEAD` but may answer `GET`.
+const HEAD_UNSUPPORTED: &[u16] = &[403, 405, 501];
+
+/// Checks bookmark URLs with a pool of worker threads, spacing out requests
+/// to the same host so large collections don't hammer a single server.
+#[derive(Debug, Clone)]
+pub struct LinkChecker {
+    concurrency: usize,
+    host_interval: Duration,
+    timeout: Duration,
+}
+
+impl Default for LinkChecker {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            host_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl LinkChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of links checked in parallel.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Minimum time between two requests to the same host.
+    pub fn with_host_interval(mut self, interval: Duration) -> Self {
+        self.host_interval = interval;
+        self
+    }
+
+    /// Time allowed for each request, including reading the response.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Checks every `(bookmark id, url)` pair and calls `on_result` on the
+    /// calling thread as each one finishes, in completion order.
+    pub fn check(&self, targets: Vec<(i32, String)>, mut on_result: impl FnMut(LinkStatus)) {
+        let workers = self.concurrency.min(targets.len());
+        let queue = Mutex::new(VecDeque::from(targets));
+        let limiter = HostLimiter::new(self.host_interval);
+        let agent = http::agent(self.timeout, 0);
+        let (tx, rx) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let tx = tx.clone();
+                let (queue, limiter, agent) = (&queue, &limiter, &agent);
+                scope.spawn(move || loop {
+                    let next = queue.lock().unwrap().pop_front();
+                    let Some((id, url)) = next else {
+                        break;
+                    };
+                    if tx.send(probe(agent, limiter, id, &url)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(tx);
+
+            for status in rx {
+                on_result(status);
+            }
+        });
+    }
+}
+
+/// Hands out request slots per host, `interval` apart.
+struct HostLimiter {
+    interval: Duration,
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostLimiter {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Blocks until a request to `url`'s host is allowed.
+    fn wait(&self, url: &Url) {
+        let Some(host) = url.host_str() else {
+            return;
+        };
+
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let slot = next.get(host).map_or(now, |&at| at.max(now));
+            next.insert(host.to_string(), slot + self.interval);
+            slot
+        };
+
+        let delay = slot.saturating_duration_since(Instant::now());
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+    }
+}
+
+/// Sends `method` to `url` and returns the status and `Location` header.
+fn request(
+    agent: &ureq::Agent,
+    method: &str,
+    url: &Url,
+) -> Result<(u16, Option<String>), ureq::Error> {
+    let response = if method == "HEAD" {
+        agent.head(url.as_str()).call()?
+    } else {
+        agent.get(url.as_str()).call()?
+    };
+
+    let location = response
+        .headers()
+        .get("location")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    Ok((response.status().as_u16(), location))
+}
+
+/// Requests `url` with `HEAD`, retrying with `GET` when the server does not
+/// support `HEAD` or the request fails outright.
+fn fetch(
+    agent: &ureq::Agent,
+    limiter: &HostLimiter,
+    url: &Url,
+) -> Result<(u16, Option<String>), ureq::Error> {
+    limiter.wait(url);
+    match request(agent, "HEAD", url) {
+        Ok((status, _)) if HEAD_UNSUPPORTED.contains(&status) => {}
+        Err(_) => {}
+        ok => return ok,
+    }
+
+    limiter.wait(url);
+    request(agent, "GET", url)
+}
+
+/// Checks one link, following redirects up to [`MAX_REDIRECTS`].
+fn probe(agent: &ureq::Agent, limiter: &HostLimiter, bookmark_id: i32, url: &str) -> LinkStatus {
+    let mut status = LinkStatus {
+        bookmark_id,
+        status: None,
+        redirect_url: None,
+        error: None,
+        checked_at: unix_now(),
+    };
+
+    let mut current = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            status.error = Some(format!("Invalid URL: {}", err));
+            return status;
+        }
+    };
+
+    // Only a followed redirect counts, not the URL's normalization by `Url`
+    let mut redirected = false;
+    for _ in 0..=MAX_REDIRECTS {
+        let (code, location) = match fetch(agent, limiter, &current) {
+            Ok(response) => response,
+            Err(err) => {
+                status.status = None;
+                status.error = Some(err.to_string());
+                return status;
+            }
+        };
+        status.status = Some(code);
+
+        let next = location
+            .filter(|_| (300..400).contains(&code))
+            .and_then(|location| current.join(&location).ok());
+        match next {
+            Some(next) => {
+                current = next;
+                redirected = true;
+            }
+            None => {
+                if redirected {
+                    status.redirect_url = Some(current.into());
+                }
+                return status;
+            }
+        }
+    }
+
+    status.status = None;
+    status.error = Some("Too many redirects".to_string());
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::stub::StubServer;
+
+    fn check(checker: &LinkChecker, urls: &[String]) -> Vec<LinkStatus> {
+        let targets = urls
+            .iter()
+            .enumerate()
+            .map(|(i, url)| (i as i32, url.clone()))
+            .collect();
+        let mut results = Vec::new();
+        checker.check(targets, |status| results.push(status));
+        results.sort_by_key(|status| status.bookmark_id);
+        results
+    }
+
+    #[test]
+    fn test_statuses_redirects_and_head_fallback() {
+        let server = StubServer::start(|method, path| match (method, path) {
+            (_, "/ok") => (200, vec![], b"hello".to_vec()),
+            (_, "/moved") => (301, vec![("Location", "/ok".to_string())], vec![]),
+            ("HEAD", "/no-head") => (405, vec![], vec![]),
+            ("GET", "/no-head") => (200, vec![], b"hello".to_vec()),
+            (_, "/loop") => (302, vec![("Location", "/loop".to_string())], vec![]),
+            _ => (404, vec![], vec![]),
+        });
+        let checker = LinkChecker::new().with_host_interval(Duration::ZERO);
+
+        let urls =
+            ["/ok", "/missing", "/moved", "/no-head", "/loop", "/a/../ok"].map(|p| server.url(p));
+        let results = check(&checker, &urls);
+
+        assert_eq!(results[0].status, Some(200));
+        assert_eq!(results[0].redirect_url, None);
+        assert!(!results[0].is_broken());
+
+        assert_eq!(results[1].status, Some(404));
+        assert!(results[1].is_broken());
+
+        assert_eq!(results[2].status, Some(200));
+        assert_eq!(results[2].redirect_url, Some(server.url("/ok")));
+
+        assert_eq!(results[3].status, Some(200));
+        assert_eq!(results[3].redirect_url, None);
+
+        assert_eq!(results[4].status, None);
+        assert_eq!(results[4].error.as_deref(), Some("Too many redirects"));
+        assert!(results[4].is_broken());
+
+        // A URL that only differs once normalized did not redirect
+        assert_eq!(results[5].status, Some(200));
+        assert_eq!(results[5].redirect_url, None);
+    }
+
+    #[test]
+    fn test_unreachable_and_invalid_urls() {
+        // Bind and drop a listener to get a port nobody listens on
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let urls = [
+            format!("http://127.0.0.1:{}/", port),
+            "not a url".to_string(),
+        ];
+        let results = check(&LinkChecker::new(), &urls);
+
+        assert_eq!(results[0].status, None);
+        assert!(results[0].error.is_some());
+        assert!(results[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("Invalid URL"));
+    }
+
+    #[test]
+    fn test_requests_to_one_host_are_spaced() {
+        let server = StubServer::start(|_, _| (200, vec![], vec![]));
+        let interval = Duration::from_millis(100);
+        let checker = LinkChecker::new()
+            .with_concurrency(4)
+            .with_host_interval(interval);
+
+        let urls: Vec<String> = (0..4).map(|i| server.url(&format!("/{}", i))).collect();
+        check(&checker, &urls);
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        let mut times: Vec<Instant> = requests.iter().map(|(_, _, at)| *at).collect();
+        times.sort();
+        for pair in times.windows(2) {
+            // Allow for scheduling jitter between the slot and the request
+            assert!(pair[1] - pair[0] >= interval - Duration::from_millis(20));
+        }
+    }
+}
@@ -0,0 +1,447 @@
+//! Page title, description and favicon lookup for new bookmarks.
+
+use crate::db::BookmarkStore;
+use crate::error::BookmarkError;
+use crate::html::{attribute, tokenize, unescape, Token};
+use crate::http;
+use crate::models::{BookmarkInput, PageMetadata};
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::Duration;
+use ureq::ResponseExt;
+use url::Url;
+
+/// Bytes of a page read while looking for its `<head>`.
+const MAX_PAGE_BYTES: u64 = 512 * 1024;
+
+/// Favicons larger than this are not cached.
+const MAX_FAVICON_BYTES: u64 = 256 * 1024;
+
+const MAX_REDIRECTS: u32 = 5;
+
+/// Extensions favicons are cached under, by content type.
+const FAVICON_TYPES: &[(&str, &str)] = &[
+    ("image/x-icon", "ico"),
+    ("image/vnd.microsoft.icon", "ico"),
+    ("image/png", "png"),
+    ("image/svg+xml", "svg"),
+    ("image/gif", "gif"),
+    ("image/jpeg", "jpg"),
+    ("image/webp", "webp"),
+];
+
+/// Fetches page details over HTTP and keeps one favicon per site on disk.
+#[derive(Debug, Clone)]
+pub struct MetadataFetcher {
+    timeout: Duration,
+    favicon_dir: PathBuf,
+}
+
+impl MetadataFetcher {
+    /// Caches favicons under MRunner's data directory.
+    pub fn new() -> Result<Self, BookmarkError> {
+        Ok(Self::with_favicon_dir(
+            BookmarkStore::data_dir()?.join("favicons"),
+        ))
+    }
+
+    pub fn with_favicon_dir(dir: PathBuf) -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            favicon_dir: dir,
+        }
+    }
+
+    /// Time allowed for each request, including reading the response.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reads the title and description of the page at `url` and caches its
+    /// favicon. A missing favicon is not an error.
+    pub fn fetch(&self, url: &str) -> Result<PageMetadata, BookmarkError> {
+        let url = web_url(url)
+            .ok_or_else(|| BookmarkError::Fetch(format!("{} is not a web page", url)))?;
+        let agent = http::agent(self.timeout, MAX_REDIRECTS);
+
+        let mut response = agent
+            .get(url.as_str())
+            .call()
+            .map_err(|e| BookmarkError::Fetch(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(BookmarkError::Fetch(format!(
+                "{} returned {}",
+                url,
+                response.status()
+            )));
+        }
+
+        let base = Url::parse(&response.get_uri().to_string()).unwrap_or(url);
+        let is_html = response
+            .body()
+            .mime_type()
+            .is_none_or(|mime| mime.contains("html"));
+
+        let mut head = Head::default();
+        if is_html {
+            let mut page = Vec::new();
+            response
+                .body_mut()
+                .as_reader()
+                .take(MAX_PAGE_BYTES)
+                .read_to_end(&mut page)
+                .map_err(|e| BookmarkError::Fetch(e.to_string()))?;
+            head = Head::parse(&String::from_utf8_lossy(&page), &base);
+        }
+
+        let favicon = self.cached_favicon(base.as_str()).or_else(|| {
+            let fallback = base.join("/favicon.ico").ok();
+            head.icon
+                .into_iter()
+                .chain(fallback)
+                .find_map(|icon| self.download_favicon(&agent, &base, &icon))
+        });
+
+        Ok(PageMetadata {
+            title: head.title,
+            description: head.description,
+            favicon,
+        })
+    }
+
+    /// Fills the title and description left blank in `input` from the page.
+    /// A title marked immutable is kept even when blank. Nothing is fetched
+    /// for URLs that are not `http` or `https`, or when there is nothing to
+    /// fill and the favicon is already cached.
+    pub fn fill(&self, input: &mut BookmarkInput) -> Result<PageMetadata, BookmarkError> {
+        let blank = |field: &Option<String>| field.as_ref().is_none_or(|s| s.trim().is_empty());
+        let wants_title = blank(&input.title) && !input.immutable_title;
+        let wants_description = blank(&input.description);
+
+        if web_url(&input.url).is_none() {
+            return Ok(PageMetadata::default());
+        }
+        if !wants_title && !wants_description {
+            if let Some(favicon) = self.cached_favicon(&input.url) {
+                return Ok(PageMetadata {
+                    favicon: Some(favicon),
+                    ..Default::default()
+                });
+            }
+        }
+
+        let metadata = self.fetch(&input.url)?;
+        if wants_title && metadata.title.is_some() {
+            input.title = metadata.title.clone();
+        }
+        if wants_description && metadata.description.is_some() {
+            input.description = metadata.description.clone();
+        }
+        Ok(metadata)
+    }
+
+    /// The cached favicon for `url`'s site, if one was downloaded before.
+    pub fn cached_favicon(&self, url: &str) -> Option<PathBuf> {
+        let key = cache_key(&Url::parse(url).ok()?)?;
+        FAVICON_TYPES
+            .iter()
+            .map(|(_, ext)| self.favicon_dir.join(format!("{}.{}", key, ext)))
+            .find(|path| path.is_file())
+    }
+
+    /// Downloads an icon and caches it under `page`'s site. Responses that
+    /// are not images, are empty or too large are ignored.
+    fn download_favicon(&self, agent: &ureq::Agent, page: &Url, icon: &Url) -> Option<PathBuf> {
+        let mut response = agent.get(icon.as_str()).call().ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+
+        let extension = match response.body().mime_type() {
+            None | Some("application/octet-stream") => "ico",
+            Some(mime) => FAVICON_TYPES
+                .iter()
+                .find(|(ty, _)| *ty == mime)
+                .map(|(_, ext)| *ext)?,
+        };
+
+        let mut bytes = Vec::new();
+        response
+            .body_mut()
+            .as_reader()
+            .take(MAX_FAVICON_BYTES + 1)
+            .read_to_end(&mut bytes)
+            .ok()?;
+        if bytes.is_empty() || bytes.len() as u64 > MAX_FAVICON_BYTES {
+            return None;
+        }
+
+        let path = self
+            .favicon_dir
+            .join(format!("{}.{}", cache_key(page)?, extension));
+        std::fs::create_dir_all(&self.favicon_dir).ok()?;
+        std::fs::write(&path, bytes).ok()?;
+        Some(path)
+    }
+}
+
+fn web_url(url: &str) -> Option<Url> {
+    Url::parse(url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// File name for a site's favicon: its host and non-default port, with
+/// anything but letters, digits, `-` and `.` replaced.
+fn cache_key(url: &Url) -> Option<String> {
+    let mut key = url.host_str()?.to_ascii_lowercase();
+    if let Some(port) = url.port() {
+        key.push_str(&format!("_{}", port));
+    }
+    Some(
+        key.chars()
+            .map(|c| match c {
+                'a'..='z' | '0'..='9' | '-' | '.' => c,
+                _ => '_',
+            })
+            .collect(),
+    )
+}
+
+/// Details found in a page's `<head>`.
+#[derive(Debug, Default)]
+struct Head {
+    title: Option<String>,
+    description: Option<String>,
+    /// Preferred icon link, resolved against the page URL.
+    icon: Option<Url>,
+}
+
+impl Head {
+    /// Reads `<title>`, the meta description and icon links, falling back to
+    /// Open Graph properties. Stops at `<body>`.
+    fn parse(html: &str, url: &Url) -> Self {
+        let mut title: Option<String> = None;
+        let mut og_title = None;
+        let mut description = None;
+        let mut og_description = None;
+        let mut icons: Vec<(String, String)> = Vec::new();
+        let mut base = url.clone();
+        let mut in_title = false;
+
+        for token in tokenize(html) {
+            match token {
+                Token::Text(text) if in_title => {
+                    title.get_or_insert_with(String::new).push_str(text);
+                }
+                Token::Text(_) => {}
+                Token::Tag { name, closing, .. } if closing => {
+                    if name == "TITLE" {
+                        in_title = false;
+                    } else if name == "HEAD" {
+                        break;
+                    }
+                }
+                Token::Tag { name, attrs, .. } => match name.as_str() {
+                    "TITLE" => in_title = title.is_none(),
+                    "BODY" => break,
+                    "BASE" => {
+                        if let Some(href) = attribute(attrs, "href") {
+                            base = url.join(&href).unwrap_or(base);
+                        }
+                    }
+                    "META" => {
+                        let key = attribute(attrs, "name")
+                            .or_else(|| attribute(attrs, "property"))
+                            .map(|key| key.to_ascii_lowercase());
+                        let content = attribute(attrs, "content");
+                        match key.as_deref() {
+                            Some("description") => description = description.or(content),
+                            Some("og:description") => og_description = og_description.or(content),
+                            Some("og:title") => og_title = og_title.or(content),
+                            _ => {}
+                        }
+                    }
+                    "LINK" => {
+                        if let (Some(rel), Some(href)) =
+                            (attribute(attrs, "rel"), attribute(attrs, "href"))
+                        {
+                            icons.push((rel.to_ascii_lowercase(), href));
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+
+        // A plain `icon` link, then Apple's touch icon
+        let icon = ["icon", "apple-touch-icon"].iter().find_map(|wanted| {
+            icons
+                .iter()
+                .find(|(rel, _)| rel.split_whitespace().any(|r| r == *wanted))
+                .and_then(|(_, href)| base.join(href).ok())
+        });
+
+        Self {
+            title: clean(title.map(|t| unescape(&t))).or(clean(og_title)),
+            description: clean(description).or(clean(og_description)),
+            icon,
+        }
+    }
+}
+
+/// Collapses whitespace; blank text becomes `None`.
+fn clean(text: Option<String>) -> Option<String> {
+    let text = text?.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::stub::StubServer;
+    use tempfile::tempdir;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+        <html><head>
+        <meta charset="utf-8">
+        <title>
+            Rust &amp; Friends
+        </title>
+        <meta name="Description" content="A page about &quot;Rust&quot;">
+        <link rel="apple-touch-icon" href="/touch.png">
+        <link rel="shortcut icon" href="static/icon.png">
+        </head><body><title>Not this</title></body></html>"#;
+
+    fn html(body: &str) -> (u16, Vec<(&'static str, String)>, Vec<u8>) {
+        (
+            200,
+            vec![("Content-Type", "text/html; charset=utf-8".to_string())],
+            body.as_bytes().to_vec(),
+        )
+    }
+
+    fn png() -> (u16, Vec<(&'static str, String)>, Vec<u8>) {
+        (
+            200,
+            vec![("Content-Type", "image/png".to_string())],
+            b"\x89PNG".to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_parse_head() {
+        let url = Url::parse("https://example.com/docs/page").unwrap();
+        let head = Head::parse(PAGE, &url);
+        assert_eq!(head.title.as_deref(), Some("Rust & Friends"));
+        assert_eq!(head.description.as_deref(), Some("A page about \"Rust\""));
+        assert_eq!(
+            head.icon.unwrap().as_str(),
+            "https://example.com/docs/static/icon.png"
+        );
+
+        let head = Head::parse(
+            r#"<head><base href="https://cdn.example/"><meta property="og:title" content="OG">
+            <meta property="og:description" content="From OG"><link rel="apple-touch-icon" href="t.png">"#,
+            &url,
+        );
+        assert_eq!(head.title.as_deref(), Some("OG"));
+        assert_eq!(head.description.as_deref(), Some("From OG"));
+        assert_eq!(head.icon.unwrap().as_str(), "https://cdn.example/t.png");
+
+        let head = Head::parse("<title>  </title>", &url);
+        assert_eq!(head.title, None);
+        assert_eq!(head.icon, None);
+    }
+
+    #[test]
+    fn test_fill_blank_fields_and_cache_favicon() {
+        let server = StubServer::start(|_, path| match path {
+            "/old" => (301, vec![("Location", "/docs/page".to_string())], vec![]),
+            "/docs/page" => html(PAGE),
+            "/docs/static/icon.png" => png(),
+            _ => (404, vec![], vec![]),
+        });
+        let dir = tempdir().unwrap();
+        let fetcher = MetadataFetcher::with_favicon_dir(dir.path().to_path_buf());
+
+        // Relative links resolve against the page the redirect ended on
+        let mut input = BookmarkInput::new(server.url("/old")).with_description("Mine");
+        let metadata = fetcher.fill(&mut input).unwrap();
+        assert_eq!(input.title.as_deref(), Some("Rust & Friends"));
+        assert_eq!(input.description.as_deref(), Some("Mine"));
+        let favicon = metadata.favicon.unwrap();
+        assert!(favicon.starts_with(dir.path()));
+        assert_eq!(favicon.extension().unwrap(), "png");
+        assert_eq!(std::fs::read(&favicon).unwrap(), b"\x89PNG");
+
+        // Nothing left to fill and the favicon is cached: no request
+        let before = server.requests.lock().unwrap().len();
+        let mut input = BookmarkInput::new(server.url("/docs/page"))
+            .with_title("Given")
+            .with_description("Given");
+        let metadata = fetcher.fill(&mut input).unwrap();
+        assert_eq!(metadata.favicon, Some(favicon.clone()));
+        assert_eq!(server.requests.lock().unwrap().len(), before);
+
+        // An immutable title stays blank; the cached favicon is reused
+        let mut input = BookmarkInput::new(server.url("/docs/page")).with_immutable_title(true);
+        fetcher.fill(&mut input).unwrap();
+        assert_eq!(input.title, None);
+        assert_eq!(input.description.as_deref(), Some("A page about \"Rust\""));
+        let requests = server.requests.lock().unwrap();
+        assert!(!requests[before..]
+            .iter()
+            .any(|(_, path, _)| path.ends_with(".png")));
+    }
+
+    #[test]
+    fn test_favicon_falls_back_to_site_root() {
+        let server = StubServer::start(|_, path| match path {
+            "/" => html("<title>Home</title>"),
+            "/favicon.ico" => (200, vec![], b"ICO".to_vec()),
+            _ => (404, vec![], vec![]),
+        });
+        let dir = tempdir().unwrap();
+        let fetcher = MetadataFetcher::with_favicon_dir(dir.path().to_path_buf());
+
+        let metadata = fetcher.fetch(&server.url("/")).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Home"));
+        assert_eq!(metadata.favicon.unwrap().extension().unwrap(), "ico");
+    }
+
+    #[test]
+    fn test_errors_and_timeouts() {
+        let server = StubServer::start(|_, path| match path {
+            "/slow" => {
+                std::thread::sleep(Duration::from_millis(500));
+                html("<title>Late</title>")
+            }
+            "/empty" => (200, vec![("Content-Type", "text/html".to_string())], vec![]),
+            _ => (404, vec![], vec![]),
+        });
+        let dir = tempdir().unwrap();
+        let fetcher = MetadataFetcher::with_favicon_dir(dir.path().to_path_buf())
+            .with_timeout(Duration::from_millis(100));
+
+        assert!(matches!(
+            fetcher.fetch(&server.url("/missing")),
+            Err(BookmarkError::Fetch(_))
+        ));
+        assert!(matches!(
+            fetcher.fetch(&server.url("/slow")),
+            Err(BookmarkError::Fetch(_))
+        ));
+
+        // Nothing to fill from an empty page, and no favicon on the site
+        let mut input = BookmarkInput::new(server.url("/empty"));
+        let metadata = fetcher.fill(&mut input).unwrap();
+        assert_eq!(metadata, PageMetadata::default());
+        assert_eq!(input.title, None);
+
+        // Non-web URLs are left alone
+        let mut input = BookmarkInput::new("file:///tmp/notes.txt");
+        assert_eq!(fetcher.fill(&mut input).unwrap(), PageMetadata::default());
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bookmark {
@@ -7,6 +8,27 @@ pub struct Bookmark {
     pub title: String,
     pub tags: Vec<String>,
     pub description: String,
+    /// Unix timestamp of when the bookmark was added, if known.
+    pub added_at: Option<i64>,
+    /// buku's immutable-title flag: the title was set by hand and is never
+    /// replaced by a fetched page title.
+    pub immutable_title: bool,
+    /// Shortcut typed in the launcher to open the bookmark; see
+    /// [`crate::BookmarkStore::resolve_keyword`].
+    pub keyword: Option<String>,
+    /// Folder containing the bookmark, `None` for the top level.
+    pub folder_id: Option<i32>,
+}
+
+/// A bookmark folder. Folders nest through `parent_id` and are ordered by
+/// `position` within their parent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Folder {
+    pub id: i32,
+    /// `None` for folders at the top level.
+    pub parent_id: Option<i32>,
+    pub name: String,
+    pub position: i32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,12 +37,238 @@ pub struct Tag {
     pub count: i32,
 }
 
+/// Outcome of importing bookmarks from another browser or file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub added: u32,
+    /// URLs that were already in the database.
+    pub skipped: u32,
+    pub failed: u32,
+}
+
+/// Result ordering for [`crate::BookmarkStore::search_ordered`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkOrder {
+    /// Newest first when listing, best match first when searching.
+    #[default]
+    Default,
+    /// Most frequently and recently opened first.
+    Frecency,
+}
+
+/// Sort key for [`crate::BookmarkStore::list_page`] and
+/// [`crate::BookmarkStore::search_page`]. Each key has a natural direction,
+/// which [`PageRequest::reverse`] flips.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BookmarkSort {
+    /// Best match first when searching, newest first when listing.
+    #[default]
+    Relevance,
+    /// Newest first.
+    Created,
+    /// A to Z, ignoring case.
+    Title,
+    /// A to Z.
+    Url,
+    /// Most recently opened first; never opened last.
+    LastOpened,
+    /// Most frequently and recently opened first.
+    Frecency,
+}
+
+/// Which page of results to return.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageRequest {
+    pub sort: BookmarkSort,
+    pub reverse: bool,
+    /// Page size; `None` returns every remaining result and `Some(0)` only
+    /// the total.
+    pub limit: Option<u32>,
+    /// [`BookmarkPage::next_cursor`] of the previous page, `None` for the
+    /// first page.
+    pub cursor: Option<String>,
+}
+
+impl PageRequest {
+    pub fn new(sort: BookmarkSort) -> Self {
+        Self {
+            sort,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_cursor(mut self, cursor: impl Into<String>) -> Self {
+        self.cursor = Some(cursor.into());
+        self
+    }
+}
+
+/// One page of bookmarks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookmarkPage {
+    pub bookmarks: Vec<Bookmark>,
+    /// Number of bookmarks matching across all pages.
+    pub total: usize,
+    /// Opaque cursor for the following page, `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// A keyword bookmark matched by launcher input, with the URL to open.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeywordMatch {
+    pub bookmark: Bookmark,
+    /// The bookmark URL with its placeholders replaced by the input's
+    /// argument.
+    pub url: String,
+}
+
+/// A deleted bookmark waiting in the trash. `bookmark.id` is the id it had
+/// before deletion, which it gets back on restore unless taken meanwhile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedBookmark {
+    #[serde(flatten)]
+    pub bookmark: Bookmark,
+    /// Unix timestamp of the deletion.
+    pub deleted_at: i64,
+}
+
+/// Kind of change recorded in the undo journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UndoAction {
+    Delete,
+    RenameTag,
+    MergeTags,
+    DeleteTag,
+}
+
+impl UndoAction {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Delete => "delete",
+            Self::RenameTag => "rename_tag",
+            Self::MergeTags => "merge_tags",
+            Self::DeleteTag => "delete_tag",
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "delete" => Some(Self::Delete),
+            "rename_tag" => Some(Self::RenameTag),
+            "merge_tags" => Some(Self::MergeTags),
+            "delete_tag" => Some(Self::DeleteTag),
+            _ => None,
+        }
+    }
+}
+
+/// A change that [`crate::BookmarkStore::undo`] can revert.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoEntry {
+    pub id: i64,
+    pub action: UndoAction,
+    /// Title or URL of the deleted bookmark, or the tag(s) that changed.
+    pub target: String,
+    pub created_at: i64,
+}
+
+/// Result of checking whether a bookmark's URL still resolves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkStatus {
+    pub bookmark_id: i32,
+    /// HTTP status of the final response, `None` if no response came back.
+    pub status: Option<u16>,
+    /// Where the URL redirects to, if it redirects.
+    pub redirect_url: Option<String>,
+    /// Network or protocol error when there was no response.
+    pub error: Option<String>,
+    /// Unix timestamp of the check.
+    pub checked_at: i64,
+}
+
+impl LinkStatus {
+    /// The server is unreachable or answered with an error status.
+    pub fn is_broken(&self) -> bool {
+        self.status.is_none_or(|status| status >= 400)
+    }
+}
+
+/// Bookmarks changed in the database by another connection, as found by
+/// [`crate::DatabaseWatcher::changes`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookmarkChanges {
+    pub added: Vec<i32>,
+    pub updated: Vec<i32>,
+    pub removed: Vec<i32>,
+    /// Folders were created, renamed, moved or deleted.
+    pub folders: bool,
+}
+
+/// Details read from a web page for a new bookmark.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Cached copy of the site's favicon.
+    pub favicon: Option<PathBuf>,
+}
+
+/// Progress reported after each link checked by
+/// [`crate::BookmarkStore::check_links`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkCheckProgress {
+    pub checked: usize,
+    pub total: usize,
+    pub status: LinkStatus,
+}
+
+/// Outcome of [`crate::GitSync::sync`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncReport {
+    /// Bookmarks added, updated and removed locally by the merge.
+    pub added: u32,
+    pub updated: u32,
+    pub removed: u32,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// A bookmark changed on both sides of a sync. The local version is kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub url: String,
+    /// Fields both sides changed to different values.
+    pub fields: Vec<String>,
+    /// One side deleted the bookmark while the other edited it.
+    pub deleted: bool,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct BookmarkInput {
     pub url: String,
     pub title: Option<String>,
     pub tags: Option<Vec<String>>,
     pub description: Option<String>,
+    /// Unix timestamp to record as the creation date; defaults to now.
+    pub added_at: Option<i64>,
+    pub immutable_title: bool,
+    pub keyword: Option<String>,
+    /// Names of the folders leading to the bookmark's folder, created as
+    /// needed. Empty for the top level.
+    pub folder: Vec<String>,
 }
 
 impl BookmarkInput {
@@ -45,4 +293,51 @@ impl BookmarkInput {
         self.description = Some(description.into());
         self
     }
+
+    pub fn with_added_at(mut self, added_at: i64) -> Self {
+        self.added_at = Some(added_at);
+        self
+    }
+
+    pub fn with_immutable_title(mut self, immutable: bool) -> Self {
+        self.immutable_title = immutable;
+        self
+    }
+
+    pub fn with_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.keyword = Some(keyword.into());
+        self
+    }
+
+    pub fn with_folder(mut self, path: Vec<String>) -> Self {
+        self.folder = path;
+        self
+    }
+
+    /// Also tags the bookmark with its folder path joined by `/`, e.g.
+    /// `Work/Infra`, so browser folders stay searchable as tags. Commas
+    /// separate tags in the database, so they become spaces.
+    pub(crate) fn with_folder_tag(mut self) -> Self {
+        let tag = self
+            .folder
+            .iter()
+            .map(|name| {
+                name.split(',')
+                    .map(str::trim)
+                    .filter(|part| !part.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if !tag.is_empty() {
+            let tags = self.tags.get_or_insert_with(Vec::new);
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+                tags.push(tag);
+            }
+        }
+        self
+    }
 }
@@ -0,0 +1,382 @@
+//! Reader and writer for the Netscape bookmark file format (`bookmarks.html`),
+//! the de-facto interchange format understood by every major browser.
+//!
+//! Folders map to bookmark folders and tags to the `TAGS` attribute, so an
+//! export followed by an import yields the same URL, title, folder, tags,
+//! description and `ADD_DATE`. On import the folder path is also added as a
+//! hierarchical tag (`Work > Infra` becomes `Work/Infra`). Keywords use the `SHORTCUTURL` attribute, as
+//! in Firefox exports.
+
+use crate::html::{attribute, tokenize, unescape, Token};
+use crate::models::{Bookmark, BookmarkInput, Folder};
+
+const HEADER: &str = "<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn split_tags(tags: &str) -> impl Iterator<Item = String> + '_ {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+}
+
+/// Where text tokens are currently being collected.
+enum Capture {
+    None,
+    FolderName(String),
+    Title(BookmarkInput, String),
+    Description(String),
+}
+
+fn set_description(inputs: &mut [BookmarkInput], desc: &str) {
+    let desc = unescape(desc.trim());
+    if let Some(last) = inputs.last_mut().filter(|_| !desc.is_empty()) {
+        last.description = Some(desc);
+    }
+}
+
+pub(crate) fn parse(html: &str) -> Vec<BookmarkInput> {
+    let mut inputs: Vec<BookmarkInput> = Vec::new();
+    // One entry per open <DL>; `None` for the unnamed top-level list
+    let mut folders: Vec<Option<String>> = Vec::new();
+    let mut pending_folder: Option<String> = None;
+    let mut capture = Capture::None;
+    // Whether the last element was a bookmark, which a <DD> then describes,
+    // rather than a folder, whose description is dropped
+    let mut last_was_anchor = false;
+
+    for token in tokenize(html) {
+        match token {
+            Token::Text(text) => match &mut capture {
+                Capture::FolderName(buf) | Capture::Title(_, buf) | Capture::Description(buf) => {
+                    buf.push_str(text)
+                }
+                Capture::None => {}
+            },
+            Token::Tag {
+                name,
+                closing,
+                attrs,
+            } => {
+                // A <DD> description runs until the next tag
+                if let Capture::Description(desc) = &capture {
+                    if last_was_anchor {
+                        set_description(&mut inputs, desc);
+                    }
+                    capture = Capture::None;
+                    last_was_anchor = false;
+                }
+
+                match (name.as_str(), closing) {
+                    ("H3", false) => {
+                        capture = Capture::FolderName(String::new());
+                        last_was_anchor = false;
+                    }
+                    ("H3", true) => {
+                        if let Capture::FolderName(folder) =
+                            std::mem::replace(&mut capture, Capture::None)
+                        {
+                            let name = unescape(folder.trim());
+                            pending_folder = Some(name).filter(|n| !n.is_empty());
+                        }
+                    }
+                    ("DL", false) => {
+                        folders.push(pending_folder.take());
+                        last_was_anchor = false;
+                    }
+                    ("DL", true) => {
+                        folders.pop();
+                        last_was_anchor = false;
+                    }
+                    ("A", false) => {
+                        let Some(href) = attribute(attrs, "HREF") else {
+                            continue;
+                        };
+
+                        let path: Vec<String> = folders.iter().flatten().cloned().collect();
+                        let mut input = BookmarkInput::new(href).with_folder(path);
+                        let tags: Vec<String> = attribute(attrs, "TAGS")
+                            .map(|t| split_tags(&t).collect())
+                            .unwrap_or_default();
+
+                        if !tags.is_empty() {
+                            input = input.with_tags(tags);
+                        }
+                        input = input.with_folder_tag();
+                        if let Some(added_at) =
+                            attribute(attrs, "ADD_DATE").and_then(|d| d.parse().ok())
+                        {
+                            input = input.with_added_at(added_at);
+                        }
+                        if let Some(keyword) = attribute(attrs, "SHORTCUTURL") {
+                            input = input.with_keyword(keyword);
+                        }
+
+                        capture = Capture::Title(input, String::new());
+                    }
+                    ("A", true) => {
+                        if let Capture::Title(input, title) =
+                            std::mem::replace(&mut capture, Capture::None)
+                        {
+                            inputs.push(input.with_title(unescape(title.trim())));
+                            last_was_anchor = true;
+                        }
+                    }
+                    ("DD", false) => capture = Capture::Description(String::new()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    if let Capture::Description(desc) = &capture {
+        if last_was_anchor {
+            set_description(&mut inputs, desc);
+        }
+    }
+
+    inputs
+}
+
+/// A folder of the exported tree with its bookmarks.
+struct Node<'a> {
+    name: String,
+    children: Vec<Node<'a>>,
+    bookmarks: Vec<&'a Bookmark>,
+}
+
+impl<'a> Node<'a> {
+    /// Builds the subtree below `parent`. Bookmarks whose folder is unknown
+    /// are placed at the top level.
+    fn build(
+        parent: Option<i32>,
+        name: String,
+        folders: &[Folder],
+        bookmarks: &'a [Bookmark],
+    ) -> Self {
+        let mut children: Vec<&Folder> = folders.iter().filter(|f| f.parent_id == parent).collect();
+        children.sort_by_key(|f| f.position);
+
+        let folder_of = |b: &Bookmark| b.folder_id.filter(|id| folders.iter().any(|f| f.id == *id));
+
+        Self {
+            name,
+            children: children
+                .into_iter()
+                .map(|f| Self::build(Some(f.id), f.name.clone(), folders, bookmarks))
+                .collect(),
+            bookmarks: bookmarks
+                .iter()
+                .filter(|b| folder_of(b) == parent)
+                .collect(),
+        }
+    }
+
+    fn write(&self, out: &mut String, depth: usize) {
+        let indent = "    ".repeat(depth);
+
+        for child in &self.children {
+            out.push_str(&format!("{}<DT><H3>{}</H3>\n", indent, escape(&child.name)));
+            out.push_str(&format!("{}<DL><p>\n", indent));
+            child.write(out, depth + 1);
+            out.push_str(&format!("{}</DL><p>\n", indent));
+        }
+
+        for bookmark in &self.bookmarks {
+            out.push_str(&format!(
+                "{}<DT><A HREF=\"{}\"",
+                indent,
+                escape(&bookmark.url)
+            ));
+            if let Some(added_at) = bookmark.added_at {
+                out.push_str(&format!(" ADD_DATE=\"{}\"", added_at));
+            }
+            if !bookmark.tags.is_empty() {
+                out.push_str(&format!(" TAGS=\"{}\"", escape(&bookmark.tags.join(","))));
+            }
+            if let Some(keyword) = &bookmark.keyword {
+                out.push_str(&format!(" SHORTCUTURL=\"{}\"", escape(keyword)));
+            }
+            out.push_str(&format!(">{}</A>\n", escape(&bookmark.title)));
+
+            if !bookmark.description.is_empty() {
+                out.push_str(&format!(
+                    "{}<DD>{}\n",
+                    indent,
+                    escape(&bookmark.description)
+                ));
+            }
+        }
+    }
+}
+
+/// Writes `bookmarks` inside the tree of `folders`, keeping the order of
+/// both within each folder.
+pub(crate) fn write(folders: &[Folder], bookmarks: &[Bookmark]) -> String {
+    let root = Node::build(None, String::new(), folders, bookmarks);
+
+    let mut out = String::from(HEADER);
+    out.push_str("<DL><p>\n");
+    root.write(&mut out, 1);
+    out.push_str("</DL><p>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file. -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1600000000" PERSONAL_TOOLBAR_FOLDER="true">Toolbar</H3>
+    <DL><p>
+        <DT><H3>Work</H3>
+        <DL><p>
+            <DT><A HREF="https://grafana.example/?a=1&amp;b=2" ADD_DATE="1700000000" TAGS="ops,monitoring">Grafana &amp; Co</A>
+            <DD>Dashboards
+for infra
+        </DL><p>
+    </DL><p>
+    <DT><a href='https://docs.rs' add_date=1650000000>Docs</a>
+</DL><p>
+"#;
+
+    #[test]
+    fn test_parse_folders_tags_dates_and_descriptions() {
+        let inputs = parse(FIXTURE);
+        assert_eq!(inputs.len(), 2);
+
+        let grafana = &inputs[0];
+        assert_eq!(grafana.url, "https://grafana.example/?a=1&b=2");
+        assert_eq!(grafana.title.as_deref(), Some("Grafana & Co"));
+        assert_eq!(
+            grafana.tags,
+            Some(vec![
+                "ops".to_string(),
+                "monitoring".to_string(),
+                "Toolbar/Work".to_string()
+            ])
+        );
+        assert_eq!(grafana.folder, vec!["Toolbar", "Work"]);
+        assert_eq!(
+            grafana.description.as_deref(),
+            Some("Dashboards\nfor infra")
+        );
+        assert_eq!(grafana.added_at, Some(1_700_000_000));
+
+        let docs = &inputs[1];
+        assert_eq!(docs.url, "https://docs.rs");
+        assert_eq!(docs.tags, None);
+        assert!(docs.folder.is_empty());
+        assert_eq!(docs.description, None);
+        assert_eq!(docs.added_at, Some(1_650_000_000));
+    }
+
+    #[test]
+    fn test_parse_ignores_folder_descriptions() {
+        let inputs = parse(
+            r#"<DL><p>
+    <DT><A HREF="https://a.example">A</A>
+    <DD>About A
+    <DT><H3>Folder</H3>
+    <DD>About the folder
+    <DL><p>
+        <DT><A HREF="https://b.example">B</A>
+    </DL><p>
+    <DT><H3>Last</H3>
+    <DD>About the last folder
+</DL><p>
+"#,
+        );
+
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].description.as_deref(), Some("About A"));
+        assert_eq!(inputs[1].description, None);
+    }
+
+    #[test]
+    fn test_write_then_parse_round_trips() {
+        let bookmarks = vec![
+            Bookmark {
+                id: 1,
+                url: "https://example.com/?q=\"a\"&b".to_string(),
+                title: "<Example>".to_string(),
+                tags: vec!["Work & Co/Infra".to_string(), "k8s".to_string()],
+                description: "multi\nline".to_string(),
+                added_at: Some(1_700_000_000),
+                immutable_title: false,
+                keyword: Some("ex".to_string()),
+                folder_id: Some(11),
+            },
+            Bookmark {
+                id: 2,
+                url: "https://untagged.example".to_string(),
+                title: String::new(),
+                tags: vec![],
+                description: String::new(),
+                added_at: None,
+                immutable_title: false,
+                keyword: None,
+                folder_id: None,
+            },
+        ];
+        let folders = vec![
+            Folder {
+                id: 10,
+                parent_id: None,
+                name: "Work & Co".to_string(),
+                position: 0,
+            },
+            Folder {
+                id: 11,
+                parent_id: Some(10),
+                name: "Infra".to_string(),
+                position: 0,
+            },
+            Folder {
+                id: 12,
+                parent_id: None,
+                name: "Empty".to_string(),
+                position: 1,
+            },
+        ];
+
+        let html = write(&folders, &bookmarks);
+        assert!(html.contains("<DT><H3>Work &amp; Co</H3>"));
+        assert!(html.contains("<DT><H3>Empty</H3>"));
+
+        let parsed = parse(&html);
+        assert_eq!(parsed.len(), bookmarks.len());
+
+        for (input, original) in parsed.iter().zip(&bookmarks) {
+            assert_eq!(input.url, original.url);
+            assert_eq!(input.title.as_deref().unwrap_or(""), original.title);
+            assert_eq!(input.tags.clone().unwrap_or_default(), original.tags);
+            assert_eq!(
+                input.description.as_deref().unwrap_or(""),
+                original.description
+            );
+            assert_eq!(input.added_at, original.added_at);
+            assert_eq!(input.keyword, original.keyword);
+        }
+        assert_eq!(parsed[0].folder, vec!["Work & Co", "Infra"]);
+        assert!(parsed[1].folder.is_empty());
+    }
+}
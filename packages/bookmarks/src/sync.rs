@@ -0,0 +1,739 @@
+//! Bookmark sync between machines through a git repository.
+//!
+//! Each machine keeps a clone of a shared repository holding one file,
+//! `bookmarks.jsonl`: one JSON record per line, sorted by URL, so diffs and
+//! history stay readable. A sync commits the local bookmarks, merges what
+//! other machines pushed record by record (keyed by URL, field by field
+//! against the common ancestor), applies the result to the database and
+//! pushes it back.
+
+use crate::db::BookmarkStore;
+use crate::error::BookmarkError;
+use crate::models::{BookmarkInput, Folder, ImportReport, SyncConflict, SyncReport};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const FILE_NAME: &str = "bookmarks.jsonl";
+const REMOTE: &str = "origin";
+
+/// Sync commits are written by MRunner, not by whoever runs it.
+const COMMITTER: [(&str, &str); 4] = [
+    ("GIT_AUTHOR_NAME", "MRunner"),
+    ("GIT_AUTHOR_EMAIL", "mrunner@localhost"),
+    ("GIT_COMMITTER_NAME", "MRunner"),
+    ("GIT_COMMITTER_EMAIL", "mrunner@localhost"),
+];
+
+/// Nobody sees a terminal to answer git's questions, so a missing credential
+/// or unknown host key fails the sync instead of hanging it.
+const NO_PROMPT: [(&str, &str); 2] = [
+    ("GIT_TERMINAL_PROMPT", "0"),
+    ("GIT_SSH_COMMAND", "ssh -o BatchMode=yes"),
+];
+
+/// A git command still running after this long is killed, so an unreachable
+/// remote fails the sync.
+const GIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A bookmark as stored in the sync file. Ids are local to each database,
+/// so bookmarks are identified by URL and folders by their path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+struct Record {
+    url: String,
+    title: String,
+    /// Sorted, so tag order never shows up as a change.
+    tags: Vec<String>,
+    description: String,
+    added_at: Option<i64>,
+    immutable_title: bool,
+    keyword: Option<String>,
+    folder: Vec<String>,
+}
+
+type Records = BTreeMap<String, Record>;
+
+fn parse_records(text: &str) -> Result<Records, BookmarkError> {
+    let mut records = Records::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let record: Record = serde_json::from_str(line)
+            .map_err(|e| BookmarkError::InvalidFile(format!("{}: {}", FILE_NAME, e)))?;
+        records.insert(record.url.clone(), record);
+    }
+    Ok(records)
+}
+
+fn format_records(records: &Records) -> String {
+    records
+        .values()
+        .filter_map(|record| serde_json::to_string(record).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+/// Syncs a [`BookmarkStore`] through a git repository cloned at `dir`.
+#[derive(Debug, Clone)]
+pub struct GitSync {
+    dir: PathBuf,
+    remote: Option<String>,
+    branch: String,
+}
+
+impl GitSync {
+    /// Keeps the working copy at `dir`, pushing to and pulling from `remote`
+    /// when given. Without a remote, syncing only records local history.
+    pub fn new(dir: PathBuf, remote: Option<String>) -> Self {
+        Self {
+            dir,
+            remote,
+            branch: "main".to_string(),
+        }
+    }
+
+    /// Keeps the working copy under MRunner's data directory.
+    pub fn in_data_dir(remote: Option<String>) -> Result<Self, BookmarkError> {
+        Ok(Self::new(BookmarkStore::data_dir()?.join("sync"), remote))
+    }
+
+    /// Branch to sync on, `main` by default.
+    pub fn with_branch(mut self, branch: impl Into<String>) -> Self {
+        self.branch = branch.into();
+        self
+    }
+
+    /// Merges the bookmarks in `store` with those pushed by other machines
+    /// and pushes the result.
+    ///
+    /// Changes made on only one side are taken as they are. When both sides
+    /// changed the same field of a bookmark, the local value is kept and the
+    /// conflict reported; when one side deleted a bookmark the other edited,
+    /// the edited bookmark is kept.
+    pub fn sync(&self, store: &BookmarkStore) -> Result<SyncReport, BookmarkError> {
+        self.ensure_repo()?;
+        if self.remote.is_some() {
+            self.git(&["fetch", "-q", REMOTE])?;
+        }
+
+        // Commit local changes first, so the merge below is between commits
+        let (ours, ids) = export(store)?;
+        let head = self.commit(&ours, &[], "Update bookmarks")?;
+
+        let mut report = SyncReport::default();
+        let remote_ref = format!("refs/remotes/{}/{}", REMOTE, self.branch);
+        if let Some(theirs_commit) = self.rev_parse(&remote_ref)? {
+            if !self.is_ancestor(&theirs_commit, &head)? {
+                let base = match self.merge_base(&head, &theirs_commit)? {
+                    Some(base) => self.records_at(&base)?,
+                    None => Records::new(),
+                };
+                let theirs = self.records_at(&theirs_commit)?;
+
+                let merged = merge(&base, &ours, &theirs, &mut report.conflicts);
+                let tx = store.transaction()?;
+                apply(store, &ours, &ids, &merged, &mut report)?;
+                tx.commit()?;
+
+                let (result, _) = export(store)?;
+                self.commit(&result, &[&theirs_commit], "Merge bookmarks")?;
+            }
+        }
+
+        if self.remote.is_some() {
+            let refspec = format!("HEAD:refs/heads/{}", self.branch);
+            self.git(&["push", "-q", REMOTE, &refspec])?;
+        }
+
+        Ok(report)
+    }
+
+    fn git(&self, args: &[&str]) -> Result<Output, BookmarkError> {
+        let output = self.git_unchecked(args)?;
+        if !output.status.success() {
+            return Err(BookmarkError::Sync(format!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output)
+    }
+
+    fn git_unchecked(&self, args: &[&str]) -> Result<Output, BookmarkError> {
+        let failed = |e: std::io::Error| BookmarkError::Sync(format!("Failed to run git: {}", e));
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .envs(COMMITTER)
+            .envs(NO_PROMPT)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(failed)?;
+
+        // Both pipes close when git exits; reading them on their own threads
+        // also keeps a large output from filling one and stalling git
+        let (done, finished) = mpsc::channel();
+        let drain = |pipe: Option<Box<dyn Read + Send>>, stream: usize| {
+            let done = done.clone();
+            thread::spawn(move || {
+                let mut buf = Vec::new();
+                if let Some(mut pipe) = pipe {
+                    let _ = pipe.read_to_end(&mut buf);
+                }
+                let _ = done.send((stream, buf));
+            });
+        };
+        drain(child.stdout.take().map(|p| Box::new(p) as _), 0);
+        drain(child.stderr.take().map(|p| Box::new(p) as _), 1);
+
+        let deadline = Instant::now() + GIT_TIMEOUT;
+        let mut output = [Vec::new(), Vec::new()];
+        for _ in 0..output.len() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match finished.recv_timeout(remaining) {
+                Ok((stream, buf)) => output[stream] = buf,
+                Err(_) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(BookmarkError::Sync(format!(
+                        "git {} timed out after {} seconds",
+                        args.first().unwrap_or(&""),
+                        GIT_TIMEOUT.as_secs()
+                    )));
+                }
+            }
+        }
+
+        let [stdout, stderr] = output;
+        Ok(Output {
+            status: child.wait().map_err(failed)?,
+            stdout,
+            stderr,
+        })
+    }
+
+    fn stdout(output: Output) -> String {
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// Creates the working copy on first use and points it at the remote.
+    fn ensure_repo(&self) -> Result<(), BookmarkError> {
+        if !self.dir.join(".git").exists() {
+            std::fs::create_dir_all(&self.dir)?;
+            self.git(&["init", "-q", "-b", &self.branch])?;
+        }
+
+        if let Some(remote) = &self.remote {
+            let current = self.git_unchecked(&["remote", "get-url", REMOTE])?;
+            // `--` keeps a remote starting with `-` from being read as an option
+            if !current.status.success() {
+                self.git(&["remote", "add", "--", REMOTE, remote])?;
+            } else if Self::stdout(current) != *remote {
+                self.git(&["remote", "set-url", "--", REMOTE, remote])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn rev_parse(&self, rev: &str) -> Result<Option<String>, BookmarkError> {
+        let output = self.git_unchecked(&["rev-parse", "--verify", "-q", rev])?;
+        Ok(output.status.success().then(|| Self::stdout(output)))
+    }
+
+    fn is_ancestor(&self, ancestor: &str, of: &str) -> Result<bool, BookmarkError> {
+        let output = self.git_unchecked(&["merge-base", "--is-ancestor", ancestor, of])?;
+        Ok(output.status.success())
+    }
+
+    fn merge_base(&self, a: &str, b: &str) -> Result<Option<String>, BookmarkError> {
+        let output = self.git_unchecked(&["merge-base", a, b])?;
+        Ok(output.status.success().then(|| Self::stdout(output)))
+    }
+
+    fn records_at(&self, commit: &str) -> Result<Records, BookmarkError> {
+        let output = self.git_unchecked(&["show", &format!("{}:{}", commit, FILE_NAME)])?;
+        if !output.status.success() {
+            return Ok(Records::new());
+        }
+        parse_records(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Commits `records` on top of `HEAD` and `extra_parents`, returning the
+    /// new `HEAD`. Nothing is committed when the file is unchanged and there
+    /// is nothing to merge.
+    fn commit(
+        &self,
+        records: &Records,
+        extra_parents: &[&str],
+        message: &str,
+    ) -> Result<String, BookmarkError> {
+        std::fs::write(self.dir.join(FILE_NAME), format_records(records))?;
+        self.git(&["add", FILE_NAME])?;
+        let tree = Self::stdout(self.git(&["write-tree"])?);
+
+        let head = self.rev_parse("HEAD")?;
+        if extra_parents.is_empty() {
+            if let Some(head) = &head {
+                if self.rev_parse(&format!("{}^{{tree}}", head))?.as_deref() == Some(&tree) {
+                    return Ok(head.clone());
+                }
+            }
+        }
+
+        let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+        for parent in head
+            .iter()
+            .map(String::as_str)
+            .chain(extra_parents.iter().copied())
+        {
+            args.extend(["-p", parent]);
+        }
+        let commit = Self::stdout(self.git(&args)?);
+        self.git(&[
+            "update-ref",
+            &format!("refs/heads/{}", self.branch),
+            &commit,
+        ])?;
+        self.git(&[
+            "symbolic-ref",
+            "HEAD",
+            &format!("refs/heads/{}", self.branch),
+        ])?;
+        Ok(commit)
+    }
+}
+
+/// The store's bookmarks as sync records, with the local id of each URL.
+fn export(store: &BookmarkStore) -> Result<(Records, HashMap<String, i32>), BookmarkError> {
+    let folders: HashMap<i32, Folder> = store
+        .list_folders()?
+        .into_iter()
+        .map(|folder| (folder.id, folder))
+        .collect();
+    let path = |mut id: Option<i32>| {
+        let mut path = Vec::new();
+        while let Some(folder) = id.and_then(|id| folders.get(&id)) {
+            path.push(folder.name.clone());
+            id = folder.parent_id;
+        }
+        path.reverse();
+        path
+    };
+
+    let mut records = Records::new();
+    let mut ids = HashMap::new();
+    for bookmark in store.list(None)? {
+        let mut tags = bookmark.tags;
+        tags.sort();
+        ids.insert(bookmark.url.clone(), bookmark.id);
+        records.insert(
+            bookmark.url.clone(),
+            Record {
+                folder: path(bookmark.folder_id),
+                url: bookmark.url,
+                title: bookmark.title,
+                tags,
+                description: bookmark.description,
+                added_at: bookmark.added_at,
+                immutable_title: bookmark.immutable_title,
+                keyword: bookmark.keyword,
+            },
+        );
+    }
+    Ok((records, ids))
+}
+
+/// Picks one side's value for a field that both sides may have changed
+/// since `base`. `None` for a conflict.
+fn pick<'a, T: PartialEq>(base: Option<&T>, ours: &'a T, theirs: &'a T) -> Option<&'a T> {
+    if ours == theirs || base == Some(theirs) {
+        Some(ours)
+    } else if base == Some(ours) {
+        Some(theirs)
+    } else {
+        None
+    }
+}
+
+/// Merges a record present on both sides, keeping our value for fields that
+/// conflict and naming them in `fields`.
+fn merge_record(base: Option<&Record>, ours: &Record, theirs: &Record) -> (Record, Vec<String>) {
+    let mut fields = Vec::new();
+    macro_rules! field {
+        ($name:ident) => {
+            match pick(base.map(|b| &b.$name), &ours.$name, &theirs.$name) {
+                Some(value) => value.clone(),
+                None => {
+                    fields.push(stringify!($name).to_string());
+                    ours.$name.clone()
+                }
+            }
+        };
+    }
+
+    let record = Record {
+        url: ours.url.clone(),
+        title: field!(title),
+        tags: field!(tags),
+        description: field!(description),
+        added_at: field!(added_at),
+        immutable_title: field!(immutable_title),
+        keyword: field!(keyword),
+        folder: field!(folder),
+    };
+    (record, fields)
+}
+
+/// Three-way merge of record sets keyed by URL.
+fn merge(
+    base: &Records,
+    ours: &Records,
+    theirs: &Records,
+    conflicts: &mut Vec<SyncConflict>,
+) -> Records {
+    let mut urls: Vec<&String> = ours
+        .keys()
+        .chain(theirs.keys())
+        .chain(base.keys())
+        .collect();
+    urls.sort();
+    urls.dedup();
+
+    let mut merged = Records::new();
+    for url in urls {
+        let base = base.get(url);
+        let record = match (ours.get(url), theirs.get(url)) {
+            (Some(ours), Some(theirs)) => {
+                let (record, fields) = merge_record(base, ours, theirs);
+                if !fields.is_empty() {
+                    conflicts.push(SyncConflict {
+                        url: url.clone(),
+                        fields,
+                        deleted: false,
+                    });
+                }
+                Some(record)
+            }
+            // Deleted on one side: stays deleted unless the other side edited it
+            (Some(kept), None) | (None, Some(kept)) => match base {
+                None => Some(kept.clone()),
+                Some(base) if base == kept => None,
+                Some(_) => {
+                    conflicts.push(SyncConflict {
+                        url: url.clone(),
+                        fields: Vec::new(),
+                        deleted: true,
+                    });
+                    Some(kept.clone())
+                }
+            },
+            (None, None) => None,
+        };
+        if let Some(record) = record {
+            merged.insert(url.clone(), record);
+        }
+    }
+    merged
+}
+
+/// Brings the store from `ours` to `merged`.
+fn apply(
+    store: &BookmarkStore,
+    ours: &Records,
+    ids: &HashMap<String, i32>,
+    merged: &Records,
+    report: &mut SyncReport,
+) -> Result<(), BookmarkError> {
+    for (url, id) in ids {
+        if !merged.contains_key(url) {
+            store.delete(*id)?;
+            report.removed += 1;
+        }
+    }
+
+    for (url, record) in merged {
+        match (ours.get(url), ids.get(url)) {
+            (Some(current), Some(&id)) if current != record => {
+                update(store, id, current, record)?;
+                report.updated += 1;
+            }
+            (Some(_), _) => {}
+            (None, _) => {
+                let mut input = BookmarkInput::new(&record.url)
+                    .with_title(&record.title)
+                    .with_tags(record.tags.clone())
+                    .with_description(&record.description)
+                    .with_immutable_title(record.immutable_title)
+                    .with_folder(record.folder.clone());
+                input.added_at = record.added_at;
+                input.keyword = record.keyword.clone();
+
+                let mut added = ImportReport::default();
+                store.import_one(input, &mut added);
+                report.added += added.added;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn update(
+    store: &BookmarkStore,
+    id: i32,
+    current: &Record,
+    record: &Record,
+) -> Result<(), BookmarkError> {
+    store.update(
+        id,
+        None,
+        Some(&record.title),
+        Some(&record.tags),
+        Some(&record.description),
+    )?;
+    if current.immutable_title != record.immutable_title {
+        store.set_immutable_title(id, record.immutable_title)?;
+    }
+    if current.keyword != record.keyword {
+        // A keyword taken by another bookmark here stays with that bookmark
+        let _ = store.set_keyword(id, record.keyword.as_deref());
+    }
+    if current.folder != record.folder {
+        let folder = store.ensure_folder(&record.folder)?;
+        store.move_bookmark(id, folder, None)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tempfile::{tempdir, TempDir};
+
+    /// A machine with its own database and working copy of `remote`.
+    struct Machine {
+        store: BookmarkStore,
+        sync: GitSync,
+        _dir: TempDir,
+    }
+
+    impl Machine {
+        fn new(remote: &Path) -> Self {
+            let dir = tempdir().unwrap();
+            Self {
+                store: BookmarkStore::with_path(dir.path().join("bookmarks.db")).unwrap(),
+                sync: GitSync::new(
+                    dir.path().join("sync"),
+                    Some(remote.to_string_lossy().into_owned()),
+                ),
+                _dir: dir,
+            }
+        }
+
+        fn sync(&self) -> SyncReport {
+            self.sync.sync(&self.store).unwrap()
+        }
+
+        fn id(&self, url: &str) -> i32 {
+            self.store.find_by_url(url, None).unwrap().unwrap()
+        }
+
+        fn title(&self, url: &str) -> String {
+            self.store.get(self.id(url)).unwrap().unwrap().title
+        }
+    }
+
+    fn bare_repo() -> TempDir {
+        let dir = tempdir().unwrap();
+        let status = Command::new("git")
+            .args(["init", "-q", "--bare"])
+            .arg(dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        dir
+    }
+
+    #[test]
+    fn test_sync_propagates_adds_edits_and_deletes() {
+        let remote = bare_repo();
+        let (a, b) = (Machine::new(remote.path()), Machine::new(remote.path()));
+
+        a.store
+            .add(
+                BookmarkInput::new("https://rust-lang.org")
+                    .with_title("Rust")
+                    .with_tags(vec!["lang".to_string()])
+                    .with_folder(vec!["Dev".to_string(), "Langs".to_string()]),
+            )
+            .unwrap();
+        a.store
+            .add(BookmarkInput::new("https://old.example").with_title("Old"))
+            .unwrap();
+        assert_eq!(a.sync(), SyncReport::default());
+
+        let report = b.sync();
+        assert_eq!(report.added, 2);
+        let rust = b.store.get(b.id("https://rust-lang.org")).unwrap().unwrap();
+        assert_eq!(rust.tags, vec!["lang"]);
+        let folder = b
+            .store
+            .get_folder(rust.folder_id.unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(folder.name, "Langs");
+
+        b.store.delete(b.id("https://old.example")).unwrap();
+        b.store
+            .update(
+                b.id("https://rust-lang.org"),
+                None,
+                Some("Rust!"),
+                None,
+                None,
+            )
+            .unwrap();
+        b.sync();
+
+        let report = a.sync();
+        assert_eq!((report.updated, report.removed), (1, 1));
+        assert_eq!(a.title("https://rust-lang.org"), "Rust!");
+        assert!(a
+            .store
+            .find_by_url("https://old.example", None)
+            .unwrap()
+            .is_none());
+        assert!(report.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_sync_merges_fields_and_reports_conflicts() {
+        let remote = bare_repo();
+        let (a, b) = (Machine::new(remote.path()), Machine::new(remote.path()));
+        a.store
+            .add(BookmarkInput::new("https://docs.rs").with_title("Docs"))
+            .unwrap();
+        a.store
+            .add(BookmarkInput::new("https://crates.io").with_title("Crates"))
+            .unwrap();
+        a.sync();
+        b.sync();
+
+        // Different fields of one bookmark merge cleanly
+        let tags = ["rust".to_string()];
+        a.store
+            .update(a.id("https://docs.rs"), None, None, Some(&tags), None)
+            .unwrap();
+        b.store
+            .update(b.id("https://docs.rs"), None, None, None, Some("API docs"))
+            .unwrap();
+        // The same field changed on both sides is a conflict
+        a.store
+            .update(a.id("https://crates.io"), None, Some("Mine"), None, None)
+            .unwrap();
+        b.store
+            .update(b.id("https://crates.io"), None, Some("Theirs"), None, None)
+            .unwrap();
+        a.sync();
+
+        let report = b.sync();
+        assert_eq!(
+            report.conflicts,
+            vec![SyncConflict {
+                url: "https://crates.io".to_string(),
+                fields: vec!["title".to_string()],
+                deleted: false,
+            }]
+        );
+        assert_eq!(b.title("https://crates.io"), "Theirs");
+        let docs = b.store.get(b.id("https://docs.rs")).unwrap().unwrap();
+        assert_eq!(docs.tags, vec!["rust"]);
+        assert_eq!(docs.description, "API docs");
+
+        // The merge result reaches the other machine
+        assert!(a.sync().conflicts.is_empty());
+        assert_eq!(a.title("https://crates.io"), "Theirs");
+        assert_eq!(
+            a.store
+                .get(a.id("https://docs.rs"))
+                .unwrap()
+                .unwrap()
+                .description,
+            "API docs"
+        );
+    }
+
+    #[test]
+    fn test_edit_wins_over_delete() {
+        let remote = bare_repo();
+        let (a, b) = (Machine::new(remote.path()), Machine::new(remote.path()));
+        a.store
+            .add(BookmarkInput::new("https://example.com").with_title("Example"))
+            .unwrap();
+        a.sync();
+        b.sync();
+
+        a.store.delete(a.id("https://example.com")).unwrap();
+        a.sync();
+        b.store
+            .update(
+                b.id("https://example.com"),
+                None,
+                Some("Edited"),
+                None,
+                None,
+            )
+            .unwrap();
+
+        let report = b.sync();
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(report.conflicts[0].deleted);
+        assert_eq!(b.title("https://example.com"), "Edited");
+
+        a.sync();
+        assert_eq!(a.title("https://example.com"), "Edited");
+    }
+
+    #[test]
+    fn test_file_is_sorted_by_url() {
+        let remote = bare_repo();
+        let a = Machine::new(remote.path());
+        for url in [
+            "https://b.example",
+            "https://a.example",
+            "https://c.example",
+        ] {
+            a.store
+                .add(BookmarkInput::new(url).with_tags(vec!["z".into(), "a".into()]))
+                .unwrap();
+        }
+        a.sync();
+
+        let text = std::fs::read_to_string(a.sync.dir.join(FILE_NAME)).unwrap();
+        let records = parse_records(&text).unwrap();
+        assert_eq!(format_records(&records), text);
+        let urls: Vec<&str> = records.keys().map(String::as_str).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://a.example",
+                "https://b.example",
+                "https://c.example"
+            ]
+        );
+        assert!(records.values().all(|r| r.tags == ["a", "z"]));
+
+        // Syncing again without changes adds no commit
+        let head = a.sync.rev_parse("HEAD").unwrap();
+        a.sync();
+        assert_eq!(a.sync.rev_parse("HEAD").unwrap(), head);
+    }
+}
@@ -0,0 +1,180 @@
+//! Detection of changes other programs make to the bookmark database.
+
+use crate::db::BookmarkStore;
+use crate::error::BookmarkError;
+use crate::models::BookmarkChanges;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Size and modification time of a file, `None` while it does not exist.
+type FileState = Option<(u64, SystemTime)>;
+
+fn file_state(path: &Path) -> FileState {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// Watches a store's database for commits made by other connections, such
+/// as buku, a sync job or a second MRunner window.
+///
+/// Checking is split in two so callers can poll often: [`Self::files_changed`]
+/// only looks at the database and WAL files on disk, and [`Self::changes`]
+/// then asks SQLite whether anyone else committed and works out which
+/// bookmarks changed from their ids and the change counters the database's
+/// triggers keep, without reading the bookmarks themselves.
+#[derive(Debug)]
+pub struct DatabaseWatcher {
+    /// The database file followed by its WAL.
+    files: [PathBuf; 2],
+    file_states: [FileState; 2],
+    data_version: i64,
+    ids: HashSet<i32>,
+    /// The highest change counter seen.
+    last_change: i64,
+    folders: u64,
+}
+
+impl DatabaseWatcher {
+    /// Starts watching `store` from its current contents.
+    pub fn new(store: &BookmarkStore) -> Result<Self, BookmarkError> {
+        let path = store
+            .path()
+            .ok_or_else(|| BookmarkError::InvalidFile("in-memory database".to_string()))?;
+        let mut wal = path.as_os_str().to_owned();
+        wal.push("-wal");
+
+        let files = [path.to_path_buf(), PathBuf::from(wal)];
+        Ok(Self {
+            file_states: [file_state(&files[0]), file_state(&files[1])],
+            files,
+            data_version: store.data_version()?,
+            ids: store.bookmark_ids()?,
+            last_change: store.last_change()?,
+            folders: store.folders_digest()?,
+        })
+    }
+
+    /// The watched database file.
+    pub fn path(&self) -> &Path {
+        &self.files[0]
+    }
+
+    /// Whether the database or WAL file changed on disk since the last call.
+    /// Writes made through the watched store count too.
+    pub fn files_changed(&mut self) -> bool {
+        let mut changed = false;
+        for (path, state) in self.files.iter().zip(self.file_states.iter_mut()) {
+            let current = file_state(path);
+            if current != *state {
+                *state = current;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// What other connections changed since the last call, or `None` if
+    /// they committed nothing. Changes made through `store` itself are not
+    /// reported.
+    pub fn changes(
+        &mut self,
+        store: &BookmarkStore,
+    ) -> Result<Option<BookmarkChanges>, BookmarkError> {
+        let version = store.data_version()?;
+        let ids = store.bookmark_ids()?;
+        let last_change = store.last_change()?;
+        let folders = store.folders_digest()?;
+
+        let external = version != self.data_version;
+        let old_ids = std::mem::replace(&mut self.ids, ids);
+        let since = std::mem::replace(&mut self.last_change, last_change);
+        let old_folders = std::mem::replace(&mut self.folders, folders);
+        self.data_version = version;
+        if !external {
+            return Ok(None);
+        }
+
+        let mut changes = BookmarkChanges {
+            added: self.ids.difference(&old_ids).copied().collect(),
+            removed: old_ids.difference(&self.ids).copied().collect(),
+            folders: old_folders != self.folders,
+            ..Default::default()
+        };
+        changes.updated = store
+            .changed_between(since, last_change)?
+            .into_iter()
+            .filter(|id| old_ids.contains(id) && self.ids.contains(id))
+            .collect();
+        changes.added.sort_unstable();
+        changes.updated.sort_unstable();
+        changes.removed.sort_unstable();
+
+        Ok(Some(changes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BookmarkInput;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reports_changes_from_other_connections_only() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("bookmarks.db");
+        let store = BookmarkStore::with_path(path.clone()).unwrap();
+        let kept = store
+            .add(BookmarkInput::new("https://kept.example"))
+            .unwrap();
+        let edited = store
+            .add(BookmarkInput::new("https://edited.example"))
+            .unwrap();
+        let deleted = store
+            .add(BookmarkInput::new("https://deleted.example"))
+            .unwrap();
+
+        let mut watcher = DatabaseWatcher::new(&store).unwrap();
+        assert_eq!(watcher.path(), path);
+        assert!(!watcher.files_changed());
+
+        // Our own writes touch the files but are not reported
+        store.update(kept, None, Some("Mine"), None, None).unwrap();
+        assert!(watcher.files_changed());
+        assert_eq!(watcher.changes(&store).unwrap(), None);
+
+        let other = BookmarkStore::with_path(path).unwrap();
+        let added = other
+            .add(BookmarkInput::new("https://added.example"))
+            .unwrap();
+        other
+            .update(edited, None, Some("Theirs"), None, None)
+            .unwrap();
+        other.delete(deleted).unwrap();
+        assert!(watcher.files_changed());
+        assert_eq!(
+            watcher.changes(&store).unwrap(),
+            Some(BookmarkChanges {
+                added: vec![added],
+                updated: vec![edited],
+                removed: vec![deleted],
+                folders: false,
+            })
+        );
+
+        let work = other.create_folder("Work", None).unwrap();
+        let changes = watcher.changes(&store).unwrap().unwrap();
+        assert!(changes.folders);
+        assert!(changes.added.is_empty() && changes.updated.is_empty());
+
+        // Edits to MRunner's own data count as well
+        other.move_bookmark(kept, Some(work), None).unwrap();
+        other.set_keyword(added, Some("add")).unwrap();
+        let changes = watcher.changes(&store).unwrap().unwrap();
+        assert_eq!(changes.updated, vec![kept, added]);
+        assert!(changes.added.is_empty() && changes.removed.is_empty());
+
+        assert_eq!(watcher.changes(&store).unwrap(), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Guarantees that a database shared with buku stays readable by buku:
+//! the `bookmarks` table layout, tag delimiters and flag bits written by
+//! MRunner must match what buku itself writes.
+
+use bookmarks::{BookmarkInput, BookmarkStore};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use tempfile::{tempdir, TempDir};
+
+const BUKU_FIXTURE: &str = include_str!("fixtures/buku.sql");
+
+fn buku_database() -> (PathBuf, TempDir) {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("bookmarks.db");
+    Connection::open(&path)
+        .unwrap()
+        .execute_batch(BUKU_FIXTURE)
+        .unwrap();
+    (path, dir)
+}
+
+/// Reads rows the way buku does: `SELECT *` unpacked by position. Reading
+/// text columns as `String` also asserts none of them is NULL.
+fn buku_rows(path: &Path) -> Vec<(i64, String, String, String, String, i64)> {
+    let conn = Connection::open(path).unwrap();
+    let mut stmt = conn.prepare("SELECT * FROM bookmarks ORDER BY id").unwrap();
+    assert_eq!(stmt.column_count(), 6, "buku expects exactly six columns");
+
+    stmt.query_map([], |row| {
+        Ok((
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+        ))
+    })
+    .unwrap()
+    .map(|r| r.unwrap())
+    .collect()
+}
+
+fn assert_buku_tags(tags: &str) {
+    assert!(tags.starts_with(',') && tags.ends_with(','), "{:?}", tags);
+    assert!(!tags.contains(",,"), "{:?}", tags);
+    assert!(!tags.contains(", ") && !tags.contains(" ,"), "{:?}", tags);
+}
+
+#[test]
+fn test_reads_buku_database() {
+    let (path, _dir) = buku_database();
+    let store = BookmarkStore::with_path(path).unwrap();
+
+    let docs = store.get(2).unwrap().unwrap();
+    assert_eq!(docs.title, "My docs");
+    assert_eq!(docs.tags, vec!["rust"]);
+    assert!(docs.immutable_title);
+
+    let buku = store.get(1).unwrap().unwrap();
+    assert_eq!(buku.tags, vec!["bookmarks", "cli"]);
+    assert!(!buku.immutable_title);
+
+    assert!(store.get(3).unwrap().unwrap().tags.is_empty());
+    assert_eq!(store.search("dashboard", None, false).unwrap().len(), 1);
+}
+
+#[test]
+fn test_opening_keeps_bookmarks_table_untouched() {
+    let (path, _dir) = buku_database();
+    let before = buku_rows(&path);
+
+    BookmarkStore::with_path(path.clone()).unwrap();
+
+    assert_eq!(buku_rows(&path), before);
+}
+
+#[test]
+fn test_writes_stay_in_buku_format() {
+    let (path, _dir) = buku_database();
+    let store = BookmarkStore::with_path(path.clone()).unwrap();
+
+    let id = store
+        .add(
+            BookmarkInput::new("https://new.example")
+                .with_title("New")
+                .with_tags(vec![" spaced ".into(), "a,b".into(), "a".into(), "".into()]),
+        )
+        .unwrap();
+    store
+        .update(2, None, None, Some(&["rust".into(), "docs".into()]), None)
+        .unwrap();
+    store.rename_tag("ops", "operations").unwrap();
+    store.delete_tag("cli").unwrap();
+
+    let rows = buku_rows(&path);
+    for (_, url, _, tags, _, _) in &rows {
+        assert!(!url.is_empty());
+        assert_buku_tags(tags);
+    }
+
+    let new = rows.iter().find(|r| r.0 == id as i64).unwrap();
+    assert_eq!(new.3, ",spaced,a,b,");
+    assert_eq!(new.5, 0);
+
+    // Flags survive edits made through MRunner
+    let docs = rows.iter().find(|r| r.0 == 2).unwrap();
+    assert_eq!(docs.3, ",rust,docs,");
+    assert_eq!(docs.5, 1);
+
+    let dashboard = rows.iter().find(|r| r.0 == 4).unwrap();
+    assert_eq!(dashboard.3, ",work/infra,operations,");
+    assert_eq!(rows.iter().find(|r| r.0 == 1).unwrap().3, ",bookmarks,");
+}
+
+#[test]
+fn test_immutable_title_flag_preserves_other_bits() {
+    let (path, _dir) = buku_database();
+    let store = BookmarkStore::with_path(path.clone()).unwrap();
+
+    store.set_immutable_title(4, false).unwrap();
+    store.set_immutable_title(3, true).unwrap();
+
+    let rows = buku_rows(&path);
+    assert_eq!(rows.iter().find(|r| r.0 == 4).unwrap().5, 2);
+    assert_eq!(rows.iter().find(|r| r.0 == 3).unwrap().5, 1);
+
+    let id = store
+        .add(BookmarkInput::new("https://manual.example").with_immutable_title(true))
+        .unwrap();
+    assert!(store.get(id).unwrap().unwrap().immutable_title);
+}
+
+#[test]
+fn test_rows_written_by_buku_after_mrunner_are_visible() {
+    let (path, _dir) = buku_database();
+    let store = BookmarkStore::with_path(path.clone()).unwrap();
+
+    // buku inserts with its own statement while MRunner has the database open
+    Connection::open(&path)
+        .unwrap()
+        .execute(
+            "INSERT INTO bookmarks(URL, metadata, tags, desc, flags) VALUES (?, ?, ?, ?, ?)",
+            ("https://from-buku.example", "From buku", ",cli,", "", 0),
+        )
+        .unwrap();
+
+    let found = store.search("from buku", None, false).unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(found[0].added_at.is_some());
+}
@@ -0,0 +1,142 @@
+//! Runs the `mrunner-bookmarks` binary against a scratch database.
+
+use serde_json::Value;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::tempdir;
+
+fn cli(db: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mrunner-bookmarks"))
+        .arg("--db")
+        .arg(db)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn json(db: &Path, args: &[&str]) -> Value {
+    let output = cli(db, &[&["--json"], args].concat());
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn urls(bookmarks: &Value) -> Vec<&str> {
+    bookmarks
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| b["url"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_add_search_list_and_rename_tags() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("bookmarks.db");
+
+    let added = json(
+        &db,
+        &[
+            "add",
+            "https://docs.rs",
+            "--title",
+            "Docs",
+            "--tag",
+            "rust",
+            "--tag=docs",
+            "--folder",
+            "Dev/Rust",
+        ],
+    );
+    assert_eq!(added["title"], "Docs");
+    assert_eq!(added["tags"], serde_json::json!(["rust", "docs"]));
+
+    let output = cli(&db, &["add", "https://crates.io", "--title", "Crates"]);
+    assert!(output.status.success());
+    let id: i32 = String::from_utf8(output.stdout)
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    assert!(id > 0);
+
+    assert_eq!(
+        urls(&json(&db, &["search", "docs"])),
+        vec!["https://docs.rs"]
+    );
+    assert_eq!(
+        urls(&json(&db, &["list", "--sort", "title"])),
+        vec!["https://crates.io", "https://docs.rs"]
+    );
+    assert_eq!(
+        json(&db, &["list", "--limit", "1"])
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+
+    assert!(cli(&db, &["tag", "rename", "rust", "rustlang"])
+        .status
+        .success());
+    assert_eq!(
+        urls(&json(&db, &["search", "docs", "--tag", "rustlang"])),
+        vec!["https://docs.rs"]
+    );
+
+    // Plain output is one tab-separated line per bookmark
+    let output = cli(&db, &["list", "--sort", "url"]);
+    let text = String::from_utf8(output.stdout).unwrap();
+    let first: Vec<&str> = text.lines().next().unwrap().split('\t').collect();
+    assert_eq!(first[1..], ["Crates", "https://crates.io", ""]);
+}
+
+#[test]
+fn test_export_and_import_round_trip() {
+    let dir = tempdir().unwrap();
+    let (source, target) = (dir.path().join("a.db"), dir.path().join("b.db"));
+    let file = dir.path().join("bookmarks.html");
+
+    cli(
+        &source,
+        &["add", "https://example.com", "--title", "Example"],
+    );
+    cli(&source, &["add", "https://example.org", "--tag", "org"]);
+    assert_eq!(
+        json(&source, &["export", file.to_str().unwrap()])["exported"],
+        2
+    );
+
+    let report = json(&target, &["import", file.to_str().unwrap()]);
+    assert_eq!(report["added"], 2);
+    assert_eq!(
+        urls(&json(&target, &["list", "--sort", "url"])),
+        vec!["https://example.com", "https://example.org"]
+    );
+}
+
+#[test]
+fn test_usage_errors() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("bookmarks.db");
+
+    for args in [
+        &[][..],
+        &["frobnicate"],
+        &["add"],
+        &["list", "--sort", "sideways"],
+        &["search", "x", "--title", "y"],
+    ] {
+        let output = cli(&db, args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Usage:"));
+    }
+
+    let output = cli(&db, &["open", "999"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not found"));
+}
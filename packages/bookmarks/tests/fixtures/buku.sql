@@ -0,0 +1,6 @@
+-- Database as created and filled by buku 4.x (`buku --nostdin -a ...`)
+CREATE TABLE if not exists bookmarks (id integer PRIMARY KEY, URL text NOT NULL UNIQUE, metadata text default '', tags text default ',', desc text default '', flags integer default 0);
+INSERT INTO bookmarks VALUES(1,'https://github.com/jarun/buku','GitHub - jarun/buku: bookmark manager',',bookmarks,cli,','Powerful command-line bookmark manager.',0);
+INSERT INTO bookmarks VALUES(2,'https://docs.rs','My docs',',rust,',' ',1);
+INSERT INTO bookmarks VALUES(3,'https://example.com','',',','',0);
+INSERT INTO bookmarks VALUES(4,'https://internal.example','Dashboard',',work/infra,ops,','',3);
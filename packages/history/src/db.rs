@@ -0,0 +1,340 @@
+use crate::error::HistoryError;
+use crate::models::{Boost, LaunchKind};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use storage::{frecency, unix_now};
+
+/// Ordered schema migrations, applied by [`storage::migrate`].
+const MIGRATIONS: &[&str] = &[
+    // 1: one row per launch, with what had been typed when it happened
+    "CREATE TABLE IF NOT EXISTS launches (
+        id INTEGER PRIMARY KEY,
+        command_id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        query TEXT NOT NULL DEFAULT '',
+        launched_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS launches_command
+        ON launches (command_id, kind, launched_at);",
+];
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Launches kept; older ones are dropped as new ones are recorded. Far more
+/// than frecency samples per result, so only results not launched in a long
+/// time lose their history.
+const MAX_LAUNCHES: i64 = 5000;
+
+/// Queries are stored up to this many characters. Longer ones rarely repeat
+/// and the prefix already tells the results apart.
+const MAX_QUERY_CHARS: usize = 32;
+
+/// Boosts returned for one query.
+const MAX_BOOSTS: usize = 100;
+
+/// Lowercases and trims `query`, keeping its first [`MAX_QUERY_CHARS`]
+/// characters.
+fn normalize_query(query: &str) -> String {
+    query
+        .trim()
+        .to_lowercase()
+        .chars()
+        .take(MAX_QUERY_CHARS)
+        .collect()
+}
+
+fn parse_kind(kind: &str) -> LaunchKind {
+    match kind {
+        "application" => LaunchKind::Application,
+        "bookmark" => LaunchKind::Bookmark,
+        "plugin" => LaunchKind::Plugin,
+        "folder" => LaunchKind::Folder,
+        _ => LaunchKind::Command,
+    }
+}
+
+/// What the user launched from the launcher, and what they had typed, for
+/// every kind of result.
+///
+/// The history is `Send` but not `Sync`: share a long-lived instance between
+/// threads behind a `Mutex` instead of reopening it for every call.
+pub struct LaunchHistory {
+    conn: Connection,
+}
+
+impl LaunchHistory {
+    pub fn new() -> Result<Self, HistoryError> {
+        Self::with_path(Self::get_db_path()?)
+    }
+
+    /// Opens (creating if needed) the database at `path`.
+    pub fn with_path(path: PathBuf) -> Result<Self, HistoryError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Self::from_connection(Connection::open(&path)?)
+    }
+
+    /// A history kept in memory and lost when dropped.
+    pub fn in_memory() -> Result<Self, HistoryError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self, HistoryError> {
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+
+        let mut history = Self { conn };
+        history.migrate()?;
+
+        Ok(history)
+    }
+
+    /// Where [`Self::new`] keeps the database, next to the bookmarks in
+    /// MRunner's data directory.
+    pub fn get_db_path() -> Result<PathBuf, HistoryError> {
+        storage::data_dir()
+            .map(|p| p.join("history.db"))
+            .ok_or(HistoryError::DataDirNotFound)
+    }
+
+    /// The database file, `None` for in-memory histories.
+    pub fn path(&self) -> Option<&Path> {
+        self.conn.path().filter(|p| !p.is_empty()).map(Path::new)
+    }
+
+    pub const SCHEMA_VERSION: i32 = MIGRATIONS.len() as i32;
+
+    pub fn schema_version(&self) -> Result<i32, HistoryError> {
+        Ok(storage::schema_version(&self.conn)?)
+    }
+
+    fn migrate(&mut self) -> Result<(), HistoryError> {
+        Ok(storage::migrate(&mut self.conn, MIGRATIONS)?)
+    }
+
+    /// Records that `command_id` was launched after typing `query`.
+    pub fn record(
+        &mut self,
+        command_id: &str,
+        kind: LaunchKind,
+        query: &str,
+    ) -> Result<(), HistoryError> {
+        self.record_at(command_id, kind, query, unix_now())
+    }
+
+    fn record_at(
+        &mut self,
+        command_id: &str,
+        kind: LaunchKind,
+        query: &str,
+        launched_at: i64,
+    ) -> Result<(), HistoryError> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO launches (command_id, kind, query, launched_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            (
+                command_id,
+                kind.as_str(),
+                normalize_query(query),
+                launched_at,
+            ),
+        )?;
+        tx.execute(
+            "DELETE FROM launches WHERE id <= (SELECT max(id) FROM launches) - ?1",
+            [MAX_LAUNCHES],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Frecency boosts for the results launched on the way to or from
+    /// `query`: launches whose stored query is a prefix of `query`, or has
+    /// `query` as a prefix, so typing "fi" or "firef" both lift what was
+    /// launched after typing "fire". Scores are relative to the best result,
+    /// which gets `1.0`; results never launched get no boost. Best first.
+    pub fn boosts(&self, query: &str) -> Result<Vec<Boost>, HistoryError> {
+        self.boosts_at(query, unix_now())
+    }
+
+    fn boosts_at(&self, query: &str, now: i64) -> Result<Vec<Boost>, HistoryError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT command_id, kind, launched_at FROM launches
+             WHERE substr(?1, 1, length(query)) = query
+                OR substr(query, 1, length(?1)) = ?1
+             ORDER BY launched_at DESC",
+        )?;
+        let rows = stmt.query_map([normalize_query(query)], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+
+        // Newest first, so each result's first launches are its sample
+        let mut launches: HashMap<(String, String), Vec<i64>> = HashMap::new();
+        for row in rows {
+            let (command_id, kind, launched_at) = row?;
+            launches
+                .entry((command_id, kind))
+                .or_default()
+                .push(launched_at);
+        }
+
+        let mut boosts: Vec<Boost> = launches
+            .into_iter()
+            .map(|((command_id, kind), times)| Boost {
+                score: frecency::score(times.len() as i64, &times, now),
+                kind: parse_kind(&kind),
+                command_id,
+            })
+            .collect();
+        boosts.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.command_id.cmp(&b.command_id))
+        });
+        boosts.truncate(MAX_BOOSTS);
+
+        if let Some(best) = boosts.first().map(|b| b.score).filter(|s| *s > 0.0) {
+            for boost in &mut boosts {
+                boost.score /= best;
+            }
+        }
+
+        Ok(boosts)
+    }
+
+    /// Forgets every launch of `command_id`, e.g. once the result is gone.
+    pub fn forget(&mut self, command_id: &str) -> Result<(), HistoryError> {
+        self.conn
+            .execute("DELETE FROM launches WHERE command_id = ?1", [command_id])?;
+        Ok(())
+    }
+
+    /// Forgets every launch.
+    pub fn clear(&mut self) -> Result<(), HistoryError> {
+        self.conn.execute("DELETE FROM launches", [])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn ids(boosts: &[Boost]) -> Vec<&str> {
+        boosts.iter().map(|b| b.command_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_creates_database_in_parent_dirs() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("nested").join("history.db");
+        let history = LaunchHistory::with_path(path.clone()).unwrap();
+        assert_eq!(history.path(), Some(path.as_path()));
+        assert_eq!(
+            history.schema_version().unwrap(),
+            LaunchHistory::SCHEMA_VERSION
+        );
+    }
+
+    #[test]
+    fn test_boosts_match_query_prefixes() {
+        let mut history = LaunchHistory::in_memory().unwrap();
+        let app = LaunchKind::Application;
+        history.record_at("firefox", app, " Fire ", NOW).unwrap();
+        history
+            .record_at("files", LaunchKind::Folder, "fil", NOW)
+            .unwrap();
+        history.record_at("terminal", app, "term", NOW).unwrap();
+
+        // Both shorter and longer queries along the same prefix match
+        assert_eq!(
+            ids(&history.boosts_at("f", NOW).unwrap()),
+            ["files", "firefox"]
+        );
+        assert_eq!(ids(&history.boosts_at("firef", NOW).unwrap()), ["firefox"]);
+        assert_eq!(ids(&history.boosts_at("FIRE", NOW).unwrap()), ["firefox"]);
+        assert!(history.boosts_at("fix", NOW).unwrap().is_empty());
+
+        // Everything launched counts for the empty query
+        assert_eq!(history.boosts_at("", NOW).unwrap().len(), 3);
+        assert_eq!(
+            history.boosts_at("f", NOW).unwrap()[0].kind,
+            LaunchKind::Folder
+        );
+    }
+
+    #[test]
+    fn test_boosts_rank_by_frecency() {
+        let mut history = LaunchHistory::in_memory().unwrap();
+        let kind = LaunchKind::Bookmark;
+        for _ in 0..3 {
+            history
+                .record_at("often", kind, "o", NOW - 200 * DAY)
+                .unwrap();
+        }
+        history.record_at("recent", kind, "", NOW - DAY).unwrap();
+
+        // One recent launch (100) beats three old ones (3 × 10)
+        let boosts = history.boosts_at("o", NOW).unwrap();
+        assert_eq!(ids(&boosts), ["recent", "often"]);
+        assert_eq!(boosts[0].score, 1.0);
+        assert_eq!(boosts[1].score, 0.3);
+    }
+
+    #[test]
+    fn test_same_id_of_other_kind_is_another_result() {
+        let mut history = LaunchHistory::in_memory().unwrap();
+        history
+            .record_at("1", LaunchKind::Bookmark, "", NOW)
+            .unwrap();
+        history.record_at("1", LaunchKind::Plugin, "", NOW).unwrap();
+        assert_eq!(history.boosts_at("", NOW).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_prunes_oldest_launches() {
+        let mut history = LaunchHistory::in_memory().unwrap();
+        let kind = LaunchKind::Command;
+        history.record_at("oldest", kind, "", NOW).unwrap();
+        for _ in 0..MAX_LAUNCHES {
+            history.record_at("newer", kind, "", NOW).unwrap();
+        }
+        let count: i64 = history
+            .conn
+            .query_row("SELECT count(*) FROM launches", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, MAX_LAUNCHES);
+        assert_eq!(ids(&history.boosts_at("", NOW).unwrap()), ["newer"]);
+    }
+
+    #[test]
+    fn test_forget_and_clear() {
+        let mut history = LaunchHistory::in_memory().unwrap();
+        let kind = LaunchKind::Application;
+        history.record_at("a", kind, "", NOW).unwrap();
+        history.record_at("b", kind, "", NOW).unwrap();
+
+        history.forget("a").unwrap();
+        assert_eq!(ids(&history.boosts_at("", NOW).unwrap()), ["b"]);
+        history.clear().unwrap();
+        assert!(history.boosts_at("", NOW).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_long_queries_are_truncated() {
+        let query = "x".repeat(100);
+        assert_eq!(normalize_query(&query).chars().count(), MAX_QUERY_CHARS);
+    }
+}
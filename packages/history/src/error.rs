@@ -0,0 +1,27 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Could not find data directory")]
+    DataDirNotFound,
+
+    #[error("Database schema version {found} is newer than supported version {supported}")]
+    SchemaTooNew { found: i32, supported: i32 },
+}
+
+impl From<storage::MigrationError> for HistoryError {
+    fn from(err: storage::MigrationError) -> Self {
+        match err {
+            storage::MigrationError::Database(e) => HistoryError::Database(e),
+            storage::MigrationError::SchemaTooNew { found, supported } => {
+                HistoryError::SchemaTooNew { found, supported }
+            }
+        }
+    }
+}
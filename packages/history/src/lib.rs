@@ -0,0 +1,7 @@
+mod db;
+mod error;
+mod models;
+
+pub use db::LaunchHistory;
+pub use error::HistoryError;
+pub use models::{Boost, LaunchKind};
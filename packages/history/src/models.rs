@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// What a launched result was, so the history can tell apart results of
+/// different sources that happen to share an id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LaunchKind {
+    Application,
+    Bookmark,
+    Plugin,
+    Folder,
+    Command,
+}
+
+impl LaunchKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Application => "application",
+            Self::Bookmark => "bookmark",
+            Self::Plugin => "plugin",
+            Self::Folder => "folder",
+            Self::Command => "command",
+        }
+    }
+}
+
+/// How much to lift a result in the ranking for a query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Boost {
+    pub command_id: String,
+    pub kind: LaunchKind,
+    /// Frecency relative to the best result for the query, in `0.0..=1.0`.
+    pub score: f64,
+}
@@ -0,0 +1,62 @@
+//! Firefox-style frecency: every item (a bookmark, a launched result) keeps a
+//! sample of its most recent visits, each weighted by how long ago it
+//! happened, and the average weight is scaled by the total number of visits.
+
+/// Number of most recent visits sampled when computing a score.
+pub const SAMPLE_SIZE: usize = 10;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// Age buckets (upper bound in days, weight), newest first.
+const BUCKETS: &[(i64, f64)] = &[(4, 100.0), (14, 70.0), (31, 50.0), (90, 30.0)];
+const OLD_VISIT_WEIGHT: f64 = 10.0;
+
+fn visit_weight(visited_at: i64, now: i64) -> f64 {
+    let age_days = (now - visited_at).max(0) / DAY;
+    BUCKETS
+        .iter()
+        .find(|(max_days, _)| age_days <= *max_days)
+        .map(|(_, weight)| *weight)
+        .unwrap_or(OLD_VISIT_WEIGHT)
+}
+
+/// Scores an item from its total visit count and the timestamps (unix
+/// seconds) of its most recent visits. Only the first [`SAMPLE_SIZE`]
+/// timestamps are considered.
+pub fn score(visit_count: i64, recent_visits: &[i64], now: i64) -> f64 {
+    if visit_count <= 0 || recent_visits.is_empty() {
+        return 0.0;
+    }
+
+    let sample = &recent_visits[..recent_visits.len().min(SAMPLE_SIZE)];
+    let total: f64 = sample.iter().map(|&t| visit_weight(t, now)).sum();
+
+    (visit_count as f64 * total / sample.len() as f64).ceil()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn test_no_visits_scores_zero() {
+        assert_eq!(score(0, &[], NOW), 0.0);
+    }
+
+    #[test]
+    fn test_recent_visits_outrank_old_ones() {
+        let recent = score(1, &[NOW - DAY], NOW);
+        let old = score(1, &[NOW - 200 * DAY], NOW);
+        assert_eq!(recent, 100.0);
+        assert_eq!(old, 10.0);
+    }
+
+    #[test]
+    fn test_visit_count_scales_sampled_average() {
+        // 20 visits, sample is one 2-day-old and one 20-day-old visit
+        let visits = [NOW - 2 * DAY, NOW - 20 * DAY];
+        assert_eq!(score(20, &visits, NOW), 20.0 * (100.0 + 50.0) / 2.0);
+    }
+}
@@ -0,0 +1,26 @@
+pub mod frecency;
+mod migrate;
+
+pub use migrate::{migrate, schema_version, MigrationError};
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// MRunner's data directory, holding its databases and cached files:
+/// `$XDG_DATA_HOME/mrunner` (`%APPDATA%\mrunner` on Windows).
+pub fn data_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let dir = dirs::data_dir();
+
+    #[cfg(not(target_os = "windows"))]
+    let dir = dirs::data_local_dir();
+
+    dir.map(|p| p.join("mrunner"))
+}
+
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,76 @@
+use rusqlite::Connection;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MigrationError {
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Database schema version {found} is newer than supported version {supported}")]
+    SchemaTooNew { found: i32, supported: i32 },
+}
+
+pub fn schema_version(conn: &Connection) -> rusqlite::Result<i32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Applies `migrations` the database has not seen yet. Migration `n`
+/// (1-based) upgrades a database from `user_version = n - 1` to
+/// `user_version = n`, each in its own transaction. Never edit or reorder
+/// an entry once released; append a new one instead.
+pub fn migrate(conn: &mut Connection, migrations: &[&str]) -> Result<(), MigrationError> {
+    let current = schema_version(conn)?;
+    let supported = migrations.len() as i32;
+
+    if current > supported {
+        return Err(MigrationError::SchemaTooNew {
+            found: current,
+            supported,
+        });
+    }
+
+    for (index, sql) in migrations.iter().enumerate().skip(current as usize) {
+        let version = index as i32 + 1;
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        // PRAGMA does not accept bound parameters
+        tx.execute_batch(&format!("PRAGMA user_version = {}", version))?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIGRATIONS: &[&str] = &[
+        "CREATE TABLE a (id INTEGER PRIMARY KEY);",
+        "CREATE TABLE b (id INTEGER PRIMARY KEY);",
+    ];
+
+    #[test]
+    fn test_applies_only_new_migrations() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, &MIGRATIONS[..1]).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 1);
+
+        migrate(&mut conn, MIGRATIONS).unwrap();
+        migrate(&mut conn, MIGRATIONS).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_refuses_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, MIGRATIONS).unwrap();
+        assert!(matches!(
+            migrate(&mut conn, &MIGRATIONS[..1]),
+            Err(MigrationError::SchemaTooNew {
+                found: 2,
+                supported: 1
+            })
+        ));
+    }
+}